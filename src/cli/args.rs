@@ -1,16 +1,73 @@
+use crate::error::ERROR_INDICATOR;
+use crate::passes::lint::{Level, LintLevels};
+
 #[derive(Debug)]
 pub struct RuntimeOptions {
     pub debug_mode: bool,
     pub help: bool,
     pub quiet: bool,
+    pub lints: LintLevels,
+    pub optimize: bool,
+    /// Print the tree that would run instead of running it
+    pub emit_ast: bool,
     // more options coming soon
 }
 
-pub fn parse_arg(arg: &str, options: &mut RuntimeOptions) {
+/// Applies flag `arg`, taking its value from `rest` if it has one
+pub fn parse_arg<'a>(
+    arg: &str,
+    rest: &mut impl Iterator<Item = &'a String>,
+    options: &mut RuntimeOptions,
+) {
     match arg {
         "-d" | "--debug" => options.debug_mode = true,
         "-h" | "--help" => options.help = true,
         "-q" | "--quiet" => options.quiet = true,
-        _ => unimplemented!(),
+        "-O" | "--optimize" => options.optimize = true,
+        "--emit" => match rest.next() {
+            Some(what) => parse_emit(what, options),
+            None => {
+                eprintln!(
+                    "{} \x1b[1mExpected what to emit after `--emit`, e.g. `--emit ast`\x1b[0m",
+                    ERROR_INDICATOR
+                );
+                std::process::exit(1);
+            }
+        },
+        _ if arg.starts_with("--emit=") => parse_emit(&arg["--emit=".len()..], options),
+        _ if arg.contains('=') => parse_lint_arg(arg, options),
+        _ => unknown_flag(arg),
+    }
+}
+
+fn unknown_flag(arg: &str) -> ! {
+    eprintln!("{} \x1b[1mUnknown flag `{}`\x1b[0m", ERROR_INDICATOR, arg);
+    std::process::exit(1);
+}
+
+/// `--allow=lint`, `--warn=lint` or `--deny=lint`
+fn parse_lint_arg(arg: &str, options: &mut RuntimeOptions) {
+    let (flag, lint) = arg.split_once('=').unwrap();
+    let level = match flag.strip_prefix("--").and_then(Level::from_string) {
+        Some(level) => level,
+        None => unknown_flag(arg),
+    };
+    if !options.lints.set(lint, level) {
+        eprintln!("{} \x1b[1mUnknown lint `{}`\x1b[0m", ERROR_INDICATOR, lint);
+        std::process::exit(1);
+    }
+}
+
+/// `--emit ast`
+fn parse_emit(what: &str, options: &mut RuntimeOptions) {
+    match what {
+        "ast" => options.emit_ast = true,
+        _ => {
+            eprintln!(
+                "{} \x1b[1mCannot emit `{}`, only `ast` can be emitted\x1b[0m",
+                ERROR_INDICATOR, what
+            );
+            std::process::exit(1);
+        }
     }
 }
@@ -1,24 +1,112 @@
+use crate::syntax::span::Span;
+
 pub const ERROR_INDICATOR: &str = "\x1b[1m[\x1b[0m\x1b[1;31merror\x1b[0m\x1b[1m]:\x1b[0m";
+pub const WARNING_INDICATOR: &str = "\x1b[1m[\x1b[0m\x1b[1;33mwarning\x1b[0m\x1b[1m]:\x1b[0m";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VeloError {
-    pub line: usize,
+    pub span: Span,
     pub message: String,
     pub error_type: ErrorType,
+    /// What a script sees as the `kind` of the error when it catches it,
+    /// the name of the error type unless something more specific was given
+    pub kind: String,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ErrorType {
     ParseError,
     RuntimeError,
+    PackageError,
+    /// A name that cannot be used where it is, found before anything runs
+    NameError,
+    /// Found by the type checker before anything runs
+    TypeError,
+    /// A lint set to `deny`
+    LintError,
+    /// Reported, but does not stop anything
+    Warning,
+}
+
+impl ErrorType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorType::ParseError => "ParseError",
+            ErrorType::RuntimeError => "RuntimeError",
+            ErrorType::PackageError => "PackageError",
+            ErrorType::NameError => "NameError",
+            ErrorType::TypeError => "TypeError",
+            ErrorType::LintError => "LintError",
+            ErrorType::Warning => "Warning",
+        }
+    }
 }
 
 impl VeloError {
-    pub fn error(line: usize, message: &str, error_type: ErrorType) -> Self {
+    pub fn error(span: Span, message: &str, error_type: ErrorType) -> Self {
         Self {
-            line,
+            span,
             message: message.to_string(),
+            kind: error_type.name().to_string(),
             error_type,
         }
     }
+
+    pub fn with_kind(mut self, kind: &str) -> Self {
+        self.kind = kind.to_string();
+        self
+    }
+
+    /// Formats the error for the terminal. When the source is available the
+    /// offending line is printed with the span underlined.
+    pub fn render(&self, source: Option<&str>, filename: &str) -> String {
+        let message = if self.kind == self.error_type.name() {
+            self.message.clone()
+        } else {
+            format!("{}: {}", self.kind, self.message)
+        };
+        let (indicator, color) = match self.error_type {
+            ErrorType::Warning => (WARNING_INDICATOR, "1;33"),
+            _ => (ERROR_INDICATOR, "1;31"),
+        };
+        let mut out = format!("{} \x1b[1m{}\x1b[0m", indicator, message);
+
+        if self.span.line == 0 {
+            return out;
+        }
+
+        if self.span.column == 0 {
+            out.push_str(&format!("\n  --> {}:{}", filename, self.span.line));
+            return out;
+        }
+
+        out.push_str(&format!(
+            "\n  --> {}:{}:{}",
+            filename, self.span.line, self.span.column
+        ));
+
+        if let Some(text) = source.and_then(|s| s.lines().nth(self.span.line - 1)) {
+            let gutter = self.span.line.to_string();
+            let pad = " ".repeat(gutter.len());
+            let width = if self.span.is_empty() {
+                1
+            } else {
+                let remaining = text.chars().count().saturating_sub(self.span.column - 1);
+                (self.span.end - self.span.start).clamp(1, remaining.max(1))
+            };
+
+            out.push_str(&format!("\n {} |", pad));
+            out.push_str(&format!("\n {} | {}", gutter, text));
+            out.push_str(&format!(
+                "\n {} | {}\x1b[{}m{}\x1b[0m",
+                pad,
+                " ".repeat(self.span.column - 1),
+                color,
+                "^".repeat(width)
+            ));
+        }
+
+        out
+    }
 }
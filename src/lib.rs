@@ -0,0 +1,72 @@
+//! Velo as a library, for applications that embed the interpreter. The
+//! `velo` command is built on the same modules.
+//!
+//! A host runs a program in an `Environment`, then reads back what it
+//! declared. Annotations Velo does not know are kept for this:
+//!
+//! ```
+//! use velo::runtime::environment::Environment;
+//! use velo::runtime::interpreter::evaluate;
+//! use velo::syntax::ast::ExpressionKind;
+//! use velo::syntax::parse::parse_source;
+//!
+//! let nodes = parse_source("@route(\"/users\") fun users() {}").unwrap();
+//! let mut env = Environment::init();
+//! evaluate(nodes, false, &mut env).unwrap();
+//!
+//! let annotations = env.annotations("users").unwrap();
+//! assert_eq!(annotations[0].name, "route");
+//! assert!(matches!(
+//!     &annotations[0].args[0].kind,
+//!     ExpressionKind::StringLiteral(path) if path == "/users"
+//! ));
+//! ```
+//!
+//! Values go the other way with `Environment::define`. A program can index
+//! a map and loop over its keys, and passes a native handle around as it is:
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//!
+//! use velo::runtime::environment::Environment;
+//! use velo::runtime::interpreter::evaluate;
+//! use velo::runtime::value::{NativeHandle, Value};
+//! use velo::syntax::parse::parse_source;
+//!
+//! struct Connection {
+//!     port: u16,
+//! }
+//!
+//! let mut env = Environment::init();
+//! let host = Value::String("localhost".to_string());
+//! let config = BTreeMap::from([("host".to_string(), host.clone())]);
+//! env.define("config", Value::Map(config));
+//! let connection = NativeHandle::new("connection", Connection { port: 5432 });
+//! env.define("db", Value::Native(connection));
+//!
+//! let source = r#"
+//!     fun first_key() > string {
+//!         for key in config { return key; }
+//!         return "";
+//!     }
+//!     [config["host"], first_key(), db];
+//! "#;
+//! let nodes = parse_source(source).unwrap();
+//! let Some(Value::Array(items)) = evaluate(nodes, false, &mut env).unwrap() else {
+//!     panic!("expected an array");
+//! };
+//! assert_eq!(items[0], host);
+//! assert_eq!(items[1], Value::String("host".to_string()));
+//! let Value::Native(handle) = &items[2] else {
+//!     panic!("expected a native handle");
+//! };
+//! assert_eq!(handle.data.downcast_ref::<Connection>().unwrap().port, 5432);
+//! ```
+
+pub mod cli;
+pub mod error;
+pub mod package;
+pub mod passes;
+pub mod runtime;
+pub mod syntax;
+pub mod utils;
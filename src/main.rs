@@ -1,26 +1,48 @@
 use std::io::{self, Write};
+use std::path::Path;
 use std::process;
-use std::{env, fs};
+use std::rc::Rc;
+use std::{env, fs, thread};
 
-use cli::args::*;
-use runtime::interpreter::evaluate;
-use syntax::lexer::Lexer;
-use syntax::parse::Parser;
+use velo::cli::args::*;
+use velo::error::ErrorType::Warning;
+use velo::error::ERROR_INDICATOR;
+use velo::package::{lock, PackageGraph, MANIFEST_NAME};
+use velo::passes::analyze;
+use velo::passes::lint::{lint_program, LintLevels};
+use velo::passes::optimize::optimize_program;
+use velo::runtime::environment::Environment;
+use velo::runtime::interpreter::{evaluate, run_function};
+use velo::runtime::value::Value;
+use velo::syntax::ast::{Ast, Statement, StatementKind};
+use velo::syntax::parse::parse_source;
+use velo::syntax::printer::{format_source, print_program};
 
-use crate::runtime::environment::Environment;
-
-mod cli;
-mod error;
-mod runtime;
-mod syntax;
-mod utils;
+/// The stack of the thread that runs the command. Each call a program
+/// makes takes some of it, and this is enough for `MAX_DEPTH` calls inside
+/// each other even in a debug build.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
+    let command = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_command)
+        .expect("the thread running the command starts");
+    if command.join().is_err() {
+        // The panic has been reported already
+        process::exit(101);
+    }
+}
+
+fn run_command() {
     let args: Vec<String> = env::args().collect();
     let mut options = RuntimeOptions {
         debug_mode: false,
         help: false,
         quiet: false,
+        lints: LintLevels::default(),
+        optimize: false,
+        emit_ast: false,
     };
     if args.len() == 1 {
         repl(options)
@@ -29,19 +51,80 @@ fn main() {
         let first_arg = args.get(1).unwrap();
         let is_filename = first_arg.contains('.');
 
-        for arg in args.iter().skip(2) {
-            parse_arg(arg, &mut options)
+        if first_arg == "run" {
+            // `velo run [dir] [flags]`
+            let mut dir = ".";
+            let mut rest = args.iter().skip(2);
+            while let Some(arg) = rest.next() {
+                if arg.starts_with('-') {
+                    parse_arg(arg, &mut rest, &mut options)
+                } else {
+                    dir = arg;
+                }
+            }
+            run_package(Path::new(dir), options);
+            return;
+        }
+
+        if first_arg == "test" {
+            // `velo test <file> [flags]`, runs every `@test` function in the file
+            let mut rest = args.iter().skip(3);
+            while let Some(arg) = rest.next() {
+                parse_arg(arg, &mut rest, &mut options)
+            }
+            match args.get(2) {
+                Some(path) => test_file(path, options),
+                None => {
+                    eprintln!("{} \x1b[1mUsage: velo test <file>\x1b[0m", ERROR_INDICATOR);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+
+        if first_arg == "check" {
+            // `velo check <file> [flags]`, reports errors and warnings without
+            // running the file
+            let mut rest = args.iter().skip(3);
+            while let Some(arg) = rest.next() {
+                parse_arg(arg, &mut rest, &mut options)
+            }
+            match args.get(2) {
+                Some(path) => check_file(path, options),
+                None => {
+                    eprintln!("{} \x1b[1mUsage: velo check <file>\x1b[0m", ERROR_INDICATOR);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+
+        if first_arg == "fmt" {
+            // `velo fmt <file>`, prints the formatted file
+            match args.get(2) {
+                Some(path) => format_file(path),
+                None => {
+                    eprintln!("{} \x1b[1mUsage: velo fmt <file>\x1b[0m", ERROR_INDICATOR);
+                    process::exit(1);
+                }
+            }
+            return;
+        }
+
+        let mut rest = args.iter().skip(2);
+        while let Some(arg) = rest.next() {
+            parse_arg(arg, &mut rest, &mut options)
         }
 
         if is_filename {
             let path = &args[1];
             if let Ok(contents) = fs::read_to_string(path) {
-                parse_file(contents, options)
+                parse_file(contents, path, options)
             } else {
                 eprintln!("Error reading file: {}", path);
             }
         } else {
-            parse_arg(first_arg, &mut options);
+            parse_arg(first_arg, &mut args.iter().skip(2), &mut options);
             repl(options)
         }
 
@@ -52,9 +135,9 @@ fn main() {
 fn repl(options: RuntimeOptions) {
     println!("Velo REPL [beta]\nUse `quit` to exit safely\n");
     println!("NOTES TO SELF:");
-    println!(
-        "Implement function body parsing\nRefactors\nWarning Emission\nTuple Types in functions"
-    );
+    println!("Implement function body parsing\nRefactors\nTuple Types in functions");
+
+    let mut env = Environment::init();
 
     loop {
         print!("> ");
@@ -69,25 +152,228 @@ fn repl(options: RuntimeOptions) {
             process::exit(0);
         }
 
-        let mut lexer = Lexer::new(&input);
-        let tokens = lexer.tokenize().tokens;
+        let nodes = match parse_source(input) {
+            Ok(nodes) => nodes,
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("{}", error.render(Some(input), "<repl>"));
+                }
+                continue;
+            }
+        };
+
+        // Like Python, echo the value of a trailing expression unless it is null
+        match evaluate(nodes, options.debug_mode, &mut env) {
+            Ok(Some(value)) => match value {
+                Value::Null => {}
+                Value::String(str) => println!("{:?}", str),
+                _ => println!("{}", value),
+            },
+            Ok(None) => {}
+            Err(error) => eprintln!("{}", error.render(Some(input), "<repl>")),
+        }
+    }
+}
+
+fn run_package(dir: &Path, options: RuntimeOptions) {
+    if !dir.join(MANIFEST_NAME).exists() {
+        eprintln!(
+            "{} \x1b[1mCould not find '{}' in '{}'\x1b[0m",
+            ERROR_INDICATOR,
+            MANIFEST_NAME,
+            dir.display()
+        );
+        process::exit(1);
+    }
+
+    let graph = match PackageGraph::resolve(dir) {
+        Ok(graph) => graph,
+        Err(error) => {
+            eprintln!("{}", error.render(None, MANIFEST_NAME));
+            process::exit(1);
+        }
+    };
+    if let Err(error) = lock::write(&graph) {
+        eprintln!("{}", error.render(None, MANIFEST_NAME));
+        process::exit(1);
+    }
+
+    let entry = graph.root().entry_path();
+    let contents = match fs::read_to_string(&entry) {
+        Ok(contents) => contents,
+        Err(_) => {
+            eprintln!(
+                "{} \x1b[1mCould not read entry point '{}' of package '{}'\x1b[0m",
+                ERROR_INDICATOR,
+                entry.display(),
+                graph.root().manifest.name
+            );
+            process::exit(1);
+        }
+    };
+
+    let filename = entry.display().to_string();
+    run_source(
+        contents,
+        &filename,
+        options,
+        Environment::with_packages(graph),
+    )
+}
+
+fn format_file(path: &str) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            eprintln!("Error reading file: {}", path);
+            process::exit(1);
+        }
+    };
+
+    match format_source(&contents) {
+        Ok(formatted) => print!("{}", formatted),
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}\n", error.render(Some(&contents), path));
+            }
+            process::exit(1);
+        }
+    }
+}
+
+/// Parses and analyzes the file, printing every error and warning found
+fn check_file(path: &str, options: RuntimeOptions) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            eprintln!("Error reading file: {}", path);
+            process::exit(1);
+        }
+    };
 
-        let mut parser = Parser::new(tokens);
-        let _ = parser.parse();
-        let mut env = Environment::init();
+    let runnable = match parse_source(&contents) {
+        Ok(nodes) => analyze_source(&nodes, &contents, path, &options.lints),
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}\n", error.render(Some(&contents), path));
+            }
+            false
+        }
+    };
+    if !runnable {
+        process::exit(1);
+    }
+}
+
+/// Resolves names, checks types and lints, printing what is found. Returns
+/// whether the program can run, which warnings do not stop it from doing.
+fn analyze_source(nodes: &[Ast], contents: &str, filename: &str, lints: &LintLevels) -> bool {
+    let mut lints = lints.clone();
+    let mut found = lints.apply_pragmas(contents);
+    let analysis = analyze(nodes);
+    found.extend(analysis.errors);
+    found.extend(lint_program(nodes, &analysis.deprecated, &lints));
+    found.sort_by_key(|error| (error.span.line, error.span.column));
+
+    for error in &found {
+        eprintln!("{}\n", error.render(Some(contents), filename));
+    }
+    found.iter().all(|error| error.error_type == Warning)
+}
+
+fn parse_file(contents: String, filename: &str, options: RuntimeOptions) {
+    run_source(contents, filename, options, Environment::init())
+}
+
+fn run_source(contents: String, filename: &str, options: RuntimeOptions, mut env: Environment) {
+    let nodes = match parse_source(&contents) {
+        Ok(nodes) => nodes,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}\n", error.render(Some(&contents), filename));
+            }
+            process::exit(1);
+        }
+    };
+    if !analyze_source(&nodes, &contents, filename, &options.lints) {
+        process::exit(1);
+    }
+    let nodes = if options.optimize {
+        optimize_program(nodes)
+    } else {
+        nodes
+    };
+    if options.emit_ast {
+        print!("{}", print_program(&nodes));
+        return;
+    }
 
-        evaluate(parser.nodes, options.debug_mode, &mut env);
+    if let Err(error) = evaluate(nodes, options.debug_mode, &mut env) {
+        eprintln!("{}", error.render(Some(&contents), filename));
+        process::exit(1);
     }
 }
 
-fn parse_file(contents: String, options: RuntimeOptions) {
-    let mut lexer = Lexer::new(&contents);
-    let tokens = lexer.tokenize();
-    let tokens = tokens.tokens;
+/// Runs the file, then each of its `@test` functions. A test fails when it
+/// throws or returns an `err` result.
+fn test_file(path: &str, options: RuntimeOptions) {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            eprintln!("Error reading file: {}", path);
+            process::exit(1);
+        }
+    };
+
+    let nodes = match parse_source(&contents) {
+        Ok(nodes) => nodes,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}\n", error.render(Some(&contents), path));
+            }
+            process::exit(1);
+        }
+    };
+    if !analyze_source(&nodes, &contents, path, &options.lints) {
+        process::exit(1);
+    }
 
     let mut env = Environment::init();
-    let mut parser = Parser::new(tokens);
-    let _ = parser.parse();
+    if let Err(error) = evaluate(nodes, false, &mut env) {
+        eprintln!("{}", error.render(Some(&contents), path));
+        process::exit(1);
+    }
+
+    let tests: Vec<Rc<Statement>> = env.annotated("test");
+    println!("running {} test(s)", tests.len());
+    let mut failed = 0;
+    for test in &tests {
+        let name = match &test.kind {
+            StatementKind::Function { name, .. } => name,
+            _ => unreachable!(),
+        };
+        match run_function(test, &mut env) {
+            Ok(Value::Result { ok: false, value }) => {
+                println!("test {} ... FAILED, returned err({})", name, value);
+                failed += 1;
+            }
+            Ok(_) => println!("test {} ... ok", name),
+            Err(error) => {
+                println!("test {} ... FAILED", name);
+                eprintln!("{}\n", error.render(Some(&contents), path));
+                failed += 1;
+            }
+        }
+    }
 
-    evaluate(parser.nodes, options.debug_mode, &mut env);
+    let status = if failed == 0 { "ok" } else { "FAILED" };
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        status,
+        tests.len() - failed,
+        failed
+    );
+    if failed > 0 {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::manifest::{error, Manifest};
+use crate::error::VeloError;
+
+/// A package together with the location it was loaded from and the
+/// indices (into `PackageGraph::packages`) of its direct dependencies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub manifest: Manifest,
+    pub root: PathBuf,
+    pub dependencies: Vec<(String, usize)>,
+}
+
+impl Package {
+    pub fn entry_path(&self) -> PathBuf {
+        self.root.join(&self.manifest.entry)
+    }
+}
+
+/// The module graph of a package and everything it depends on.
+/// Packages are stored in dependency order: every package comes after
+/// all of its dependencies, so the root package is always last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageGraph {
+    pub packages: Vec<Package>,
+}
+
+impl PackageGraph {
+    pub fn resolve(root_dir: &Path) -> Result<PackageGraph, VeloError> {
+        let mut resolver = Resolver {
+            packages: Vec::new(),
+            by_path: HashMap::new(),
+            stack: Vec::new(),
+        };
+        resolver.visit(root_dir)?;
+
+        Ok(PackageGraph {
+            packages: resolver.packages,
+        })
+    }
+
+    pub fn root(&self) -> &Package {
+        self.packages.last().expect("package graph is never empty")
+    }
+
+    pub fn root_index(&self) -> usize {
+        self.packages.len() - 1
+    }
+
+    /// Finds the package that `name` refers to when imported from the
+    /// package at index `from`.
+    pub fn dependency(&self, from: usize, name: &str) -> Option<usize> {
+        self.packages[from]
+            .dependencies
+            .iter()
+            .find(|(dep, _)| dep == name)
+            .map(|(_, index)| *index)
+    }
+}
+
+struct Resolver {
+    packages: Vec<Package>,
+    by_path: HashMap<PathBuf, usize>,
+    stack: Vec<(PathBuf, String)>,
+}
+
+impl Resolver {
+    fn visit(&mut self, dir: &Path) -> Result<usize, VeloError> {
+        let root = match dir.canonicalize() {
+            Ok(root) => root,
+            Err(_) => {
+                return Err(error(&format!(
+                    "Package directory '{}' does not exist",
+                    dir.display()
+                )))
+            }
+        };
+
+        if let Some(index) = self.by_path.get(&root) {
+            return Ok(*index);
+        }
+
+        let manifest = Manifest::load(&root)?;
+
+        if let Some(pos) = self.stack.iter().position(|(path, _)| *path == root) {
+            let mut cycle: Vec<&str> = self.stack[pos..]
+                .iter()
+                .map(|(_, name)| name.as_str())
+                .collect();
+            cycle.push(&manifest.name);
+            return Err(error(&format!(
+                "Dependency cycle detected: {}",
+                cycle.join(" -> ")
+            )));
+        }
+
+        self.stack.push((root.clone(), manifest.name.clone()));
+
+        let mut dependencies = Vec::new();
+        for dep in &manifest.dependencies {
+            let index = self.visit(&root.join(&dep.path))?;
+            let found = &self.packages[index].manifest.name;
+            if *found != dep.name {
+                return Err(error(&format!(
+                    "Dependency '{}' of package '{}' points to a package named '{}'",
+                    dep.name, manifest.name, found
+                )));
+            }
+            dependencies.push((dep.name.clone(), index));
+        }
+
+        self.stack.pop();
+
+        if let Some(other) = self
+            .packages
+            .iter()
+            .find(|p| p.manifest.name == manifest.name)
+        {
+            return Err(error(&format!(
+                "Package '{}' is found at both '{}' and '{}'",
+                manifest.name,
+                other.root.display(),
+                root.display()
+            )));
+        }
+
+        self.packages.push(Package {
+            manifest,
+            root: root.clone(),
+            dependencies,
+        });
+        let index = self.packages.len() - 1;
+        self.by_path.insert(root, index);
+
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use super::PackageGraph;
+
+    /// A package's directory, name and dependencies, each dependency a
+    /// name and a path
+    type Spec<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
+    /// Writes each package under a fresh directory for the test
+    fn write_packages(test: &str, packages: &[Spec]) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("velo-graph-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        for (dir, name, dependencies) in packages {
+            let mut manifest = format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[dependencies]\n",
+                name
+            );
+            for (dep, path) in *dependencies {
+                manifest.push_str(&format!("{} = {{ path = \"{}\" }}\n", dep, path));
+            }
+            fs::create_dir_all(base.join(dir)).unwrap();
+            fs::write(base.join(dir).join("velo.toml"), manifest).unwrap();
+        }
+        base
+    }
+
+    fn resolve_error(base: &Path, root: &str) -> String {
+        let result = PackageGraph::resolve(&base.join(root));
+        fs::remove_dir_all(base).unwrap();
+        result.expect_err("graph should be rejected").message
+    }
+
+    #[test]
+    fn dependencies_come_first() {
+        let base = write_packages(
+            "order",
+            &[
+                ("app", "app", &[("utils", "../utils"), ("core", "../core")]),
+                ("utils", "utils", &[("core", "../core")]),
+                ("core", "core", &[]),
+            ],
+        );
+        let graph = PackageGraph::resolve(&base.join("app")).unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        let names: Vec<&str> = graph
+            .packages
+            .iter()
+            .map(|p| p.manifest.name.as_str())
+            .collect();
+        // `core` is shared, not loaded twice
+        assert_eq!(names, ["core", "utils", "app"]);
+        assert_eq!(graph.root().manifest.name, "app");
+        let root = graph.root_index();
+        assert_eq!(graph.dependency(root, "utils"), Some(1));
+        assert_eq!(graph.dependency(root, "core"), Some(0));
+        assert_eq!(graph.dependency(1, "core"), Some(0));
+        assert_eq!(graph.dependency(0, "utils"), None);
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let base = write_packages(
+            "cycle",
+            &[
+                ("a", "a", &[("b", "../b")]),
+                ("b", "b", &[("c", "../c")]),
+                ("c", "c", &[("a", "../a")]),
+            ],
+        );
+        assert_eq!(
+            resolve_error(&base, "a"),
+            "Dependency cycle detected: a -> b -> c -> a"
+        );
+    }
+
+    #[test]
+    fn a_package_depending_on_itself_is_a_cycle() {
+        let base = write_packages("self", &[("a", "a", &[("a", ".")])]);
+        assert_eq!(
+            resolve_error(&base, "a"),
+            "Dependency cycle detected: a -> a"
+        );
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let base = write_packages(
+            "duplicate",
+            &[
+                ("app", "app", &[("utils", "../one"), ("other", "../other")]),
+                ("other", "other", &[("utils", "../two")]),
+                ("one", "utils", &[]),
+                ("two", "utils", &[]),
+            ],
+        );
+        let message = resolve_error(&base, "app");
+        assert!(
+            message.starts_with("Package 'utils' is found at both '"),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains("one") && message.contains("two"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn dependency_names_must_match() {
+        let base = write_packages(
+            "mismatch",
+            &[
+                ("app", "app", &[("utils", "../core")]),
+                ("core", "core", &[]),
+            ],
+        );
+        assert_eq!(
+            resolve_error(&base, "app"),
+            "Dependency 'utils' of package 'app' points to a package named 'core'"
+        );
+    }
+
+    #[test]
+    fn missing_packages() {
+        let base = write_packages("missing", &[("app", "app", &[("utils", "../utils")])]);
+        let message = resolve_error(&base, "app");
+        assert!(
+            message.starts_with("Package directory '")
+                && message.ends_with("utils' does not exist"),
+            "{}",
+            message
+        );
+    }
+}
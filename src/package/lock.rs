@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use super::graph::PackageGraph;
+use super::manifest::error;
+use crate::error::VeloError;
+use crate::utils::relative_path;
+
+pub const LOCKFILE_NAME: &str = "velo.lock";
+pub const LOCKFILE_VERSION: usize = 1;
+
+/// Renders the lockfile for a resolved graph. Packages are sorted by name
+/// and paths are written relative to the root package, so the output only
+/// changes when the graph itself does.
+pub fn render(graph: &PackageGraph) -> String {
+    let root = &graph.root().root;
+    let mut packages: Vec<_> = graph.packages.iter().collect();
+    packages.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+
+    let mut out = String::new();
+    out.push_str("# This file is automatically generated by velo.\n");
+    out.push_str("# It is not intended for manual editing.\n");
+    out.push_str(&format!("version = {}\n", LOCKFILE_VERSION));
+
+    for package in packages {
+        let mut deps: Vec<&str> = package
+            .dependencies
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        deps.sort();
+
+        out.push_str("\n[[package]]\n");
+        out.push_str(&format!("name = \"{}\"\n", package.manifest.name));
+        out.push_str(&format!("version = \"{}\"\n", package.manifest.version));
+        out.push_str(&format!(
+            "path = \"{}\"\n",
+            relative_path(root, &package.root).display()
+        ));
+        if !deps.is_empty() {
+            let deps: Vec<String> = deps.iter().map(|d| format!("\"{}\"", d)).collect();
+            out.push_str(&format!("dependencies = [{}]\n", deps.join(", ")));
+        }
+    }
+
+    out
+}
+
+/// Writes `velo.lock` next to the root manifest. Returns whether the file
+/// had to be (re)written.
+pub fn write(graph: &PackageGraph) -> Result<bool, VeloError> {
+    let path = graph.root().root.join(LOCKFILE_NAME);
+    let contents = render(graph);
+
+    if read(&path).as_deref() == Some(contents.as_str()) {
+        return Ok(false);
+    }
+
+    match std::fs::write(&path, contents) {
+        Ok(_) => Ok(true),
+        Err(_) => Err(error(&format!("Could not write '{}'", path.display()))),
+    }
+}
+
+fn read(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::render;
+    use crate::package::graph::{Package, PackageGraph};
+    use crate::package::manifest::Manifest;
+
+    fn package(name: &str, root: &str, dependencies: &[(&str, usize)]) -> Package {
+        Package {
+            manifest: Manifest {
+                name: name.to_string(),
+                version: "0.1.0".to_string(),
+                entry: PathBuf::from("main.velo"),
+                dependencies: Vec::new(),
+            },
+            root: PathBuf::from(root),
+            dependencies: dependencies
+                .iter()
+                .map(|(name, index)| (name.to_string(), *index))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn packages_sorted_by_name_with_relative_paths() {
+        let graph = PackageGraph {
+            packages: vec![
+                package("zlib", "/work/zlib", &[]),
+                package("core", "/work/app/vendor/core", &[]),
+                package("app", "/work/app", &[("zlib", 0), ("core", 1)]),
+            ],
+        };
+        assert_eq!(
+            render(&graph),
+            "# This file is automatically generated by velo.\n\
+             # It is not intended for manual editing.\n\
+             version = 1\n\
+             \n\
+             [[package]]\n\
+             name = \"app\"\n\
+             version = \"0.1.0\"\n\
+             path = \".\"\n\
+             dependencies = [\"core\", \"zlib\"]\n\
+             \n\
+             [[package]]\n\
+             name = \"core\"\n\
+             version = \"0.1.0\"\n\
+             path = \"vendor/core\"\n\
+             \n\
+             [[package]]\n\
+             name = \"zlib\"\n\
+             version = \"0.1.0\"\n\
+             path = \"../zlib\"\n"
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use super::toml::{self, TomlTable, TomlValue};
+use crate::error::{ErrorType::PackageError, VeloError};
+use crate::syntax::span::Span;
+
+pub const MANIFEST_NAME: &str = "velo.toml";
+pub const DEFAULT_ENTRY: &str = "main.velo";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub entry: PathBuf,
+    pub dependencies: Vec<Dependency>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl Manifest {
+    pub fn load(dir: &Path) -> Result<Manifest, VeloError> {
+        let path = dir.join(MANIFEST_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Self::parse(&contents),
+            Err(_) => Err(error(&format!("Could not read '{}'", path.display()))),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Manifest, VeloError> {
+        let root = toml::parse(source)?;
+
+        let package = match root.get("package") {
+            Some(TomlValue::Table(table)) => table,
+            _ => return Err(error("Manifest is missing a [package] table")),
+        };
+
+        let name = required_string(package, "name")?;
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(error(&format!("Invalid package name '{}'", name)));
+        }
+
+        let version = required_string(package, "version")?;
+        let parts: Vec<&str> = version.split('.').collect();
+        if parts.len() != 3 || parts.iter().any(|p| p.parse::<u64>().is_err()) {
+            return Err(error(&format!(
+                "Invalid version '{}' for package '{}', expected 'major.minor.patch'",
+                version, name
+            )));
+        }
+
+        let entry = match package.get("entry") {
+            Some(TomlValue::String(entry)) => PathBuf::from(entry),
+            Some(_) => return Err(error("Package entry must be a string")),
+            None => PathBuf::from(DEFAULT_ENTRY),
+        };
+
+        let mut dependencies = Vec::new();
+        match root.get("dependencies") {
+            Some(TomlValue::Table(table)) => {
+                for (dep_name, value) in &table.entries {
+                    let path = match value {
+                        TomlValue::Table(dep) => match dep.get("path") {
+                            Some(TomlValue::String(path)) => PathBuf::from(path),
+                            _ => {
+                                return Err(error(&format!(
+                                    "Dependency '{}' must specify a `path`, only local dependencies are supported",
+                                    dep_name
+                                )))
+                            }
+                        },
+                        _ => {
+                            return Err(error(&format!(
+                                "Dependency '{}' must be written as `{} = {{ path = \"...\" }}`",
+                                dep_name, dep_name
+                            )))
+                        }
+                    };
+                    dependencies.push(Dependency {
+                        name: dep_name.clone(),
+                        path,
+                    });
+                }
+            }
+            Some(_) => return Err(error("[dependencies] must be a table")),
+            None => {}
+        }
+
+        Ok(Manifest {
+            name,
+            version,
+            entry,
+            dependencies,
+        })
+    }
+}
+
+fn required_string(table: &TomlTable, key: &str) -> Result<String, VeloError> {
+    match table.get(key) {
+        Some(TomlValue::String(value)) => Ok(value.clone()),
+        Some(_) => Err(error(&format!("Package {} must be a string", key))),
+        None => Err(error(&format!("Package is missing required key '{}'", key))),
+    }
+}
+
+pub fn error(message: &str) -> VeloError {
+    VeloError::error(Span::default(), message, PackageError)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{Dependency, Manifest};
+
+    fn parse_error(source: &str) -> String {
+        Manifest::parse(source)
+            .expect_err("manifest should be rejected")
+            .message
+    }
+
+    #[test]
+    fn parses_a_manifest() {
+        let manifest = Manifest::parse(
+            "[package]\n\
+             name = \"app\"\n\
+             version = \"0.1.0\"\n\
+             entry = \"src/app.velo\"\n\
+             \n\
+             [dependencies]\n\
+             utils = { path = \"../utils\" }\n",
+        )
+        .unwrap();
+        assert_eq!(
+            manifest,
+            Manifest {
+                name: "app".to_string(),
+                version: "0.1.0".to_string(),
+                entry: PathBuf::from("src/app.velo"),
+                dependencies: vec![Dependency {
+                    name: "utils".to_string(),
+                    path: PathBuf::from("../utils"),
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn entry_defaults_to_main() {
+        let manifest = Manifest::parse("[package]\nname = \"a\"\nversion = \"1.2.3\"").unwrap();
+        assert_eq!(manifest.entry, PathBuf::from("main.velo"));
+        assert!(manifest.dependencies.is_empty());
+    }
+
+    #[test]
+    fn invalid_manifests() {
+        let package = "[package]\nname = \"app\"\nversion = \"0.1.0\"\n";
+        let cases = [
+            (
+                "name = \"app\"".to_string(),
+                "Manifest is missing a [package] table",
+            ),
+            (
+                "[package]\nversion = \"0.1.0\"".to_string(),
+                "Package is missing required key 'name'",
+            ),
+            (
+                "[package]\nname = 5\nversion = \"0.1.0\"".to_string(),
+                "Package name must be a string",
+            ),
+            (
+                "[package]\nname = \"my app\"\nversion = \"0.1.0\"".to_string(),
+                "Invalid package name 'my app'",
+            ),
+            (
+                "[package]\nname = \"app\"\nversion = \"1.0\"".to_string(),
+                "Invalid version '1.0' for package 'app', expected 'major.minor.patch'",
+            ),
+            (
+                format!("dependencies = 1\n{}", package),
+                "[dependencies] must be a table",
+            ),
+            (
+                format!("{}entry = true", package),
+                "Package entry must be a string",
+            ),
+            (
+                format!("{}[dependencies]\nutils = \"1.0\"", package),
+                "Dependency 'utils' must be written as `utils = { path = \"...\" }`",
+            ),
+            (
+                format!("{}[dependencies]\nutils = {{ version = \"1.0\" }}", package),
+                "Dependency 'utils' must specify a `path`, only local dependencies are supported",
+            ),
+        ];
+        for (source, message) in cases {
+            assert_eq!(parse_error(&source), message, "{}", source);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod graph;
+pub mod lock;
+pub mod manifest;
+mod toml;
+
+pub use graph::PackageGraph;
+pub use manifest::MANIFEST_NAME;
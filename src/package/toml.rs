@@ -0,0 +1,379 @@
+use crate::error::{ErrorType::PackageError, VeloError};
+use crate::syntax::span::Span;
+
+// Just enough TOML to read `velo.toml`: tables, strings, integers,
+// booleans, arrays and inline tables. No dotted keys, dates or floats.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TomlValue {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Array(Vec<TomlValue>),
+    Table(TomlTable),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TomlTable {
+    pub entries: Vec<(String, TomlValue)>,
+}
+
+impl TomlTable {
+    pub fn get(&self, key: &str) -> Option<&TomlValue> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn insert(&mut self, key: String, value: TomlValue) -> bool {
+        if self.get(&key).is_some() {
+            return false;
+        }
+        self.entries.push((key, value));
+        true
+    }
+}
+
+pub fn parse(source: &str) -> Result<TomlTable, VeloError> {
+    let mut root = TomlTable::default();
+    let mut section: Option<String> = None;
+
+    for (index, raw) in source.lines().enumerate() {
+        let line_num = index + 1;
+        let line = strip_comment(raw).trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('[') {
+            if line.starts_with("[[") {
+                return Err(error(
+                    line_num,
+                    "Arrays of tables are not supported in manifests",
+                ));
+            }
+            let name = match line.strip_suffix(']') {
+                Some(name) => name[1..].trim(),
+                None => return Err(error(line_num, "Expected ']' to close table header")),
+            };
+            if name.is_empty() || !name.chars().all(is_bare_key_char) {
+                return Err(error(line_num, &format!("Invalid table name '{}'", name)));
+            }
+            if !root.insert(name.to_string(), TomlValue::Table(TomlTable::default())) {
+                return Err(error(
+                    line_num,
+                    &format!("Table '{}' is defined twice", name),
+                ));
+            }
+            section = Some(name.to_string());
+            continue;
+        }
+
+        let mut cursor = Cursor::new(line, line_num);
+        let key = cursor.key()?;
+        cursor.skip_whitespace();
+        cursor.expect('=')?;
+        let value = cursor.value()?;
+        cursor.skip_whitespace();
+        if !cursor.at_end() {
+            return Err(error(line_num, "Unexpected characters after value"));
+        }
+
+        let table = match &section {
+            Some(name) => match root.entries.iter_mut().find(|(k, _)| k == name) {
+                Some((_, TomlValue::Table(table))) => table,
+                _ => unreachable!(),
+            },
+            None => &mut root,
+        };
+        if !table.insert(key.clone(), value) {
+            return Err(error(line_num, &format!("Key '{}' is defined twice", key)));
+        }
+    }
+
+    Ok(root)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '\\' if in_string => escaped = !escaped,
+            '"' if !escaped => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => escaped = false,
+        }
+    }
+    line
+}
+
+fn is_bare_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn error(line: usize, message: &str) -> VeloError {
+    VeloError::error(Span::line(line), message, PackageError)
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Cursor {
+    fn new(source: &str, line: usize) -> Self {
+        Cursor {
+            chars: source.chars().collect(),
+            pos: 0,
+            line,
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), VeloError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(error(self.line, &format!("Expected '{}'", c)))
+        }
+    }
+
+    fn key(&mut self) -> Result<String, VeloError> {
+        self.skip_whitespace();
+        if self.peek() == Some('"') {
+            return self.string();
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(is_bare_key_char) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(error(self.line, "Expected a key"));
+        }
+        Ok(self.chars[start..self.pos].iter().collect())
+    }
+
+    fn value(&mut self) -> Result<TomlValue, VeloError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"') => Ok(TomlValue::String(self.string()?)),
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(']') {
+                        self.pos += 1;
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {}
+                        _ => return Err(error(self.line, "Expected ',' or ']' in array")),
+                    }
+                }
+                Ok(TomlValue::Array(items))
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut table = TomlTable::default();
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some('}') {
+                        self.pos += 1;
+                        break;
+                    }
+                    let key = self.key()?;
+                    self.skip_whitespace();
+                    self.expect('=')?;
+                    let value = self.value()?;
+                    if !table.insert(key.clone(), value) {
+                        return Err(error(self.line, &format!("Key '{}' is defined twice", key)));
+                    }
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => {}
+                        _ => return Err(error(self.line, "Expected ',' or '}' in inline table")),
+                    }
+                }
+                Ok(TomlValue::Table(table))
+            }
+            Some(_) => {
+                let start = self.pos;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    self.pos += 1;
+                }
+                let word: String = self.chars[start..self.pos].iter().collect();
+                match word.as_str() {
+                    "true" => Ok(TomlValue::Bool(true)),
+                    "false" => Ok(TomlValue::Bool(false)),
+                    _ => match word.replace('_', "").parse::<i64>() {
+                        Ok(int) => Ok(TomlValue::Integer(int)),
+                        Err(_) => Err(error(self.line, &format!("Invalid value '{}'", word))),
+                    },
+                }
+            }
+            None => Err(error(self.line, "Expected a value")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, VeloError> {
+        self.pos += 1;
+        let mut string = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = match self.peek() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        _ => return Err(error(self.line, "Invalid escape sequence in string")),
+                    };
+                    self.pos += 1;
+                    string.push(escaped);
+                }
+                _ => string.push(c),
+            }
+        }
+        Err(error(self.line, "Unclosed string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, TomlTable, TomlValue};
+
+    fn string(value: &str) -> TomlValue {
+        TomlValue::String(value.to_string())
+    }
+
+    /// The message and line of the error parsing `source` gives
+    fn parse_error(source: &str) -> (String, usize) {
+        let error = parse(source).expect_err("source should not parse");
+        (error.message, error.span.line)
+    }
+
+    #[test]
+    fn values_and_tables() {
+        let root = parse(
+            "title = \"app\"\n\
+             \n\
+             [package]\n\
+             count = 1_000\n\
+             debug = true\n\
+             tags = [\"a\", \"b\",]\n\
+             \"quoted key\" = -5\n",
+        )
+        .unwrap();
+        assert_eq!(root.get("title"), Some(&string("app")));
+
+        let Some(TomlValue::Table(package)) = root.get("package") else {
+            panic!("expected a [package] table");
+        };
+        assert_eq!(package.get("count"), Some(&TomlValue::Integer(1000)));
+        assert_eq!(package.get("debug"), Some(&TomlValue::Bool(true)));
+        assert_eq!(
+            package.get("tags"),
+            Some(&TomlValue::Array(vec![string("a"), string("b")]))
+        );
+        assert_eq!(package.get("quoted key"), Some(&TomlValue::Integer(-5)));
+    }
+
+    #[test]
+    fn inline_tables() {
+        let root = parse("utils = { path = \"../utils\", pinned = false, nested = {} }").unwrap();
+        let expected = TomlTable {
+            entries: vec![
+                ("path".to_string(), string("../utils")),
+                ("pinned".to_string(), TomlValue::Bool(false)),
+                ("nested".to_string(), TomlValue::Table(TomlTable::default())),
+            ],
+        };
+        assert_eq!(root.get("utils"), Some(&TomlValue::Table(expected)));
+    }
+
+    #[test]
+    fn comments_inside_strings() {
+        let root = parse(
+            "# a comment line\n\
+             a = \"x # y\" # a trailing comment\n\
+             b = \"say \\\"#\\\" \\\\\" # after an escaped quote and backslash\n\
+             c = { d = \"#\" } # in an inline table\n",
+        )
+        .unwrap();
+        assert_eq!(root.get("a"), Some(&string("x # y")));
+        assert_eq!(root.get("b"), Some(&string("say \"#\" \\")));
+        let Some(TomlValue::Table(c)) = root.get("c") else {
+            panic!("expected an inline table");
+        };
+        assert_eq!(c.get("d"), Some(&string("#")));
+    }
+
+    #[test]
+    fn escapes() {
+        let root = parse("s = \"tab\\tnewline\\n\"").unwrap();
+        assert_eq!(root.get("s"), Some(&string("tab\tnewline\n")));
+    }
+
+    #[test]
+    fn errors_report_their_line() {
+        let cases = [
+            ("a = 1\na = 2", "Key 'a' is defined twice", 2),
+            ("[p]\n[p]", "Table 'p' is defined twice", 2),
+            (
+                "[[bin]]",
+                "Arrays of tables are not supported in manifests",
+                1,
+            ),
+            ("\n[package", "Expected ']' to close table header", 2),
+            ("[a.b]", "Invalid table name 'a.b'", 1),
+            ("a = \"open", "Unclosed string", 1),
+            ("a = \"\\q\"", "Invalid escape sequence in string", 1),
+            ("a = 1.5", "Unexpected characters after value", 1),
+            ("a = yes", "Invalid value 'yes'", 1),
+            ("a = 1 2", "Unexpected characters after value", 1),
+            ("a 1", "Expected '='", 1),
+            ("= 1", "Expected a key", 1),
+            ("a =", "Expected a value", 1),
+            ("a = [1 2]", "Expected ',' or ']' in array", 1),
+            (
+                "a = { b = 1 c = 2 }",
+                "Expected ',' or '}' in inline table",
+                1,
+            ),
+            ("a = { b = 1, b = 2 }", "Key 'b' is defined twice", 1),
+        ];
+        for (source, message, line) in cases {
+            assert_eq!(
+                parse_error(source),
+                (message.to_string(), line),
+                "{}",
+                source
+            );
+        }
+    }
+}
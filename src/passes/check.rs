@@ -0,0 +1,1531 @@
+//! The static type checker, run between parsing and evaluation.
+//!
+//! It infers the type of every expression from the declarations in the
+//! program and reports each mismatch it finds, rather than stopping at the
+//! first. Anything it cannot know, like a variable it has not seen or a
+//! function that may come from an import, has type `any`, which fits
+//! everywhere. Names that do not exist at all are left to the resolver.
+//!
+//! Numbers widen from `short` to `int` to `large` to `bigint`, and from
+//! there to `float` or `decimal`, never the other way, except that an
+//! integer literal fits any type it is in range of. `float` and `decimal`
+//! do not widen to each other.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::Analysis;
+use crate::error::{ErrorType::TypeError, VeloError};
+use crate::runtime::eval::types::named_types;
+use crate::syntax::ast::visit::{walk_statement, Visitor};
+use crate::syntax::ast::{
+    Ast, Expression, ExpressionKind, MethodSignature, NamedArg, Param, Statement, StatementKind,
+};
+use crate::syntax::lexer::{TokenType, Type};
+use crate::syntax::printer::{comparison, operator, type_name};
+use crate::syntax::span::Span;
+use crate::utils::capitalize;
+
+/// Checks a whole program, returning every type error in it and every use
+/// of something `@deprecated`
+pub fn check_program(nodes: &[Ast]) -> Analysis {
+    let mut declarations = Declarations::default();
+    declarations.visit_block(nodes);
+
+    let mut checker = Checker {
+        declarations,
+        errors: Vec::new(),
+        deprecated: Vec::new(),
+        scopes: vec![Scope::default()],
+        function: None,
+    };
+    checker.block(nodes);
+    Analysis {
+        errors: checker.errors,
+        deprecated: checker.deprecated,
+    }
+}
+
+/// The message for a use of `declaration` if it is `@deprecated`, with
+/// the reason given, e.g. "Function `f` is deprecated: use g"
+fn deprecation(declaration: &Statement, what: &str) -> Option<String> {
+    let annotation = declaration.annotation("deprecated")?;
+    Some(match annotation.args.first().map(|arg| &arg.kind) {
+        Some(ExpressionKind::StringLiteral(reason)) => {
+            format!("{} is deprecated: {}", what, reason)
+        }
+        _ => format!("{} is deprecated", what),
+    })
+}
+
+/// Every struct, interface and `impl` block in the program, wherever it is
+/// declared. Functions are looked up through the scopes instead, as a
+/// function declared inside another is only seen there.
+#[derive(Default)]
+struct Declarations {
+    structs: HashMap<String, Vec<(String, Type)>>,
+    struct_params: HashMap<String, Vec<String>>,
+    /// The message for each `@deprecated` struct
+    deprecated_structs: HashMap<String, String>,
+    interfaces: HashMap<String, Vec<MethodSignature>>,
+    /// Interface, struct and methods of each `impl` block
+    impls: Vec<(String, String, Vec<Statement>)>,
+    /// With an import anywhere, names the checker has not seen may still
+    /// be declared
+    imports: bool,
+}
+
+impl Visitor for Declarations {
+    fn visit_statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::Struct {
+                name,
+                type_params,
+                fields,
+            } => {
+                self.structs.insert(name.clone(), fields.clone());
+                self.struct_params.insert(name.clone(), type_params.clone());
+                if let Some(message) = deprecation(stmt, &format!("Struct `{}`", name)) {
+                    self.deprecated_structs.insert(name.clone(), message);
+                }
+            }
+            StatementKind::Interface { name, methods } => {
+                self.interfaces.insert(name.clone(), methods.clone());
+            }
+            StatementKind::Impl {
+                interface,
+                target,
+                methods,
+            } => {
+                self.impls
+                    .push((interface.clone(), target.clone(), methods.clone()));
+                // Methods are not functions that can be called by name
+                for method in methods {
+                    walk_statement(self, method);
+                }
+                return;
+            }
+            StatementKind::Import(_) => self.imports = true,
+            _ => {}
+        }
+        walk_statement(self, stmt);
+    }
+}
+
+/// The signature of something callable, as the checker sees it
+struct Signature<'a> {
+    /// e.g. "function `f`", for messages
+    owner: String,
+    /// "parameter" or "field"
+    noun: &'static str,
+    params: &'a [Param],
+    type_params: &'a [String],
+    ret_type: &'a Type,
+    named: bool,
+}
+
+impl Signature<'_> {
+    /// The owner, to start a sentence with
+    fn subject(&self) -> String {
+        capitalize(&self.owner)
+    }
+}
+
+/// The variables and functions declared in one block
+#[derive(Default)]
+struct Scope {
+    variables: HashMap<String, Type>,
+    functions: HashMap<String, Statement>,
+}
+
+struct Checker {
+    declarations: Declarations,
+    errors: Vec<VeloError>,
+    deprecated: Vec<(Span, String)>,
+    scopes: Vec<Scope>,
+    /// The name and return type of the function being checked
+    function: Option<(String, Type)>,
+}
+
+impl Checker {
+    fn error(&mut self, span: Span, message: String) {
+        self.errors
+            .push(VeloError::error(span, &message, TypeError));
+    }
+
+    fn declare(&mut self, name: &str, t: Type) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.variables.insert(name.to_string(), t);
+    }
+
+    fn lookup(&self, name: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.variables.get(name).cloned())
+            .unwrap_or(Type::Any)
+    }
+
+    /// Function `name` as declared in the innermost scope that has one
+    fn find_function(&self, name: &str) -> Option<Statement> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.functions.get(name).cloned())
+    }
+
+    /// Checks a block and returns the type of its trailing expression, the
+    /// value it has when used as the branch of an `if` expression
+    fn block(&mut self, nodes: &[Ast]) -> Type {
+        // A function can be called from anywhere in its block, including
+        // the bodies of functions declared before it
+        for node in nodes {
+            if let Ast::Statement(stmt) = node {
+                if let StatementKind::Function { name, .. } = &stmt.kind {
+                    let scope = self.scopes.last_mut().unwrap();
+                    scope.functions.insert(name.clone(), stmt.clone());
+                }
+            }
+        }
+
+        let mut last = Type::Void;
+        for node in nodes {
+            last = match node {
+                Ast::Expression(expr) => self.expression(expr),
+                Ast::Statement(stmt) => {
+                    self.statement(stmt);
+                    Type::Void
+                }
+            };
+        }
+        last
+    }
+
+    /// Checks a block in a scope of its own, with `bindings` declared in it,
+    /// as it runs
+    fn scoped_block(&mut self, nodes: &[Ast], bindings: Vec<(String, Type)>) -> Type {
+        self.scopes.push(Scope {
+            variables: bindings.into_iter().collect(),
+            ..Scope::default()
+        });
+        let t = self.block(nodes);
+        self.scopes.pop();
+        t
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::VariableAssignment { name, value, .. } => {
+                let t = self.expression(value);
+                self.declare(name, t);
+            }
+            StatementKind::IfStatement {
+                condition,
+                body,
+                else_body,
+            } => {
+                self.condition(condition);
+                self.scoped_block(body, vec![]);
+                if let Some(else_body) = else_body {
+                    self.scoped_block(else_body, vec![]);
+                }
+            }
+            StatementKind::For {
+                name,
+                iterable,
+                body,
+            } => {
+                let element = self.element_type(iterable);
+                self.scoped_block(body, vec![(name.clone(), element)]);
+            }
+            StatementKind::Function { .. } => self.function(stmt, None),
+            StatementKind::Struct { name, fields, .. } => {
+                for (field, field_type) in fields {
+                    let what = format!("field `{}` of struct `{}`", field, name);
+                    self.type_exists(field_type, &what, stmt.span);
+                }
+            }
+            StatementKind::Interface { name, methods } => {
+                for method in methods {
+                    let what = format!("method `{}` of interface `{}`", method.name, name);
+                    for param in &method.params {
+                        self.type_exists(&param.param_type, &what, method.span);
+                    }
+                    self.type_exists(&method.ret_type, &what, method.span);
+                }
+            }
+            StatementKind::Impl {
+                target, methods, ..
+            } => {
+                let receiver = Type::Named {
+                    name: target.clone(),
+                    args: Vec::new(),
+                };
+                for method in methods {
+                    self.function(method, Some(receiver.clone()));
+                }
+            }
+            StatementKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.scoped_block(body, vec![]);
+                if let Some(catch) = catch {
+                    let bindings = match &catch.name {
+                        Some(name) => vec![(name.clone(), Type::Error)],
+                        None => Vec::new(),
+                    };
+                    self.scoped_block(&catch.body, bindings);
+                }
+                if let Some(finally) = finally {
+                    self.scoped_block(finally, vec![]);
+                }
+            }
+            StatementKind::Throw(value) => {
+                let t = self.expression(value);
+                if !matches!(t, Type::Error | Type::String | Type::Any) {
+                    self.error(
+                        value.span,
+                        format!(
+                            "Cannot throw a value of type `{}`, expected an error or a string",
+                            type_name(&t)
+                        ),
+                    );
+                }
+            }
+            StatementKind::Import(_) => {}
+            StatementKind::Return(value) => self.return_statement(value.as_ref(), stmt.span),
+            StatementKind::ExprStmt(expr) => {
+                self.expression(expr);
+            }
+        }
+    }
+
+    /// Checks the body of a function, or of a method with `self` of type
+    /// `receiver`. The body sees what is in scope where it is declared.
+    fn function(&mut self, stmt: &Statement, receiver: Option<Type>) {
+        let (name, params, body, ret_type) = match &stmt.kind {
+            StatementKind::Function {
+                name,
+                params,
+                body,
+                ret_type,
+                ..
+            } => (name, params, body, ret_type),
+            _ => unreachable!(),
+        };
+
+        let what = format!("function `{}`", name);
+        self.type_exists(ret_type, &what, stmt.span);
+        let mut bindings = Vec::new();
+        if let Some(receiver) = receiver {
+            bindings.push(("self".to_string(), receiver));
+        }
+        for param in params {
+            let what = format!("parameter `{}` of function `{}`", param.name, name);
+            self.type_exists(&param.param_type, &what, stmt.span);
+            if let Some(default) = &param.default {
+                let t = self.expression(default);
+                if !assignable(Some(default), &t, &param.param_type, &self.declarations) {
+                    self.error(
+                        default.span,
+                        format!(
+                            "Default value of parameter `{}` of function `{}` must be `{}`, found {}",
+                            param.name,
+                            name,
+                            type_name(&param.param_type),
+                            found(&t)
+                        ),
+                    );
+                }
+            }
+            bindings.push((param.name.clone(), param.param_type.clone()));
+        }
+
+        let outer = self.function.replace((name.clone(), ret_type.clone()));
+        self.scoped_block(body.stmts(), bindings);
+        self.function = outer;
+
+        if !accepts_nothing(ret_type) && !always_returns(body.stmts()) {
+            self.error(
+                stmt.span,
+                format!(
+                    "Function `{}` must return `{}`, but can reach its end without returning",
+                    name,
+                    type_name(ret_type)
+                ),
+            );
+        }
+    }
+
+    fn return_statement(&mut self, value: Option<&Expression>, span: Span) {
+        let t = value.map(|value| self.expression(value));
+        let (name, ret_type) = match &self.function {
+            Some(function) => function.clone(),
+            None => return,
+        };
+
+        match (value, t) {
+            (Some(value), Some(t))
+                if ret_type == Type::Void && t != Type::Void && t != Type::Any =>
+            {
+                self.error(
+                    value.span,
+                    format!(
+                        "Function `{}` does not return a value, but returns `{}`",
+                        name,
+                        type_name(&t)
+                    ),
+                )
+            }
+            (Some(value), Some(t))
+                if !assignable(Some(value), &t, &ret_type, &self.declarations) =>
+            {
+                self.error(
+                    value.span,
+                    format!(
+                        "Function `{}` must return `{}`, found {}",
+                        name,
+                        type_name(&ret_type),
+                        found(&t)
+                    ),
+                )
+            }
+            (Some(_), _) => {}
+            _ if !accepts_nothing(&ret_type) => self.error(
+                span,
+                format!(
+                    "Function `{}` must return `{}`, but returns nothing",
+                    name,
+                    type_name(&ret_type)
+                ),
+            ),
+            _ => {}
+        }
+    }
+
+    /// `if` conditions are bool
+    fn condition(&mut self, condition: &Expression) {
+        let t = self.expression(condition);
+        if !matches!(t, Type::Bool | Type::Any) {
+            self.error(
+                condition.span,
+                format!("`if` condition must be `bool`, found {}", found(&t)),
+            );
+        }
+    }
+
+    /// The type of the values a `for` loop takes from `iterable`
+    fn element_type(&mut self, iterable: &Expression) -> Type {
+        if is_range(iterable) {
+            self.expression(iterable);
+            return Type::Int;
+        }
+        match self.expression(iterable) {
+            Type::Array(element) => *element,
+            Type::String => Type::String,
+            Type::Any => Type::Any,
+            t => {
+                self.error(
+                    iterable.span,
+                    format!("Cannot loop over a value of type `{}`", type_name(&t)),
+                );
+                Type::Any
+            }
+        }
+    }
+
+    /// Reports struct and interface names in `t` that are declared nowhere
+    fn type_exists(&mut self, t: &Type, what: &str, span: Span) {
+        if self.declarations.imports {
+            return;
+        }
+        for name in named_types(t) {
+            let known = self.declarations.structs.contains_key(name)
+                || self.declarations.interfaces.contains_key(name);
+            if !known {
+                self.error(span, format!("Cannot find type `{}` of {}", name, what));
+            }
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Type {
+        match &expr.kind {
+            ExpressionKind::Short(_) => Type::Short,
+            ExpressionKind::Int(_) => Type::Int,
+            ExpressionKind::Large(_) => Type::Large,
+            ExpressionKind::BigInt(_) => Type::BigInt,
+            ExpressionKind::Float(_) => Type::Float,
+            ExpressionKind::Decimal(_) => Type::Decimal,
+            ExpressionKind::Bool(_) => Type::Bool,
+            ExpressionKind::StringLiteral(_) => Type::String,
+            ExpressionKind::Null => Type::Optional(Box::new(Type::Any)),
+            ExpressionKind::Identifier(name) => self.lookup(name),
+            ExpressionKind::Array(items) => {
+                let mut element = Type::Any;
+                let mut mixed = false;
+                for item in items {
+                    let t = self.expression(item);
+                    match merge(&element, &t) {
+                        Some(joined) => element = joined,
+                        None => mixed = true,
+                    }
+                }
+                if mixed {
+                    element = Type::Any;
+                }
+                Type::Array(Box::new(element))
+            }
+            ExpressionKind::CallExpr { .. } => self.call(expr),
+            ExpressionKind::MethodCall { .. } => self.method_call(expr),
+            ExpressionKind::Field {
+                target,
+                name,
+                optional,
+            } => {
+                let target_type = self.expression(target);
+                let (t, nullable) = self.unwrap_optional(target_type, *optional, expr.span, name);
+                let field = self.field_type(&t, name, expr.span);
+                if nullable {
+                    make_optional(field)
+                } else {
+                    field
+                }
+            }
+            ExpressionKind::Index {
+                target,
+                index,
+                optional,
+            } => {
+                let target_type = self.expression(target);
+                let (t, nullable) = self.unwrap_optional(target_type, *optional, expr.span, "[");
+                let slice = is_range(index);
+                let index_type = self.expression(index);
+                if !slice && !is_numeric(&index_type) && index_type != Type::Any {
+                    self.error(
+                        index.span,
+                        format!(
+                            "Cannot index with a value of type `{}`, expected a number or a range",
+                            type_name(&index_type)
+                        ),
+                    );
+                }
+                let element = match t {
+                    Type::Array(element) if slice => Type::Array(element),
+                    Type::Array(element) => *element,
+                    Type::String => Type::String,
+                    Type::Any => Type::Any,
+                    t => {
+                        self.error(
+                            expr.span,
+                            format!("Cannot index into a value of type `{}`", type_name(&t)),
+                        );
+                        Type::Any
+                    }
+                };
+                if nullable {
+                    make_optional(element)
+                } else {
+                    element
+                }
+            }
+            ExpressionKind::Range {
+                start, end, step, ..
+            } => {
+                for bound in [start, end, step].into_iter().flatten() {
+                    let t = self.expression(bound);
+                    if !is_numeric(&t) && t != Type::Any {
+                        self.error(
+                            bound.span,
+                            format!("Range bounds must be numbers, found {}", found(&t)),
+                        );
+                    }
+                }
+                Type::Any
+            }
+            ExpressionKind::Coalesce { lhs, rhs } => {
+                let lhs = self.expression(lhs);
+                let rhs = self.expression(rhs);
+                match lhs {
+                    Type::Optional(inner) => merge(&inner, &rhs).unwrap_or(Type::Any),
+                    lhs => lhs,
+                }
+            }
+            ExpressionKind::Spread(items) => {
+                let t = self.expression(items);
+                if !matches!(t, Type::Array(_) | Type::Any) {
+                    self.error(
+                        expr.span,
+                        format!(
+                            "Cannot spread a value of type `{}`, expected an array",
+                            type_name(&t)
+                        ),
+                    );
+                }
+                Type::Any
+            }
+            ExpressionKind::BinaryOp { lhs, op, rhs } => {
+                let lhs_type = self.expression(lhs);
+                let rhs_type = self.expression(rhs);
+                let strings = *op == TokenType::Add
+                    && [&lhs_type, &rhs_type].contains(&&Type::String)
+                    && join(&lhs_type, &rhs_type) == Some(Type::String);
+                let shift = matches!(op, TokenType::ShiftLeft | TokenType::ShiftRight);
+                let integers = shift
+                    || matches!(
+                        op,
+                        TokenType::BitwiseAnd | TokenType::BitwiseOr | TokenType::BitwiseXor
+                    );
+                match numeric_join(&lhs_type, &rhs_type) {
+                    _ if strings => Type::String,
+                    // Bitwise operators and shifts only take fixed size
+                    // integers
+                    Some(t) if integers && !is_integer(&t) && t != Type::Any => {
+                        self.error(
+                            expr.span,
+                            format!(
+                                "Cannot apply `{}` to `{}` and `{}`, it takes `short`, `int` or `large` values",
+                                operator(op),
+                                type_name(&lhs_type),
+                                type_name(&rhs_type)
+                            ),
+                        );
+                        Type::Any
+                    }
+                    // A shift keeps the type of what it shifts
+                    Some(_) if shift => lhs_type,
+                    Some(t) => t,
+                    None => {
+                        self.error(
+                            expr.span,
+                            format!(
+                                "Cannot apply `{}` to `{}` and `{}`",
+                                operator(op),
+                                type_name(&lhs_type),
+                                type_name(&rhs_type)
+                            ),
+                        );
+                        Type::Any
+                    }
+                }
+            }
+            ExpressionKind::Conditional { lhs, op, rhs } => {
+                let lhs_type = self.expression(lhs);
+                let rhs_type = self.expression(rhs);
+                let joined = join(&lhs_type, &rhs_type);
+                if op.is_ordering()
+                    && joined
+                        .as_ref()
+                        .is_some_and(|t| *t != Type::Any && !is_numeric(t))
+                {
+                    self.error(
+                        expr.span,
+                        format!(
+                            "Cannot compare `{}` and `{}` with `{}`, it takes numbers",
+                            type_name(&lhs_type),
+                            type_name(&rhs_type),
+                            comparison(op)
+                        ),
+                    );
+                } else if joined.is_none() {
+                    self.error(
+                        expr.span,
+                        format!(
+                            "Cannot compare `{}` and `{}`, they are different types",
+                            type_name(&lhs_type),
+                            type_name(&rhs_type)
+                        ),
+                    );
+                }
+                Type::Bool
+            }
+            ExpressionKind::If {
+                condition,
+                body,
+                else_body,
+            } => {
+                self.condition(condition);
+                let body = self.scoped_block(body, vec![]);
+                let else_body = self.scoped_block(else_body, vec![]);
+                match merge(&body, &else_body) {
+                    Some(t) => t,
+                    None => {
+                        self.error(
+                            expr.span,
+                            format!(
+                                "Branches of this `if` have different types, `{}` and `{}`",
+                                type_name(&body),
+                                type_name(&else_body)
+                            ),
+                        );
+                        Type::Any
+                    }
+                }
+            }
+            ExpressionKind::Propagate(value) => {
+                let t = self.expression(value);
+                if !matches!(t, Type::Result | Type::Any) {
+                    self.error(
+                        expr.span,
+                        format!("`?` expects a result, found {}", found(&t)),
+                    );
+                }
+                Type::Any
+            }
+        }
+    }
+
+    /// Strips the `?` off the type of a value that is read from with `.`,
+    /// `[` or a method call, which needs `?.` if the value may be null.
+    /// Returns the type and whether the result may be null.
+    fn unwrap_optional(&mut self, t: Type, optional: bool, span: Span, what: &str) -> (Type, bool) {
+        match t {
+            Type::Optional(inner) if optional => (*inner, true),
+            Type::Optional(inner) => {
+                let access = if what == "[" {
+                    "Cannot index into".to_string()
+                } else {
+                    format!("Cannot use `.{}` on", what)
+                };
+                self.error(
+                    span,
+                    format!(
+                        "{} a value of type `{}?`, which may be null, use `?.` to allow it",
+                        access,
+                        type_name(&inner)
+                    ),
+                );
+                (*inner, false)
+            }
+            t => (t, optional),
+        }
+    }
+
+    fn field_type(&mut self, t: &Type, field: &str, span: Span) -> Type {
+        match t {
+            Type::Named { name, args } => {
+                let fields = match self.declarations.structs.get(name) {
+                    Some(fields) => fields.clone(),
+                    None => return Type::Any,
+                };
+                match fields.iter().find(|(f, _)| f == field) {
+                    Some((_, field_type)) => {
+                        let params = &self.declarations.struct_params[name];
+                        substitute(field_type, params, args)
+                    }
+                    None => {
+                        self.error(
+                            span,
+                            format!("Struct `{}` has no field named `{}`", name, field),
+                        );
+                        Type::Any
+                    }
+                }
+            }
+            Type::Error if field == "kind" || field == "message" => Type::String,
+            Type::Any => Type::Any,
+            t => {
+                self.error(
+                    span,
+                    format!(
+                        "Cannot find field `{}` for a value of type `{}`",
+                        field,
+                        type_name(t)
+                    ),
+                );
+                Type::Any
+            }
+        }
+    }
+
+    fn call(&mut self, call_expr: &Expression) -> Type {
+        let (name, params, named) = match &call_expr.kind {
+            ExpressionKind::CallExpr {
+                name,
+                params,
+                named,
+            } => (name, params, named),
+            _ => unreachable!(),
+        };
+
+        if let Some((params_of, ret_type)) = built_in(name) {
+            if let Some(arg) = named.first() {
+                self.error(
+                    arg.span,
+                    format!("Built-in function `{}` does not take named arguments", name),
+                );
+            }
+            let type_params = match &ret_type {
+                Type::Param(param) => vec![param.clone()],
+                _ => Vec::new(),
+            };
+            let signature = Signature {
+                owner: format!("function `{}`", name),
+                noun: "parameter",
+                params: &params_of,
+                type_params: &type_params,
+                ret_type: &ret_type,
+                named: false,
+            };
+            let t = self.check_call(&signature, params, &[], call_expr.span);
+            if !type_params.is_empty() && !is_integer(&t) && t != Type::Any {
+                self.error(
+                    call_expr.span,
+                    format!(
+                        "Function `{}` takes `short`, `int` or `large` values, found {}",
+                        name,
+                        found(&t)
+                    ),
+                );
+                return Type::Any;
+            }
+            return t;
+        }
+
+        if let Some(function) = self.find_function(name) {
+            if let Some(message) = deprecation(&function, &format!("Function `{}`", name)) {
+                self.deprecated.push((call_expr.span, message));
+            }
+            if let StatementKind::Function {
+                type_params,
+                params: params_of,
+                ret_type,
+                ..
+            } = &function.kind
+            {
+                let signature = Signature {
+                    owner: format!("function `{}`", name),
+                    noun: "parameter",
+                    params: params_of,
+                    type_params,
+                    ret_type,
+                    named: true,
+                };
+                return self.check_call(&signature, params, named, call_expr.span);
+            }
+        }
+
+        if let Some(fields) = self.declarations.structs.get(name).cloned() {
+            if let Some(message) = self.declarations.deprecated_structs.get(name) {
+                self.deprecated.push((call_expr.span, message.clone()));
+            }
+            let type_params = self.declarations.struct_params[name].clone();
+            let params_of: Vec<Param> = fields
+                .into_iter()
+                .map(|(name, param_type)| Param {
+                    name,
+                    param_type,
+                    default: None,
+                    rest: false,
+                })
+                .collect();
+            let ret_type = Type::Named {
+                name: name.clone(),
+                args: type_params
+                    .iter()
+                    .map(|param| Type::Param(param.clone()))
+                    .collect(),
+            };
+            let signature = Signature {
+                owner: format!("struct `{}`", name),
+                noun: "field",
+                params: &params_of,
+                type_params: &type_params,
+                ret_type: &ret_type,
+                named: true,
+            };
+            return self.check_call(&signature, params, named, call_expr.span);
+        }
+
+        // Maybe imported, the resolver reports it if it is not
+        for arg in params.iter().chain(named.iter().map(|arg| &arg.value)) {
+            self.expression(arg);
+        }
+        Type::Any
+    }
+
+    fn method_call(&mut self, method_call: &Expression) -> Type {
+        let (target, name, params, optional) = match &method_call.kind {
+            ExpressionKind::MethodCall {
+                target,
+                name,
+                params,
+                optional,
+            } => (target, name, params, *optional),
+            _ => unreachable!(),
+        };
+        let target_type = self.expression(target);
+        let (t, nullable) = self.unwrap_optional(target_type, optional, method_call.span, name);
+
+        let found = match &t {
+            Type::Named { name: owner, .. } => {
+                let message = self.impl_method(owner, name).and_then(|method| {
+                    deprecation(method, &format!("Method `{}` of `{}`", name, owner))
+                });
+                if let Some(message) = message {
+                    self.deprecated.push((method_call.span, message));
+                }
+                self.find_method(owner, name)
+            }
+            _ => None,
+        };
+        let ret = match (found, &t) {
+            (Some((owner, params_of, ret_type)), _) => {
+                let signature = Signature {
+                    owner,
+                    noun: "parameter",
+                    params: &params_of,
+                    type_params: &[],
+                    ret_type: &ret_type,
+                    named: false,
+                };
+                self.check_call(&signature, params, &[], method_call.span)
+            }
+            (None, Type::Named { name: owner, .. }) if !self.declarations.imports => {
+                for param in params {
+                    self.expression(param);
+                }
+                self.error(
+                    method_call.span,
+                    format!("Cannot find method `{}` for `{}`", name, owner),
+                );
+                Type::Any
+            }
+            (None, Type::Result) => {
+                for param in params {
+                    self.expression(param);
+                }
+                match name.as_str() {
+                    "is_ok" | "is_err" => Type::Bool,
+                    _ => Type::Any,
+                }
+            }
+            _ => {
+                for param in params {
+                    self.expression(param);
+                }
+                Type::Any
+            }
+        };
+
+        if nullable {
+            make_optional(ret)
+        } else {
+            ret
+        }
+    }
+
+    /// The parameters and return type of method `name` of struct or
+    /// interface `owner`
+    fn find_method(&self, owner: &str, name: &str) -> Option<(String, Vec<Param>, Type)> {
+        if let Some(methods) = self.declarations.interfaces.get(owner) {
+            return methods.iter().find(|m| m.name == name).map(|m| {
+                let what = format!("method `{}` of `{}`", name, owner);
+                (what, m.params.clone(), m.ret_type.clone())
+            });
+        }
+
+        match &self.impl_method(owner, name)?.kind {
+            StatementKind::Function {
+                params, ret_type, ..
+            } => {
+                let what = format!("method `{}` of `{}`", name, owner);
+                Some((what, params.clone(), ret_type.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Method `name` from an `impl` block for struct `owner`
+    fn impl_method(&self, owner: &str, name: &str) -> Option<&Statement> {
+        self.declarations
+            .impls
+            .iter()
+            .filter(|(_, target, _)| target == owner)
+            .flat_map(|(_, _, methods)| methods)
+            .find(|method| matches!(&method.kind, StatementKind::Function { name: n, .. } if n == name))
+    }
+
+    /// Checks the arguments of a call and returns the type of its value
+    fn check_call(
+        &mut self,
+        signature: &Signature,
+        args: &[Expression],
+        named: &[NamedArg],
+        span: Span,
+    ) -> Type {
+        let arg_types: Vec<Type> = args.iter().map(|arg| self.expression(arg)).collect();
+        let named_types: Vec<Type> = named
+            .iter()
+            .map(|arg| self.expression(&arg.value))
+            .collect();
+
+        let (fixed, rest) = match signature.params.split_last() {
+            Some((last, fixed)) if last.rest => (fixed, Some(last)),
+            _ => (signature.params, None),
+        };
+        let spread = args
+            .iter()
+            .any(|arg| matches!(arg.kind, ExpressionKind::Spread(_)));
+
+        // Each parameter's argument, if it was given
+        let mut slots: Vec<Option<(&Expression, &Type)>> = vec![None; fixed.len()];
+        let mut rest_args = Vec::new();
+        for (i, (arg, t)) in args.iter().zip(&arg_types).enumerate() {
+            if matches!(arg.kind, ExpressionKind::Spread(_)) {
+                continue;
+            }
+            match slots.get_mut(i) {
+                Some(slot) if !spread => *slot = Some((arg, t)),
+                Some(_) => {}
+                None => rest_args.push((arg, t)),
+            }
+        }
+
+        let all_required = fixed.iter().all(|param| param.default.is_none());
+        if !spread && rest.is_none() && args.len() > fixed.len() {
+            let at_most = if all_required { "" } else { "at most " };
+            self.error(
+                span,
+                format!(
+                    "{} takes {}{} argument(s) but {} were given",
+                    signature.subject(),
+                    at_most,
+                    fixed.len(),
+                    args.len()
+                ),
+            );
+        }
+
+        for (arg, t) in named.iter().zip(&named_types) {
+            match fixed.iter().position(|param| param.name == arg.name) {
+                Some(i) if slots[i].is_some() => self.error(
+                    arg.span,
+                    format!(
+                        "{} `{}` of {} is given both by position and by name",
+                        if signature.noun == "field" {
+                            "Field"
+                        } else {
+                            "Argument"
+                        },
+                        arg.name,
+                        signature.owner
+                    ),
+                ),
+                Some(i) => slots[i] = Some((&arg.value, t)),
+                None if signature.named => self.error(
+                    arg.span,
+                    format!(
+                        "{} has no {} named `{}`",
+                        signature.subject(),
+                        signature.noun,
+                        arg.name
+                    ),
+                ),
+                None => {}
+            }
+        }
+
+        if !spread {
+            let missing: Vec<&Param> = fixed
+                .iter()
+                .zip(&slots)
+                .filter(|(param, slot)| slot.is_none() && param.default.is_none())
+                .map(|(param, _)| param)
+                .collect();
+            if let Some(param) = missing.first() {
+                if all_required && named.is_empty() && signature.noun == "parameter" {
+                    let at_least = if rest.is_some() { "at least " } else { "" };
+                    self.error(
+                        span,
+                        format!(
+                            "{} takes {}{} argument(s) but {} were given",
+                            signature.subject(),
+                            at_least,
+                            fixed.len(),
+                            args.len()
+                        ),
+                    );
+                } else {
+                    let message = if signature.noun == "field" {
+                        format!(
+                            "Missing value for field `{}` of {}",
+                            param.name, signature.owner
+                        )
+                    } else {
+                        format!(
+                            "Missing argument `{}` in call to {}",
+                            param.name, signature.owner
+                        )
+                    };
+                    self.error(span, message);
+                }
+            }
+        }
+
+        // Type parameters are bound by the arguments, in order
+        let mut bindings: HashMap<String, Type> = HashMap::new();
+        let given = fixed
+            .iter()
+            .zip(&slots)
+            .filter_map(|(param, slot)| slot.map(|slot| (param, &param.param_type, slot)));
+        let element = match rest.map(|rest| &rest.param_type) {
+            Some(Type::Array(element)) => Some(element.as_ref()),
+            _ => None,
+        };
+        let rest_given = rest_args
+            .iter()
+            .filter_map(|arg| Some((rest?, element?, *arg)));
+        for (param, declared, (arg, t)) in given.chain(rest_given).collect::<Vec<_>>() {
+            bind(declared, t, &mut bindings);
+            let expected = apply(declared, signature.type_params, &bindings);
+            if !assignable(Some(arg), t, &expected, &self.declarations) {
+                self.error(
+                    arg.span,
+                    format!(
+                        "{} expects `{}` for {} `{}`, found {}",
+                        signature.subject(),
+                        type_name(&expected),
+                        signature.noun,
+                        param.name,
+                        found(t)
+                    ),
+                );
+            }
+        }
+
+        apply(signature.ret_type, signature.type_params, &bindings)
+    }
+}
+
+/// The signatures of the built-in functions, with `any` where they take
+/// anything
+fn built_in(name: &str) -> Option<(Vec<Param>, Type)> {
+    let param = |name: &str, param_type: Type, optional: bool| Param {
+        name: name.to_string(),
+        param_type,
+        default: optional.then(|| Expression::synthetic(ExpressionKind::Null)),
+        rest: false,
+    };
+    let values = Param {
+        name: "values".to_string(),
+        param_type: Type::Array(Box::new(Type::Any)),
+        default: None,
+        rest: true,
+    };
+
+    let signature = match name {
+        "print" | "println" => (vec![values], Type::Void),
+        "input" => (
+            vec![
+                param("prompt", Type::Any, true),
+                param("_", Type::Any, true),
+            ],
+            Type::String,
+        ),
+        "error" => (
+            vec![
+                param("message", Type::String, false),
+                param("kind", Type::String, true),
+            ],
+            Type::Error,
+        ),
+        "ok" => (vec![param("value", Type::Any, true)], Type::Result),
+        "err" => (vec![param("value", Type::Any, false)], Type::Result),
+        "read_file" => (vec![param("path", Type::String, false)], Type::Result),
+        "write_file" => (
+            vec![
+                param("path", Type::String, false),
+                param("contents", Type::String, false),
+            ],
+            Type::Result,
+        ),
+        "parse_number" => (vec![param("text", Type::String, false)], Type::Result),
+        // Generic over the integer types, giving the wider of the two
+        "wrapping_add" | "wrapping_sub" | "wrapping_mul" | "saturating_add" | "saturating_sub"
+        | "saturating_mul" => {
+            let t = Type::Param("T".to_string());
+            (
+                vec![param("a", t.clone(), false), param("b", t.clone(), false)],
+                t,
+            )
+        }
+        // Conversions take any number, and a `bigint` or `decimal` can be
+        // parsed from a string
+        "short" | "int" | "large" | "bigint" | "float" | "decimal" => (
+            vec![param("value", Type::Any, false)],
+            Type::from_string(name).unwrap(),
+        ),
+        "round" => (
+            vec![
+                param("value", Type::Decimal, false),
+                param("places", Type::Int, true),
+                param("mode", Type::String, true),
+            ],
+            Type::Decimal,
+        ),
+        _ => return None,
+    };
+    Some(signature)
+}
+
+fn is_numeric(t: &Type) -> bool {
+    matches!(
+        t,
+        Type::Short | Type::Int | Type::Large | Type::BigInt | Type::Float | Type::Decimal
+    )
+}
+
+fn is_integer(t: &Type) -> bool {
+    matches!(t, Type::Short | Type::Int | Type::Large)
+}
+
+/// How far along the numeric tower a type is. `float` and `decimal` are
+/// both at the top, and neither widens to the other.
+fn rank(t: &Type) -> u8 {
+    match t {
+        Type::Short => 0,
+        Type::Int => 1,
+        Type::Large => 2,
+        Type::BigInt => 3,
+        _ => 4,
+    }
+}
+
+/// The type of arithmetic on `a` and `b`, if both are numbers
+fn numeric_join(a: &Type, b: &Type) -> Option<Type> {
+    match (a, b) {
+        (Type::Any, Type::Any) => Some(Type::Any),
+        (Type::Any, t) | (t, Type::Any) if is_numeric(t) => Some(t.clone()),
+        (a, b) if is_numeric(a) && is_numeric(b) => match rank(a).cmp(&rank(b)) {
+            Ordering::Greater => Some(a.clone()),
+            Ordering::Less => Some(b.clone()),
+            Ordering::Equal => (a == b).then(|| a.clone()),
+        },
+        _ => None,
+    }
+}
+
+/// The most specific type both `a` and `b` fit, numbers widening
+fn join(a: &Type, b: &Type) -> Option<Type> {
+    match (a, b) {
+        (Type::Any, t) | (t, Type::Any) => Some(t.clone()),
+        (a, b) if is_numeric(a) && is_numeric(b) => numeric_join(a, b),
+        (Type::Optional(a), Type::Optional(b)) => Some(make_optional(join(a, b)?)),
+        (Type::Optional(a), b) | (b, Type::Optional(a)) => Some(make_optional(join(a, b)?)),
+        (Type::Array(a), Type::Array(b)) => Some(Type::Array(Box::new(join(a, b)?))),
+        (a, b) if a == b => Some(a.clone()),
+        _ => None,
+    }
+}
+
+/// The type of a value that is either an `a` or a `b`, like the value of
+/// an `if` with branches of those types. Unlike in `join`, numbers do not
+/// widen: nothing converts the value, so an `int` branch gives an `int`.
+fn merge(a: &Type, b: &Type) -> Option<Type> {
+    match (a, b) {
+        (Type::Any, t) | (t, Type::Any) => Some(t.clone()),
+        (Type::Optional(a), Type::Optional(b)) => Some(make_optional(merge(a, b)?)),
+        (Type::Optional(a), b) | (b, Type::Optional(a)) => Some(make_optional(merge(a, b)?)),
+        (Type::Array(a), Type::Array(b)) => Some(Type::Array(Box::new(merge(a, b)?))),
+        (a, b) if a == b => Some(a.clone()),
+        _ => None,
+    }
+}
+
+fn make_optional(t: Type) -> Type {
+    match t {
+        Type::Optional(_) | Type::Any => t,
+        t => Type::Optional(Box::new(t)),
+    }
+}
+
+/// Whether a value of type `from` can be given where `to` is expected.
+/// `expr` is the value, when there is one, so that integer literals can
+/// narrow.
+fn assignable(
+    expr: Option<&Expression>,
+    from: &Type,
+    to: &Type,
+    declarations: &Declarations,
+) -> bool {
+    match (from, to) {
+        (Type::Any, _) | (_, Type::Any) | (Type::Param(_), _) | (_, Type::Param(_)) => true,
+        (Type::Optional(from), Type::Optional(to)) => assignable(expr, from, to, declarations),
+        (from, Type::Optional(to)) => assignable(expr, from, to, declarations),
+        (Type::Optional(_), _) => false,
+        (from, to) if is_numeric(from) && is_numeric(to) => {
+            rank(from) < rank(to) || from == to || expr.is_some_and(|expr| literal_fits(expr, to))
+        }
+        (Type::Array(from), Type::Array(to)) => assignable(None, from, to, declarations),
+        (
+            Type::Named { name, args },
+            Type::Named {
+                name: other,
+                args: other_args,
+            },
+        ) => {
+            if name == other {
+                args.len() != other_args.len()
+                    || args
+                        .iter()
+                        .zip(other_args)
+                        .all(|(a, b)| assignable(None, a, b, declarations))
+            } else {
+                declarations.interfaces.contains_key(other)
+                    && declarations
+                        .impls
+                        .iter()
+                        .any(|(interface, target, _)| interface == other && target == name)
+            }
+        }
+        (from, to) => from == to,
+    }
+}
+
+/// Whether `expr` is an integer literal in range of integer type `to`
+fn literal_fits(expr: &Expression, to: &Type) -> bool {
+    let value = match expr.kind {
+        ExpressionKind::Short(value) => value as i64,
+        ExpressionKind::Int(value) => value as i64,
+        ExpressionKind::Large(value) => value,
+        _ => return false,
+    };
+    match to {
+        Type::Short => i16::try_from(value).is_ok(),
+        Type::Int => i32::try_from(value).is_ok(),
+        Type::Large => true,
+        _ => false,
+    }
+}
+
+/// Binds the type parameters in `declared` from the type of an argument
+fn bind(declared: &Type, actual: &Type, bindings: &mut HashMap<String, Type>) {
+    match (declared, actual) {
+        (_, Type::Any) => {}
+        (Type::Param(name), actual) => {
+            let bound = bindings.get(name).cloned().unwrap_or(Type::Any);
+            if let Some(t) = join(&bound, actual) {
+                bindings.insert(name.clone(), t);
+            }
+        }
+        (Type::Array(declared), Type::Array(actual)) => bind(declared, actual, bindings),
+        (Type::Optional(declared), Type::Optional(actual)) => bind(declared, actual, bindings),
+        (Type::Optional(declared), actual) => bind(declared, actual, bindings),
+        (Type::Named { args, .. }, Type::Named { args: actual, .. })
+            if args.len() == actual.len() =>
+        {
+            for (declared, actual) in args.iter().zip(actual) {
+                bind(declared, actual, bindings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `t` with the type parameters of the callee replaced by what they are
+/// bound to, `any` if nothing
+fn apply(t: &Type, type_params: &[String], bindings: &HashMap<String, Type>) -> Type {
+    match t {
+        Type::Param(name) if type_params.contains(name) => {
+            bindings.get(name).cloned().unwrap_or(Type::Any)
+        }
+        Type::Array(element) => Type::Array(Box::new(apply(element, type_params, bindings))),
+        Type::Optional(inner) => make_optional(apply(inner, type_params, bindings)),
+        Type::Named { name, args } => Type::Named {
+            name: name.clone(),
+            args: args
+                .iter()
+                .map(|arg| apply(arg, type_params, bindings))
+                .collect(),
+        },
+        _ => t.clone(),
+    }
+}
+
+/// The type of a field of a generic struct, given the struct's type
+/// arguments
+fn substitute(t: &Type, params: &[String], args: &[Type]) -> Type {
+    if params.len() != args.len() {
+        return apply(t, params, &HashMap::new());
+    }
+    let bindings = params.iter().cloned().zip(args.iter().cloned()).collect();
+    apply(t, params, &bindings)
+}
+
+/// How a type is named in a message saying what was found instead, the
+/// type of `null` being no type a program can write
+fn found(t: &Type) -> String {
+    match t {
+        Type::Optional(inner) if **inner == Type::Any => "null".to_string(),
+        t => format!("`{}`", type_name(t)),
+    }
+}
+
+/// Whether running `nodes` always ends in a `return` or a `throw`
+fn always_returns(nodes: &[Ast]) -> bool {
+    nodes.iter().any(|node| {
+        let stmt = match node {
+            Ast::Statement(stmt) => stmt,
+            Ast::Expression(_) => return false,
+        };
+        match &stmt.kind {
+            StatementKind::Return(_) | StatementKind::Throw(_) => true,
+            StatementKind::IfStatement {
+                body,
+                else_body: Some(else_body),
+                ..
+            } => always_returns(body) && always_returns(else_body),
+            StatementKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                finally.as_deref().is_some_and(always_returns)
+                    || (always_returns(body)
+                        && catch
+                            .as_ref()
+                            .is_none_or(|catch| always_returns(&catch.body)))
+            }
+            _ => false,
+        }
+    })
+}
+
+/// A function without a return type, or with one that allows null, may
+/// `return;`
+fn accepts_nothing(t: &Type) -> bool {
+    matches!(t, Type::Void | Type::Any | Type::Optional(_))
+}
+
+fn is_range(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Range { .. } => true,
+        ExpressionKind::MethodCall { target, name, .. } => name == "step" && is_range(target),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_program;
+    use crate::syntax::parse::parse_source;
+
+    /// The message of each type error in `source`
+    fn errors(source: &str) -> Vec<String> {
+        let nodes = parse_source(source).expect("test source parses");
+        let errors = check_program(&nodes).errors;
+        errors.into_iter().map(|e| e.message).collect()
+    }
+
+    #[test]
+    fn blocks_are_scopes() {
+        let source = r#"
+            x := 1;
+            c := true;
+            if c { x := "s"; println(x); } else { x := "e"; }
+            try { x := "t"; } finally { x := "f"; }
+            v := if c { x := "w"; x } else { "z" };
+            y := x + 1;
+        "#;
+        assert_eq!(errors(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn nested_functions_are_scoped() {
+        let source = r#"
+            fun a() > int {
+                fun h(n int) > int { return n; }
+                return h(1);
+            }
+            fun b() > string {
+                fun h(s string) > string { return s; }
+                return h("x");
+            }
+            println(a(), b());
+        "#;
+        assert_eq!(errors(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn missing_return() {
+        let source = r#"
+            fun f() > int { }
+            fun g(n int) > int {
+                if n == 1 { return 1; } else { throw "no"; }
+            }
+            fun h() > int? { }
+        "#;
+        assert_eq!(
+            errors(source),
+            vec!["Function `f` must return `int`, but can reach its end without returning"]
+        );
+    }
+
+    #[test]
+    fn null_argument() {
+        let source = "fun k(s string) > string { return s; }\nk(null);";
+        assert_eq!(
+            errors(source),
+            vec!["Function `k` expects `string` for parameter `s`, found null"]
+        );
+    }
+
+    #[test]
+    fn if_branch_types() {
+        let source = r#"
+            s := "x";
+            label := if s == "x" { s + "!" } else { "none" };
+            n := if s == "x" { 1 } elif s == "y" { 2 } else { 3 };
+            m := if s == "x" { null } else { 2.5 };
+            half := if s == "x" { 1 } else { 2.5 };
+            bad := if s == "x" { 1 } else { "none" };
+        "#;
+        // The value of an `if` is not converted, so numbers do not widen
+        assert_eq!(
+            errors(source),
+            vec![
+                "Branches of this `if` have different types, `int` and `float`",
+                "Branches of this `if` have different types, `int` and `string`",
+            ]
+        );
+    }
+
+    #[test]
+    fn mixed_numbers_do_not_widen() {
+        // An array holds its elements as they are, and `??` gives either
+        // side as it is, so mixing number types gives `any`
+        let source = r#"
+            fun ints(xs array<int>) > int { return 0; }
+            fun int_of(x int) > int { return x; }
+            ints([1.5, 2.5]);
+            ints([1, 2.5]);
+            maybe := if ints([]) == 0 { 1 } else { null };
+            int_of(maybe ?? 2.5);
+            int_of(maybe ?? 2);
+        "#;
+        assert_eq!(
+            errors(source),
+            vec!["Function `ints` expects `array<int>` for parameter `xs`, found `array<float>`"]
+        );
+    }
+
+    #[test]
+    fn ordering_comparisons() {
+        let source = r#"
+            a := 1 < 2.5;
+            b := 2n >= 1;
+            c := 1.5d <= 2;
+            d := "a" < "b";
+            e := 1.5d > 2.5;
+        "#;
+        assert_eq!(
+            errors(source),
+            vec![
+                "Cannot compare `string` and `string` with `<`, it takes numbers",
+                "Cannot compare `decimal` and `float`, they are different types",
+            ]
+        );
+    }
+}
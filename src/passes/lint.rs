@@ -0,0 +1,806 @@
+//! Lints, the mistakes a program can run with but probably should not
+//! have.
+//!
+//! Each lint is at one of three levels: `allow` says nothing, `warn`
+//! reports a warning and runs the program anyway, and `deny` reports an
+//! error and does not run it. Every lint warns unless told otherwise by a
+//! command line flag, e.g. `--deny=shadowing`, or by a pragma comment in
+//! the file, e.g. `// velo: allow(unused_variable, shadowing)`, which wins
+//! over the flags. `warnings` stands for every lint at once.
+
+use std::collections::HashMap;
+
+use crate::error::{
+    ErrorType::{LintError, Warning},
+    VeloError,
+};
+use crate::runtime::eval::numbers;
+use crate::runtime::value::Value;
+use crate::syntax::ast::visit::{walk_expression, walk_statement, Visitor};
+use crate::syntax::ast::{
+    Ast, ConditionType, Expression, ExpressionKind, Statement, StatementKind,
+};
+use crate::syntax::span::Span;
+use crate::utils::{capitalize, interpolated_names};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedFunction,
+    Shadowing,
+    UnreachableCode,
+    ConstantCondition,
+    UnusedImmut,
+    Deprecated,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 7] = [
+        Lint::UnusedVariable,
+        Lint::UnusedFunction,
+        Lint::Shadowing,
+        Lint::UnreachableCode,
+        Lint::ConstantCondition,
+        Lint::UnusedImmut,
+        Lint::Deprecated,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused_variable",
+            Lint::UnusedFunction => "unused_function",
+            Lint::Shadowing => "shadowing",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::ConstantCondition => "constant_condition",
+            Lint::UnusedImmut => "unused_immut",
+            Lint::Deprecated => "deprecated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    pub fn from_string(level: &str) -> Option<Level> {
+        match level {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The level of every lint
+#[derive(Debug, Clone)]
+pub struct LintLevels {
+    levels: HashMap<Lint, Level>,
+}
+
+impl Default for LintLevels {
+    fn default() -> Self {
+        LintLevels {
+            levels: Lint::ALL.iter().map(|lint| (*lint, Level::Warn)).collect(),
+        }
+    }
+}
+
+impl LintLevels {
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels[&lint]
+    }
+
+    /// Sets lint `name`, or every lint for `warnings`. Returns false when
+    /// there is no such lint.
+    pub fn set(&mut self, name: &str, level: Level) -> bool {
+        if name == "warnings" {
+            for lint in Lint::ALL {
+                self.levels.insert(lint, level);
+            }
+            return true;
+        }
+        match Lint::ALL.iter().find(|lint| lint.name() == name) {
+            Some(lint) => {
+                self.levels.insert(*lint, level);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Applies the `// velo: level(lint, ...)` pragmas in `source`,
+    /// returning a warning for each one that makes no sense
+    pub fn apply_pragmas(&mut self, source: &str) -> Vec<VeloError> {
+        let mut warnings = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let pragma = match line.trim().strip_prefix("//") {
+                Some(comment) => match comment.trim().strip_prefix("velo:") {
+                    Some(pragma) => pragma.trim(),
+                    None => continue,
+                },
+                None => continue,
+            };
+            let span = Span {
+                start: 0,
+                end: 0,
+                line: i + 1,
+                column: 0,
+            };
+
+            let parsed = pragma
+                .strip_suffix(')')
+                .and_then(|pragma| pragma.split_once('('))
+                .and_then(|(level, lints)| Some((Level::from_string(level.trim())?, lints)));
+            let (level, lints) = match parsed {
+                Some(parsed) => parsed,
+                None => {
+                    let message = format!(
+                        "Cannot understand pragma `{}`, expected `allow(...)`, `warn(...)` or `deny(...)`",
+                        pragma
+                    );
+                    warnings.push(VeloError::error(span, &message, Warning));
+                    continue;
+                }
+            };
+            for lint in lints.split(',').map(str::trim).filter(|l| !l.is_empty()) {
+                if !self.set(lint, level) {
+                    let message = format!("Unknown lint `{}`", lint);
+                    warnings.push(VeloError::error(span, &message, Warning));
+                }
+            }
+        }
+        warnings
+    }
+}
+
+/// Lints a program, returning a warning for each lint that warns and an
+/// error for each that is denied. `deprecated` is what `analyze` found.
+pub fn lint_program(
+    nodes: &[Ast],
+    deprecated: &[(Span, String)],
+    levels: &LintLevels,
+) -> Vec<VeloError> {
+    let mut linter = Linter {
+        levels,
+        scopes: Vec::new(),
+        bindings: Vec::new(),
+        function_body: false,
+        functions: Vec::new(),
+        pending_reads: Vec::new(),
+        pending_calls: Vec::new(),
+        found: Vec::new(),
+    };
+    linter.visit_block(nodes);
+    // Which declaration a call reaches takes the types the checker works
+    // out, a method call's especially
+    for (span, message) in deprecated {
+        linter.report(Lint::Deprecated, *span, message.clone());
+    }
+
+    linter
+        .found
+        .sort_by_key(|error| (error.span.line, error.span.column));
+    linter.found
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VariableKind {
+    Variable,
+    Immut,
+    Parameter,
+    LoopVariable,
+    CatchVariable,
+}
+
+impl VariableKind {
+    fn noun(&self) -> &'static str {
+        match self {
+            VariableKind::Variable | VariableKind::Immut => "variable",
+            VariableKind::Parameter => "parameter",
+            VariableKind::LoopVariable => "loop variable",
+            VariableKind::CatchVariable => "catch variable",
+        }
+    }
+}
+
+struct Variable {
+    name: String,
+    kind: VariableKind,
+    span: Span,
+    read: bool,
+    /// Annotated declarations may be read by the host, not the program
+    exempt: bool,
+}
+
+struct Function {
+    name: String,
+    span: Span,
+    called: bool,
+    exempt: bool,
+}
+
+#[derive(Default)]
+struct Scope {
+    variables: Vec<Variable>,
+    functions: Vec<Function>,
+    function_body: bool,
+}
+
+struct Linter<'a> {
+    levels: &'a LintLevels,
+    scopes: Vec<Scope>,
+    /// Declared in the next block before anything in it, e.g. parameters
+    bindings: Vec<Variable>,
+    function_body: bool,
+    /// The functions being linted, innermost last, with the scope each is
+    /// declared in
+    functions: Vec<(String, usize)>,
+    /// Names read or called from a function body that were not declared
+    /// yet, which can be declared later in any scope up to the index
+    pending_reads: Vec<(String, usize)>,
+    pending_calls: Vec<(String, usize)>,
+    found: Vec<VeloError>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, lint: Lint, span: Span, message: String) {
+        let message = format!("{} ({})", message, lint.name());
+        let error_type = match self.levels.level(lint) {
+            Level::Allow => return,
+            Level::Warn => Warning,
+            Level::Deny => LintError,
+        };
+        self.found
+            .push(VeloError::error(span, &message, error_type));
+    }
+
+    fn declare(&mut self, variable: Variable) {
+        if !variable.name.starts_with('_') {
+            // Only within the function, a parameter with the name of a
+            // variable outside is not a mistake
+            let shadowed = self
+                .scopes
+                .iter()
+                .rev()
+                .skip(1)
+                .scan(
+                    self.scopes.last().unwrap().function_body,
+                    |inside, scope| {
+                        let visible = !*inside;
+                        *inside |= scope.function_body;
+                        visible.then_some(scope)
+                    },
+                )
+                .find_map(|scope| scope.variables.iter().find(|v| v.name == variable.name));
+            if let Some(shadowed) = shadowed {
+                let message = format!(
+                    "{} `{}` shadows the {} declared on line {}",
+                    capitalize(variable.kind.noun()),
+                    variable.name,
+                    shadowed.kind.noun(),
+                    shadowed.span.line
+                );
+                self.report(Lint::Shadowing, variable.span, message);
+            }
+        }
+        self.scopes.last_mut().unwrap().variables.push(variable);
+    }
+
+    fn read(&mut self, name: &str) {
+        let mut bound = None;
+        for (i, scope) in self.scopes.iter_mut().enumerate().rev() {
+            if let Some(variable) = scope.variables.iter_mut().rev().find(|v| v.name == name) {
+                variable.read = true;
+                return;
+            }
+            if scope.function_body && bound.is_none() {
+                bound = Some(i.saturating_sub(1));
+            }
+        }
+        if let Some(bound) = bound {
+            self.pending_reads.push((name.to_string(), bound));
+        }
+    }
+
+    fn call(&mut self, name: &str) {
+        let mut bound = None;
+        for (i, scope) in self.scopes.iter_mut().enumerate().rev() {
+            if let Some(function) = scope.functions.iter_mut().rev().find(|f| f.name == name) {
+                // Calling itself does not make a function used
+                let recursive = self
+                    .functions
+                    .iter()
+                    .any(|(current, declared)| current == name && *declared == i);
+                function.called |= !recursive;
+                return;
+            }
+            if scope.function_body && bound.is_none() {
+                bound = Some(i.saturating_sub(1));
+            }
+        }
+        if let Some(bound) = bound {
+            self.pending_calls.push((name.to_string(), bound));
+        }
+    }
+
+    /// Reports what was never used in the innermost scope, and leaves it
+    fn pop_scope(&mut self) {
+        let depth = self.scopes.len() - 1;
+        let mut scope = self.scopes.pop().unwrap();
+
+        for variable in scope.variables.iter_mut() {
+            if let Some(i) = self
+                .pending_reads
+                .iter()
+                .position(|(name, bound)| *name == variable.name && *bound >= depth)
+            {
+                self.pending_reads.remove(i);
+                variable.read = true;
+            }
+        }
+        for function in scope.functions.iter_mut() {
+            while let Some(i) = self
+                .pending_calls
+                .iter()
+                .position(|(name, bound)| *name == function.name && *bound >= depth)
+            {
+                self.pending_calls.remove(i);
+                function.called = true;
+            }
+        }
+
+        for variable in scope.variables {
+            if variable.read || variable.exempt || variable.name.starts_with('_') {
+                continue;
+            }
+            let (lint, message) = match variable.kind {
+                VariableKind::Parameter => continue,
+                VariableKind::Immut => (
+                    Lint::UnusedImmut,
+                    format!("Immutable variable `{}` is never read", variable.name),
+                ),
+                kind => (
+                    Lint::UnusedVariable,
+                    format!(
+                        "{} `{}` is never used",
+                        capitalize(kind.noun()),
+                        variable.name
+                    ),
+                ),
+            };
+            self.report(lint, variable.span, message);
+        }
+        for function in scope.functions {
+            if !function.called && !function.exempt && !function.name.starts_with('_') {
+                let message = format!("Function `{}` is never called", function.name);
+                self.report(Lint::UnusedFunction, function.span, message);
+            }
+        }
+    }
+
+    /// Lints a function or method, its parameters declared in its body
+    fn function(&mut self, stmt: &Statement, method: bool) {
+        let (name, params, body) = match &stmt.kind {
+            StatementKind::Function {
+                name, params, body, ..
+            } => (name, params, body),
+            _ => unreachable!(),
+        };
+        for default in params.iter().filter_map(|param| param.default.as_ref()) {
+            self.visit_expression(default);
+        }
+
+        for param in params {
+            self.bindings.push(Variable {
+                name: param.name.clone(),
+                kind: VariableKind::Parameter,
+                span: stmt.span,
+                read: false,
+                exempt: false,
+            });
+        }
+        if !method {
+            self.functions.push((name.clone(), self.scopes.len() - 1));
+        }
+        self.function_body = true;
+        self.visit_block(body.stmts());
+        if !method {
+            self.functions.pop();
+        }
+    }
+
+    fn condition(&mut self, condition: &Expression) {
+        if let Some(value) = constant(condition) {
+            let message = match value {
+                Constant::Bool(value) => format!("`if` condition is always {}", value),
+                _ => "`if` condition is a constant".to_string(),
+            };
+            self.report(Lint::ConstantCondition, condition.span, message);
+        }
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn visit_block(&mut self, block: &[Ast]) {
+        self.scopes.push(Scope {
+            function_body: std::mem::take(&mut self.function_body),
+            ..Scope::default()
+        });
+        for variable in std::mem::take(&mut self.bindings) {
+            self.declare(variable);
+        }
+
+        for (i, node) in block.iter().enumerate() {
+            self.visit_ast(node);
+            let keyword = match node {
+                Ast::Statement(stmt) => match stmt.kind {
+                    StatementKind::Return(_) => Some("return"),
+                    StatementKind::Throw(_) => Some("throw"),
+                    _ => None,
+                },
+                Ast::Expression(_) => None,
+            };
+            if let (Some(keyword), Some(next)) = (keyword, block.get(i + 1)) {
+                let span = next.span().to(block.last().unwrap().span());
+                let message = format!("Unreachable code after `{}`", keyword);
+                self.report(Lint::UnreachableCode, span, message);
+                // What cannot run is still linted
+                for node in &block[i + 1..] {
+                    self.visit_ast(node);
+                }
+                break;
+            }
+        }
+
+        self.pop_scope();
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        let exempt = !stmt.annotations.is_empty();
+        match &stmt.kind {
+            StatementKind::VariableAssignment {
+                constant,
+                name,
+                value,
+            } => {
+                self.visit_expression(value);
+                let kind = if *constant {
+                    VariableKind::Immut
+                } else {
+                    VariableKind::Variable
+                };
+                self.declare(Variable {
+                    name: name.clone(),
+                    kind,
+                    span: stmt.span,
+                    read: false,
+                    exempt,
+                });
+            }
+            StatementKind::Function { name, .. } => {
+                self.scopes.last_mut().unwrap().functions.push(Function {
+                    name: name.clone(),
+                    span: stmt.span,
+                    called: false,
+                    exempt: stmt.annotation("test").is_some(),
+                });
+                self.function(stmt, false);
+            }
+            StatementKind::Impl { methods, .. } => {
+                for method in methods {
+                    self.function(method, true);
+                }
+            }
+            StatementKind::IfStatement { condition, .. } => {
+                self.condition(condition);
+                walk_statement(self, stmt);
+            }
+            StatementKind::For {
+                name,
+                iterable,
+                body,
+            } => {
+                self.visit_expression(iterable);
+                self.bindings.push(Variable {
+                    name: name.clone(),
+                    kind: VariableKind::LoopVariable,
+                    span: stmt.span,
+                    read: false,
+                    exempt: false,
+                });
+                self.visit_block(body);
+            }
+            StatementKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.visit_block(body);
+                if let Some(catch) = catch {
+                    if let Some(name) = &catch.name {
+                        self.bindings.push(Variable {
+                            name: name.clone(),
+                            kind: VariableKind::CatchVariable,
+                            span: stmt.span,
+                            read: false,
+                            exempt: false,
+                        });
+                    }
+                    self.visit_block(&catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.visit_block(finally);
+                }
+            }
+            _ => walk_statement(self, stmt),
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            // A name may be a variable or a function used as a value, and
+            // a call may be to the function a variable holds
+            ExpressionKind::Identifier(name) => {
+                self.read(name);
+                self.call(name);
+            }
+            ExpressionKind::StringLiteral(str) => {
+                for name in interpolated_names(str) {
+                    self.read(&name);
+                }
+            }
+            ExpressionKind::CallExpr { name, .. } => {
+                self.call(name);
+                self.read(name);
+                walk_expression(self, expr);
+            }
+            ExpressionKind::If { condition, .. } => {
+                self.condition(condition);
+                walk_expression(self, expr);
+            }
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Constant {
+    Number(Value),
+    Bool(bool),
+    String(String),
+}
+
+/// The value of an expression made only of literals
+fn constant(expr: &Expression) -> Option<Constant> {
+    match &expr.kind {
+        ExpressionKind::Short(_)
+        | ExpressionKind::Int(_)
+        | ExpressionKind::Large(_)
+        | ExpressionKind::BigInt(_)
+        | ExpressionKind::Float(_)
+        | ExpressionKind::Decimal(_) => numbers::literal_value(&expr.kind).map(Constant::Number),
+        ExpressionKind::Bool(value) => Some(Constant::Bool(*value)),
+        ExpressionKind::StringLiteral(str) if interpolated_names(str).is_empty() => {
+            Some(Constant::String(str.clone()))
+        }
+        ExpressionKind::BinaryOp { lhs, op, rhs } => match (constant(lhs)?, constant(rhs)?) {
+            (Constant::Number(lhs), Constant::Number(rhs)) => {
+                numbers::binary(op, &lhs, &rhs).ok().map(Constant::Number)
+            }
+            _ => None,
+        },
+        ExpressionKind::Conditional { lhs, op, rhs } => {
+            let (lhs, rhs) = (constant(lhs)?, constant(rhs)?);
+            match (op, lhs, rhs) {
+                (ConditionType::Equal, lhs, rhs) => Some(Constant::Bool(lhs == rhs)),
+                (ConditionType::NotEqual, lhs, rhs) => Some(Constant::Bool(lhs != rhs)),
+                (ConditionType::Unary, _, _) => None,
+                (op, Constant::Number(lhs), Constant::Number(rhs)) => {
+                    numbers::ordering(op, &lhs, &rhs).ok().map(Constant::Bool)
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint_program, Level, Lint, LintLevels};
+    use crate::error::ErrorType::{LintError, Warning};
+    use crate::passes::analyze;
+    use crate::syntax::parse::parse_source;
+
+    /// The message of each lint found in `source` at the default levels
+    fn lints(source: &str) -> Vec<String> {
+        let nodes = parse_source(source).expect("test source parses");
+        let found = lint_program(&nodes, &analyze(&nodes).deprecated, &LintLevels::default());
+        found.into_iter().map(|e| e.message).collect()
+    }
+
+    const DEPRECATED: &str = r#"
+        @deprecated("use g")
+        fun f() > int { return 1; }
+        @deprecated
+        struct P { x int }
+        type Shape interface { fun area(self) > int }
+        impl Shape for P {
+            @deprecated("use size")
+            fun area(self) > int { return self.x; }
+        }
+        fun main() {
+            fun f() > int { return 2; }
+            println(f());
+        }
+        p := P(1);
+        println(f() + p.area());
+        main();
+    "#;
+
+    #[test]
+    fn deprecated_uses() {
+        let nodes = parse_source(DEPRECATED).expect("test source parses");
+        let found = lint_program(&nodes, &analyze(&nodes).deprecated, &LintLevels::default());
+        let messages: Vec<&str> = found.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "Struct `P` is deprecated (deprecated)",
+                "Function `f` is deprecated: use g (deprecated)",
+                "Method `area` of `P` is deprecated: use size (deprecated)",
+            ]
+        );
+        assert!(found.iter().all(|e| e.error_type == Warning));
+
+        let mut levels = LintLevels::default();
+        levels.set("deprecated", Level::Deny);
+        let found = lint_program(&nodes, &analyze(&nodes).deprecated, &levels);
+        assert!(found.len() == 3 && found.iter().all(|e| e.error_type == LintError));
+
+        levels.apply_pragmas("// velo: allow(deprecated)");
+        assert!(lint_program(&nodes, &analyze(&nodes).deprecated, &levels).is_empty());
+    }
+
+    #[test]
+    fn unused_variables_and_functions() {
+        let source = r#"
+            x := 1;
+            _skip := 2;
+            used := 3;
+            println(used);
+            fun helper() {}
+            fun forever(n int) > int { return forever(n); }
+            fun caller() > int { return later(); }
+            fun later() > int { return 1; }
+            caller();
+            for i in [1] {}
+            immut limit := 4;
+            @config
+            port := 80;
+        "#;
+        assert_eq!(
+            lints(source),
+            [
+                "Variable `x` is never used (unused_variable)",
+                "Function `helper` is never called (unused_function)",
+                "Function `forever` is never called (unused_function)",
+                "Loop variable `i` is never used (unused_variable)",
+                "Immutable variable `limit` is never read (unused_immut)",
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowing() {
+        let source = r#"
+            n := 1;
+            if n > 0 {
+                n := 2;
+                println(n);
+            }
+            fun f(n int) > int {
+                m := n;
+                return m;
+            }
+            println(f(n));
+        "#;
+        assert_eq!(
+            lints(source),
+            ["Variable `n` shadows the variable declared on line 2 (shadowing)"]
+        );
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let source = r#"
+            fun f() > int {
+                return 1;
+                println("never");
+                unused := 2;
+            }
+            fun g() {
+                throw "no";
+                println("never");
+            }
+            f();
+            g();
+        "#;
+        assert_eq!(
+            lints(source),
+            [
+                "Unreachable code after `return` (unreachable_code)",
+                "Variable `unused` is never used (unused_variable)",
+                "Unreachable code after `throw` (unreachable_code)",
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_condition() {
+        let source = r#"
+            if 1 < 2 { println("a"); }
+            if "a" == "b" { println("b"); }
+            if 1 + 1 { println("c"); }
+            x := 3;
+            if x > 2 { println("d"); }
+        "#;
+        assert_eq!(
+            lints(source),
+            [
+                "`if` condition is always true (constant_condition)",
+                "`if` condition is always false (constant_condition)",
+                "`if` condition is a constant (constant_condition)",
+            ]
+        );
+    }
+
+    #[test]
+    fn pragmas() {
+        let mut levels = LintLevels::default();
+        let source =
+            "x := 1;\n// velo: allow(unused_variable, shadowing)\n//velo:deny( unused_function )";
+        assert!(levels.apply_pragmas(source).is_empty());
+        assert_eq!(levels.level(Lint::UnusedVariable), Level::Allow);
+        assert_eq!(levels.level(Lint::Shadowing), Level::Allow);
+        assert_eq!(levels.level(Lint::UnusedFunction), Level::Deny);
+        assert_eq!(levels.level(Lint::UnusedImmut), Level::Warn);
+
+        let source = "// velo: forbid(shadowing)\n// velo: warn(typo, warnings)\n// velo allow(x)";
+        let warnings = levels.apply_pragmas(source);
+        // The last line is a plain comment, without `velo:`
+        let found: Vec<(usize, &str)> = warnings
+            .iter()
+            .map(|e| (e.span.line, e.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, "Cannot understand pragma `forbid(shadowing)`, expected `allow(...)`, `warn(...)` or `deny(...)`"),
+                (2, "Unknown lint `typo`"),
+            ]
+        );
+        assert!(warnings.iter().all(|e| e.error_type == Warning));
+        // The lints after an unknown one are still set
+        assert!(Lint::ALL
+            .iter()
+            .all(|lint| levels.level(*lint) == Level::Warn));
+    }
+
+    #[test]
+    fn pragmas_win_over_flags() {
+        let source = "// velo: warn(unused_variable)\nx := 1;";
+        let nodes = parse_source(source).expect("test source parses");
+        let mut levels = LintLevels::default();
+        levels.set("warnings", Level::Deny);
+        levels.apply_pragmas(source);
+        let found = lint_program(&nodes, &analyze(&nodes).deprecated, &levels);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].error_type, Warning);
+    }
+}
@@ -0,0 +1,31 @@
+//! Passes over the syntax tree that run before the interpreter
+
+pub mod check;
+pub mod lint;
+pub mod optimize;
+pub mod resolve;
+
+use crate::error::VeloError;
+use crate::syntax::ast::Ast;
+use crate::syntax::span::Span;
+
+/// What checking a program finds
+#[derive(Debug, Default)]
+pub struct Analysis {
+    /// Every error, in the order they appear in the source
+    pub errors: Vec<VeloError>,
+    /// Every use of a `@deprecated` function, struct or method, with the
+    /// message the `deprecated` lint reports it with
+    pub deprecated: Vec<(Span, String)>,
+}
+
+/// Resolves names and checks types
+pub fn analyze(nodes: &[Ast]) -> Analysis {
+    let mut analysis = check::check_program(nodes);
+    let errors = resolve::resolve_program(nodes).err().unwrap_or_default();
+    analysis.errors.extend(errors);
+    analysis
+        .errors
+        .sort_by_key(|error| (error.span.line, error.span.column));
+    analysis
+}
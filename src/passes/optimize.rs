@@ -0,0 +1,671 @@
+//! The optimizer, run with `-O` after the program is checked.
+//!
+//! It folds arithmetic and comparisons on literals, replaces reads of an
+//! `immut` whose value is a literal with the literal, drops the branches
+//! of an `if` that can never be taken and inlines calls to tiny functions.
+//! It relies on the program having passed the checker, and none of it
+//! changes what such a program does.
+//!
+//! A function is tiny when its body is a single `return` of an expression
+//! of a few nodes, made only of its parameters and literals. `@inline`
+//! lifts the limit on the size. Only calls whose arguments are literals or
+//! variables are inlined, so no argument is evaluated a different number
+//! of times. An inlined call still converts its arguments to the types of
+//! the parameters and its value to the return type, as the call would: a
+//! literal is converted there and then, anything else is wrapped in a call
+//! like `float(x)`. The checker only lets a number widen into a parameter
+//! or return type, and that is what the wrapping call does. Functions whose parameters or return type hold numbers
+//! inside, like `float?` or `array<int>`, are not inlined.
+
+use std::collections::HashMap;
+
+use crate::runtime::eval::numbers::{self, is_number_type};
+use crate::syntax::ast::visit::{fold_expression, fold_statement, walk_expression, Fold, Visitor};
+use crate::syntax::ast::{
+    Ast, CatchClause, ConditionType, Expression, ExpressionKind, Param, Statement, StatementKind,
+};
+use crate::syntax::lexer::Type;
+use crate::syntax::printer::type_name;
+
+/// How many nodes the body of a function without `@inline` may have to be
+/// inlined
+const INLINE_LIMIT: usize = 8;
+
+/// Optimizes a checked program
+pub fn optimize_program(nodes: Vec<Ast>) -> Vec<Ast> {
+    let mut optimizer = Optimizer {
+        inlinable: inlinable_functions(&nodes),
+        scopes: Vec::new(),
+        bindings: Vec::new(),
+    };
+    optimizer.fold_block(nodes)
+}
+
+/// A function that can be inlined, its parameters and what it returns
+struct Inlinable {
+    params: Vec<(String, Type)>,
+    ret_type: Type,
+    value: Expression,
+}
+
+struct Optimizer {
+    inlinable: HashMap<String, Inlinable>,
+    /// What each name in scope is bound to, the literal value of an
+    /// `immut` that has one and `None` for anything else
+    scopes: Vec<HashMap<String, Option<Expression>>>,
+    /// Bound in the next block before anything in it, e.g. parameters
+    bindings: Vec<String>,
+}
+
+impl Optimizer {
+    fn bind(&mut self, name: &str, value: Option<Expression>) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_string(), value);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Expression> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .and_then(Option::as_ref)
+    }
+
+    /// The nodes an `if` statement is left as once its condition is known
+    fn if_statement(&mut self, stmt: Statement) -> Vec<Ast> {
+        let span = stmt.span;
+        let (condition, body, else_body) = match stmt.kind {
+            StatementKind::IfStatement {
+                condition,
+                body,
+                else_body,
+            } => (condition, body, else_body),
+            _ => unreachable!(),
+        };
+        let condition = self.fold_expression(condition);
+        let taken = match condition.kind {
+            ExpressionKind::Bool(true) => body,
+            ExpressionKind::Bool(false) => match else_body {
+                Some(else_body) => else_body,
+                None => return Vec::new(),
+            },
+            _ => {
+                let kind = StatementKind::IfStatement {
+                    condition,
+                    body: self.fold_block(body),
+                    else_body: else_body.map(|else_body| self.fold_block(else_body)),
+                };
+                return vec![Ast::Statement(Statement::new(kind, span))];
+            }
+        };
+
+        // What the branch declares stays in a block of its own
+        let taken = self.fold_block(taken);
+        if taken.iter().any(declares) {
+            let kind = StatementKind::IfStatement {
+                condition: Expression::new(ExpressionKind::Bool(true), condition.span),
+                body: taken,
+                else_body: None,
+            };
+            vec![Ast::Statement(Statement::new(kind, span))]
+        } else {
+            taken
+        }
+    }
+
+    /// A function or method, its parameters bound in its body
+    fn function(&mut self, stmt: Statement, method: bool) -> Statement {
+        let (name, type_params, params, body, ret_type) = match stmt.kind {
+            StatementKind::Function {
+                name,
+                type_params,
+                params,
+                body,
+                ret_type,
+            } => (name, type_params, params, body, ret_type),
+            _ => unreachable!(),
+        };
+        let params: Vec<Param> = params
+            .into_iter()
+            .map(|param| Param {
+                default: param.default.map(|default| self.fold_expression(default)),
+                ..param
+            })
+            .collect();
+
+        if method {
+            self.bindings.push("self".to_string());
+        }
+        self.bindings
+            .extend(params.iter().map(|param| param.name.clone()));
+        let body = self.fold_function_body(body);
+
+        let kind = StatementKind::Function {
+            name,
+            type_params,
+            params,
+            body,
+            ret_type,
+        };
+        Statement { kind, ..stmt }
+    }
+
+    /// `name(args)`, inlined if it can be
+    fn call(&mut self, expr: Expression) -> Expression {
+        let expr = fold_expression(self, expr);
+        let (name, params, named) = match &expr.kind {
+            ExpressionKind::CallExpr {
+                name,
+                params,
+                named,
+            } => (name, params, named),
+            _ => unreachable!(),
+        };
+        let function = match self.inlinable.get(name) {
+            Some(function) => function,
+            None => return expr,
+        };
+        let simple = params
+            .iter()
+            .all(|arg| is_literal(arg) || matches!(arg.kind, ExpressionKind::Identifier(_)));
+        if !named.is_empty() || params.len() != function.params.len() || !simple {
+            return expr;
+        }
+
+        let mut args = HashMap::new();
+        for ((name, param_type), arg) in function.params.iter().zip(params) {
+            match convert(arg.clone(), param_type) {
+                Some(arg) => args.insert(name.as_str(), arg),
+                // Left for the call to report when it runs
+                None => return expr,
+            };
+        }
+        let ret_type = function.ret_type.clone();
+        let mut inlined = substitute(function.value.clone(), &args);
+        inlined.span = expr.span;
+        let inlined = self.fold_expression(inlined);
+        convert(inlined, &ret_type).unwrap_or(expr)
+    }
+}
+
+impl Fold for Optimizer {
+    fn fold_block(&mut self, block: Vec<Ast>) -> Vec<Ast> {
+        let scope = std::mem::take(&mut self.bindings)
+            .into_iter()
+            .map(|name| (name, None))
+            .collect();
+        self.scopes.push(scope);
+
+        let mut folded = Vec::new();
+        for node in block {
+            match node {
+                Ast::Statement(
+                    stmt @ Statement {
+                        kind: StatementKind::IfStatement { .. },
+                        ..
+                    },
+                ) => folded.extend(self.if_statement(stmt)),
+                node => folded.push(self.fold_ast(node)),
+            }
+        }
+
+        self.scopes.pop();
+        folded
+    }
+
+    fn fold_statement(&mut self, stmt: Statement) -> Statement {
+        match &stmt.kind {
+            StatementKind::VariableAssignment { constant, name, .. } => {
+                let (constant, name) = (*constant, name.clone());
+                let stmt = fold_statement(self, stmt);
+                let value = match &stmt.kind {
+                    StatementKind::VariableAssignment { value, .. }
+                        if constant && is_literal(value) =>
+                    {
+                        Some(value.clone())
+                    }
+                    _ => None,
+                };
+                self.bind(&name, value);
+                stmt
+            }
+            StatementKind::Function { .. } => self.function(stmt, false),
+            StatementKind::Impl { .. } => {
+                let (interface, target, methods) = match stmt.kind {
+                    StatementKind::Impl {
+                        interface,
+                        target,
+                        methods,
+                    } => (interface, target, methods),
+                    _ => unreachable!(),
+                };
+                let methods = methods
+                    .into_iter()
+                    .map(|method| self.function(method, true))
+                    .collect();
+                let kind = StatementKind::Impl {
+                    interface,
+                    target,
+                    methods,
+                };
+                Statement { kind, ..stmt }
+            }
+            StatementKind::For { .. } => {
+                let (name, iterable, body) = match stmt.kind {
+                    StatementKind::For {
+                        name,
+                        iterable,
+                        body,
+                    } => (name, iterable, body),
+                    _ => unreachable!(),
+                };
+                let iterable = self.fold_expression(iterable);
+                self.bindings.push(name.clone());
+                let body = self.fold_block(body);
+                let kind = StatementKind::For {
+                    name,
+                    iterable,
+                    body,
+                };
+                Statement { kind, ..stmt }
+            }
+            StatementKind::Try { .. } => {
+                let (body, catch, finally) = match stmt.kind {
+                    StatementKind::Try {
+                        body,
+                        catch,
+                        finally,
+                    } => (body, catch, finally),
+                    _ => unreachable!(),
+                };
+                let body = self.fold_block(body);
+                let catch = catch.map(|catch| {
+                    self.bindings.extend(catch.name.clone());
+                    CatchClause {
+                        body: self.fold_block(catch.body),
+                        ..catch
+                    }
+                });
+                let finally = finally.map(|finally| self.fold_block(finally));
+                let kind = StatementKind::Try {
+                    body,
+                    catch,
+                    finally,
+                };
+                Statement { kind, ..stmt }
+            }
+            _ => fold_statement(self, stmt),
+        }
+    }
+
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        let span = expr.span;
+        match expr.kind {
+            ExpressionKind::Identifier(ref name) => match self.lookup(name) {
+                Some(value) => Expression::new(value.kind.clone(), span),
+                None => expr,
+            },
+            ExpressionKind::CallExpr { .. } => self.call(expr),
+            _ => {
+                let expr = fold_expression(self, expr);
+                match fold_constant(&expr) {
+                    Some(kind) => Expression::new(kind, span),
+                    None => expr,
+                }
+            }
+        }
+    }
+}
+
+/// The value of an operation on literals, worked out as the interpreter
+/// would
+fn fold_constant(expr: &Expression) -> Option<ExpressionKind> {
+    match &expr.kind {
+        // Overflow and division by zero are left for the program to report
+        // when it runs
+        ExpressionKind::BinaryOp { lhs, op, rhs } => {
+            let lhs = numbers::literal_value(&lhs.kind)?;
+            let rhs = numbers::literal_value(&rhs.kind)?;
+            numbers::value_literal(&numbers::binary(op, &lhs, &rhs).ok()?)
+        }
+        ExpressionKind::Conditional { lhs, op, rhs } => {
+            let lhs = numbers::literal_value(&lhs.kind)?;
+            let rhs = numbers::literal_value(&rhs.kind)?;
+            match op {
+                ConditionType::Equal => Some(ExpressionKind::Bool(lhs == rhs)),
+                ConditionType::NotEqual => Some(ExpressionKind::Bool(lhs != rhs)),
+                ConditionType::Unary => None,
+                _ => numbers::ordering(op, &lhs, &rhs)
+                    .ok()
+                    .map(ExpressionKind::Bool),
+            }
+        }
+        ExpressionKind::Coalesce { lhs, rhs } => match lhs.kind {
+            ExpressionKind::Null => Some(rhs.kind.clone()),
+            _ if is_literal(lhs) => Some(lhs.kind.clone()),
+            _ => None,
+        },
+        // An `if` expression with a known condition is the value of the
+        // branch taken, when that is all the branch is
+        ExpressionKind::If {
+            condition,
+            body,
+            else_body,
+        } => {
+            let taken = match condition.kind {
+                ExpressionKind::Bool(true) => body,
+                ExpressionKind::Bool(false) => else_body,
+                _ => return None,
+            };
+            match taken.as_slice() {
+                [Ast::Expression(value)] => Some(value.kind.clone()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// `value` converted to `to` as a call converts its arguments and value.
+/// A literal is converted now, `None` if it cannot be, and anything else
+/// is wrapped in a call of the conversion function.
+fn convert(value: Expression, to: &Type) -> Option<Expression> {
+    if !is_number_type(to) {
+        return Some(value);
+    }
+    let span = value.span;
+    if let Some(number) = numbers::literal_value(&value.kind) {
+        if !numbers::fits_type(&number, to) {
+            return None;
+        }
+        let converted = numbers::convert(&number, to).ok()?;
+        return Some(Expression::new(numbers::value_literal(&converted)?, span));
+    }
+    if is_literal(&value) {
+        return None;
+    }
+    let kind = ExpressionKind::CallExpr {
+        name: type_name(to),
+        params: vec![value],
+        named: Vec::new(),
+    };
+    Some(Expression::new(kind, span))
+}
+
+/// Whether `t` holds numbers inside it, which a call converts one by one
+fn holds_numbers(t: &Type) -> bool {
+    match t {
+        Type::Optional(inner) | Type::Array(inner) => is_number_type(inner) || holds_numbers(inner),
+        Type::Tuple(items) => items.iter().any(|t| is_number_type(t) || holds_numbers(t)),
+        Type::Named { args, .. } => args.iter().any(|t| is_number_type(t) || holds_numbers(t)),
+        _ => false,
+    }
+}
+
+/// Literals that mean the same wherever they are put. A string that
+/// interpolates does not, it reads variables where it is.
+fn is_literal(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Short(_)
+        | ExpressionKind::Int(_)
+        | ExpressionKind::Large(_)
+        | ExpressionKind::BigInt(_)
+        | ExpressionKind::Float(_)
+        | ExpressionKind::Decimal(_)
+        | ExpressionKind::Bool(_)
+        | ExpressionKind::Null => true,
+        ExpressionKind::StringLiteral(str) => !str.contains("${"),
+        _ => false,
+    }
+}
+
+/// Whether a node declares a name, which moving it to the block around it
+/// would put in a different scope
+fn declares(node: &Ast) -> bool {
+    matches!(
+        node,
+        Ast::Statement(Statement {
+            kind: StatementKind::VariableAssignment { .. }
+                | StatementKind::Function { .. }
+                | StatementKind::Struct { .. }
+                | StatementKind::Interface { .. }
+                | StatementKind::Impl { .. },
+            ..
+        })
+    )
+}
+
+/// The top-level functions declared once that can be inlined
+fn inlinable_functions(nodes: &[Ast]) -> HashMap<String, Inlinable> {
+    struct Declared(HashMap<String, usize>);
+    impl Visitor for Declared {
+        fn visit_statement(&mut self, stmt: &Statement) {
+            if let StatementKind::Function { name, .. } = &stmt.kind {
+                *self.0.entry(name.clone()).or_default() += 1;
+            }
+            crate::syntax::ast::visit::walk_statement(self, stmt);
+        }
+    }
+    let mut declared = Declared(HashMap::new());
+    declared.visit_block(nodes);
+
+    let mut inlinable = HashMap::new();
+    for node in nodes {
+        let stmt = match node {
+            Ast::Statement(stmt) => stmt,
+            Ast::Expression(_) => continue,
+        };
+        let (name, params, body, ret_type) = match &stmt.kind {
+            StatementKind::Function {
+                name,
+                params,
+                body,
+                ret_type,
+                ..
+            } => (name, params, body, ret_type),
+            _ => continue,
+        };
+        let value = match body.stmts() {
+            [Ast::Statement(Statement {
+                kind: StatementKind::Return(Some(value)),
+                ..
+            })] => value,
+            _ => continue,
+        };
+
+        let plain = params
+            .iter()
+            .all(|Param { default, rest, .. }| default.is_none() && !rest)
+            && !params.iter().any(|param| holds_numbers(&param.param_type))
+            && !holds_numbers(ret_type);
+        let names: Vec<String> = params.iter().map(|param| param.name.clone()).collect();
+        let mut shape = Shape {
+            params: &names,
+            nodes: 0,
+            inlinable: true,
+        };
+        shape.visit_expression(value);
+        let small = shape.nodes <= INLINE_LIMIT || stmt.annotation("inline").is_some();
+
+        if declared.0[name] == 1
+            && plain
+            && shape.inlinable
+            && small
+            && stmt.annotation("deprecated").is_none()
+        {
+            let function = Inlinable {
+                params: params
+                    .iter()
+                    .map(|param| (param.name.clone(), param.param_type.clone()))
+                    .collect(),
+                ret_type: ret_type.clone(),
+                value: value.clone(),
+            };
+            inlinable.insert(name.clone(), function);
+        }
+    }
+    inlinable
+}
+
+/// Counts the nodes of an expression, and whether it is only parameters
+/// and literals put together
+struct Shape<'a> {
+    params: &'a [String],
+    nodes: usize,
+    inlinable: bool,
+}
+
+impl Visitor for Shape<'_> {
+    fn visit_expression(&mut self, expr: &Expression) {
+        self.nodes += 1;
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.inlinable &= self.params.contains(name),
+            ExpressionKind::StringLiteral(str) => self.inlinable &= !str.contains("${"),
+            // A call could be to the function itself, `?` and the blocks of
+            // an `if` could return from it
+            ExpressionKind::CallExpr { .. }
+            | ExpressionKind::Propagate(_)
+            | ExpressionKind::If { .. } => self.inlinable = false,
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+/// `value` with each parameter replaced by its argument
+fn substitute(value: Expression, args: &HashMap<&str, Expression>) -> Expression {
+    struct Substitute<'a>(&'a HashMap<&'a str, Expression>);
+    impl Fold for Substitute<'_> {
+        fn fold_expression(&mut self, expr: Expression) -> Expression {
+            match &expr.kind {
+                ExpressionKind::Identifier(name) => match self.0.get(name.as_str()) {
+                    Some(arg) => arg.clone(),
+                    None => expr,
+                },
+                _ => fold_expression(self, expr),
+            }
+        }
+    }
+    Substitute(args).fold_expression(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::optimize_program;
+    use crate::runtime::environment::Environment;
+    use crate::runtime::interpreter::evaluate;
+    use crate::runtime::value::Value;
+    use crate::syntax::parse::parse_source;
+    use crate::syntax::printer::print_program;
+
+    /// The optimized program, printed
+    fn optimized(source: &str) -> String {
+        let nodes = parse_source(source).expect("test source parses");
+        print_program(&optimize_program(nodes))
+    }
+
+    /// The value of the program's trailing expression, or the message of
+    /// the error it stops with
+    fn run(source: &str, optimize: bool) -> Result<Option<Value>, String> {
+        let nodes = parse_source(source).expect("test source parses");
+        let nodes = if optimize {
+            optimize_program(nodes)
+        } else {
+            nodes
+        };
+        evaluate(nodes, false, &mut Environment::init()).map_err(|error| error.message)
+    }
+
+    #[test]
+    fn folds_literals() {
+        let source = "a := 1 + 2 * 3; b := 2 < 3; c := null ?? 4; d := 7 / 0;";
+        assert_eq!(
+            optimized(source),
+            "a := 7;\nb := true;\nc := 4;\nd := 7 / 0;\n"
+        );
+    }
+
+    #[test]
+    fn propagates_immut_literals() {
+        let source = r#"
+            immut n := 4;
+            immut s := "x${n}";
+            m := n * 2;
+            fun f() > int {
+                n := 1;
+                return n;
+            }
+        "#;
+        assert_eq!(
+            optimized(source),
+            "immut n := 4;\nimmut s := \"x${n}\";\nm := 8;\n\n\
+             fun f() > int {\n  n := 1;\n  return n;\n}\n"
+        );
+    }
+
+    #[test]
+    fn removes_dead_branches_keeping_scopes() {
+        let source = r#"
+            if true { println(1); }
+            if 1 > 2 { println(2); }
+            if false { println(3); } else { c := 3; println(c); }
+        "#;
+        assert_eq!(
+            optimized(source),
+            "println(1);\nif true {\n  c := 3;\n  println(c);\n}\n"
+        );
+    }
+
+    #[test]
+    fn inlines_tiny_functions() {
+        let source = r#"
+            fun sq(x int) > int { return x * x; }
+            fun name(s string) > string { return s; }
+            a := sq(3);
+            b := sq(a);
+            c := name("v");
+        "#;
+        let printed = optimized(source);
+        assert!(
+            printed.ends_with("a := 9;\nb := int(int(a) * int(a));\nc := \"v\";\n"),
+            "{}",
+            printed
+        );
+    }
+
+    #[test]
+    fn leaves_calls_it_cannot_inline() {
+        let source = r#"
+            fun f(n int) > int { return f(n); }
+            fun g(x float?) > float? { return x; }
+            fun h(x int) > int { return x; }
+            a := g(1);
+            b := h(x: 1);
+            c := h(f(1));
+        "#;
+        let printed = optimized(source);
+        assert!(
+            printed.ends_with("a := g(1);\nb := h(x: 1);\nc := h(f(1));\n"),
+            "{}",
+            printed
+        );
+    }
+
+    #[test]
+    fn inlined_calls_convert_like_calls() {
+        let programs = [
+            "fun half(x float) > float { return x / 2; } half(3);",
+            "fun half(x float) > float { return x / 2; } y := 3; half(y);",
+            "fun wide(x short) > large { return x * 1000; } wide(300);",
+            "fun dbl(x short) > short { return x + x; } dbl(20000);",
+            "fun dbl(x short) > short { return x + x; } y := 20000; dbl(y);",
+        ];
+        for source in programs {
+            assert_eq!(run(source, true), run(source, false), "{}", source);
+        }
+        assert_eq!(run(programs[0], true), Ok(Some(Value::Float(1.5))));
+        let overflow = run(programs[3], true).unwrap_err();
+        assert!(overflow.contains("does not fit in `short`"), "{}", overflow);
+    }
+}
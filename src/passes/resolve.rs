@@ -0,0 +1,595 @@
+//! Name resolution, run before the type checker.
+//!
+//! Every block is a scope. A name can be used once it is declared in the
+//! block or one around it, and only then, except inside a function body,
+//! which runs when the function is called and so sees everything declared
+//! around the function. A name can be declared once per scope; declaring
+//! it again in an inner scope shadows it, unless it is an `immut`
+//! constant, whose name cannot be declared again with `:=` anywhere the
+//! constant can be seen.
+//!
+//! Variables live apart from functions, structs and interfaces, which
+//! share one namespace. After an `import` any name might have come from
+//! the imported file, so names that are nowhere to be found are not
+//! reported.
+
+use std::collections::HashMap;
+
+use crate::error::{ErrorType::NameError, VeloError};
+use crate::runtime::environment::BUILT_IN_FUNCTIONS;
+use crate::syntax::ast::visit::{walk_expression, walk_function_body, walk_statement, Visitor};
+use crate::syntax::ast::{Ast, Expression, ExpressionKind, Param, Statement, StatementKind};
+use crate::syntax::span::Span;
+use crate::utils::{capitalize, interpolated_names, similar_names};
+
+/// Resolves every name in a program, returning each one that is wrong
+pub fn resolve_program(nodes: &[Ast]) -> Result<(), Vec<VeloError>> {
+    let mut resolver = Resolver {
+        scopes: Vec::new(),
+        bindings: Vec::new(),
+        function_body: false,
+        imports: has_imports(nodes),
+        errors: Vec::new(),
+    };
+    resolver.visit_block(nodes);
+
+    if resolver.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(resolver.errors)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Item {
+    Function,
+    Struct,
+    Interface,
+}
+
+impl Item {
+    fn noun(&self) -> &'static str {
+        match self {
+            Item::Function => "function",
+            Item::Struct => "struct",
+            Item::Interface => "interface",
+        }
+    }
+}
+
+/// Where a variable was declared, and as what, e.g. "parameter"
+#[derive(Clone, Copy)]
+struct Binding {
+    what: &'static str,
+    span: Span,
+}
+
+#[derive(Default)]
+struct Scope {
+    variables: HashMap<String, Binding>,
+    items: HashMap<String, (Item, Span)>,
+    /// Declared further down the block
+    later_variables: HashMap<String, Span>,
+    later_items: HashMap<String, (Item, Span)>,
+    /// The body of a function, which runs after everything around it
+    function_body: bool,
+}
+
+struct Resolver {
+    scopes: Vec<Scope>,
+    /// Declared in the next block before anything in it, e.g. parameters
+    bindings: Vec<(String, Binding)>,
+    function_body: bool,
+    imports: bool,
+    errors: Vec<VeloError>,
+}
+
+impl Resolver {
+    fn error(&mut self, span: Span, message: String) {
+        self.errors
+            .push(VeloError::error(span, &message, NameError));
+    }
+
+    fn declare_variable(&mut self, name: &str, binding: Binding) {
+        if binding.what == "variable" || binding.what == "constant" {
+            let constant = self.scopes.iter().rev().find_map(|scope| {
+                scope
+                    .variables
+                    .get(name)
+                    .filter(|existing| existing.what == "constant")
+                    .copied()
+            });
+            if let Some(existing) = constant {
+                let message = format!(
+                    "Cannot redeclare constant `{}` declared on line {}",
+                    name, existing.span.line
+                );
+                self.error(binding.span, message);
+                return;
+            }
+        }
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(existing) = scope.variables.get(name).copied() {
+            let message = format!(
+                "Cannot redeclare {} `{}` declared on line {}",
+                existing.what, name, existing.span.line
+            );
+            self.error(binding.span, message);
+            return;
+        }
+        scope.variables.insert(name.to_string(), binding);
+    }
+
+    fn declare_item(&mut self, name: &str, item: Item, span: Span) {
+        if BUILT_IN_FUNCTIONS.iter().any(|(f, _)| *f == name) {
+            self.error(
+                span,
+                format!("Cannot redeclare built-in function `{}`", name),
+            );
+            return;
+        }
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some((existing, declared)) = scope.items.get(name).copied() {
+            let message = format!(
+                "Cannot redeclare {} `{}` declared on line {}",
+                existing.noun(),
+                name,
+                declared.line
+            );
+            self.error(span, message);
+            return;
+        }
+        scope.items.insert(name.to_string(), (item, span));
+    }
+
+    /// Reports `name` if no variable of that name is in scope at `span`.
+    /// The name of a function is a value too.
+    fn resolve_variable(&mut self, name: &str, span: Span) {
+        let mut deferred = false;
+        let mut later = None;
+        for scope in self.scopes.iter().rev() {
+            let function = |(item, _): &(Item, Span)| *item == Item::Function;
+            if scope.variables.contains_key(name) || scope.items.get(name).is_some_and(function) {
+                return;
+            }
+            if deferred && scope.later_items.get(name).is_some_and(function) {
+                return;
+            }
+            if let Some(declared) = scope.later_variables.get(name) {
+                if deferred {
+                    return;
+                }
+                later.get_or_insert(*declared);
+            }
+            deferred |= scope.function_body;
+        }
+
+        if let Some(declared) = later {
+            let message = format!(
+                "Variable `{}` is used before it is declared on line {}",
+                name, declared.line
+            );
+            self.error(span, message);
+        } else if !self.imports {
+            let candidates: Vec<&str> = self
+                .scopes
+                .iter()
+                .flat_map(|scope| scope.variables.keys())
+                .map(String::as_str)
+                .collect();
+            let message = format!("Cannot find variable `{}`", name);
+            let message = with_suggestions(message, name, candidates);
+            self.error(span, message);
+        }
+    }
+
+    /// The function, struct or interface `name` refers to at `span`, if it
+    /// can be found
+    fn resolve_item(&mut self, name: &str, span: Span) -> Option<Item> {
+        let mut deferred = false;
+        let mut later = None;
+        for scope in self.scopes.iter().rev() {
+            if let Some((item, _)) = scope.items.get(name) {
+                return Some(*item);
+            }
+            if let Some((item, declared)) = scope.later_items.get(name) {
+                if deferred {
+                    return Some(*item);
+                }
+                later.get_or_insert((*item, *declared));
+            }
+            deferred |= scope.function_body;
+        }
+
+        if let Some((item, declared)) = later {
+            let message = format!(
+                "{} `{}` is used before it is declared on line {}",
+                capitalize(item.noun()),
+                name,
+                declared.line
+            );
+            self.error(span, message);
+        }
+        None
+    }
+
+    /// `name(...)`, which calls a function, makes a struct or calls the
+    /// function a variable holds
+    fn resolve_call(&mut self, name: &str, span: Span) {
+        if BUILT_IN_FUNCTIONS.iter().any(|(f, _)| *f == name) {
+            return;
+        }
+        if self
+            .scopes
+            .iter()
+            .any(|scope| scope.variables.contains_key(name))
+        {
+            return;
+        }
+        let errors = self.errors.len();
+        match self.resolve_item(name, span) {
+            Some(Item::Interface) => self.error(
+                span,
+                format!("`{}` is an interface, it cannot be called", name),
+            ),
+            Some(_) => {}
+            None if self.errors.len() > errors || self.imports => {}
+            None => {
+                let candidates: Vec<&str> = self
+                    .scopes
+                    .iter()
+                    .flat_map(|scope| scope.items.iter())
+                    .filter(|(_, (item, _))| *item != Item::Interface)
+                    .map(|(name, _)| name.as_str())
+                    .chain(BUILT_IN_FUNCTIONS.iter().map(|(f, _)| *f))
+                    .collect();
+                let message = format!("Cannot find function `{}`", name);
+                let message = with_suggestions(message, name, candidates);
+                self.error(span, message);
+            }
+        }
+    }
+
+    /// The interface and struct of `impl Interface for Struct`
+    fn resolve_impl(&mut self, interface: &str, target: &str, span: Span) {
+        let errors = self.errors.len();
+        let found = [
+            (interface, self.resolve_item(interface, span)),
+            (target, self.resolve_item(target, span)),
+        ];
+        if self.errors.len() > errors || self.imports {
+            return;
+        }
+        for (name, item) in found {
+            if item.is_none() {
+                self.error(span, format!("Cannot find `{}`", name));
+            }
+        }
+    }
+
+    /// Visits a function or method, its parameters bound in its body
+    fn function(&mut self, stmt: &Statement, method: bool) {
+        let (params, body) = match &stmt.kind {
+            StatementKind::Function { params, body, .. } => (params, body),
+            _ => unreachable!(),
+        };
+        let binding = Binding {
+            what: "parameter",
+            span: stmt.span,
+        };
+        let mut names: Vec<&str> = Vec::new();
+        if method {
+            names.push("self");
+        }
+
+        // A default is worked out when the function is called, and can use
+        // the parameters before it
+        for Param { name, default, .. } in params {
+            if let Some(default) = default {
+                self.scopes.push(Scope {
+                    variables: names.iter().map(|n| (n.to_string(), binding)).collect(),
+                    function_body: true,
+                    ..Scope::default()
+                });
+                self.visit_expression(default);
+                self.scopes.pop();
+            }
+            names.push(name);
+        }
+
+        for name in names {
+            self.bind(name, "parameter", stmt.span);
+        }
+        self.function_body = true;
+        walk_function_body(self, body);
+    }
+
+    fn bind(&mut self, name: &str, what: &'static str, span: Span) {
+        self.bindings
+            .push((name.to_string(), Binding { what, span }));
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_block(&mut self, block: &[Ast]) {
+        let mut scope = Scope {
+            function_body: std::mem::take(&mut self.function_body),
+            ..Scope::default()
+        };
+        for node in block {
+            if let Ast::Statement(stmt) = node {
+                let span = stmt.span;
+                match &stmt.kind {
+                    StatementKind::VariableAssignment { name, .. } => {
+                        scope.later_variables.entry(name.clone()).or_insert(span);
+                    }
+                    StatementKind::Function { name, .. } => {
+                        let item = (Item::Function, span);
+                        scope.later_items.entry(name.clone()).or_insert(item);
+                    }
+                    StatementKind::Struct { name, .. } => {
+                        let item = (Item::Struct, span);
+                        scope.later_items.entry(name.clone()).or_insert(item);
+                    }
+                    StatementKind::Interface { name, .. } => {
+                        let item = (Item::Interface, span);
+                        scope.later_items.entry(name.clone()).or_insert(item);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.scopes.push(scope);
+        for (name, binding) in std::mem::take(&mut self.bindings) {
+            self.declare_variable(&name, binding);
+        }
+
+        for node in block {
+            self.visit_ast(node);
+        }
+        self.scopes.pop();
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::VariableAssignment {
+                constant,
+                name,
+                value,
+            } => {
+                self.visit_expression(value);
+                let binding = Binding {
+                    what: if *constant { "constant" } else { "variable" },
+                    span: stmt.span,
+                };
+                self.declare_variable(name, binding);
+            }
+            StatementKind::Function { name, .. } => {
+                self.declare_item(name, Item::Function, stmt.span);
+                self.function(stmt, false);
+            }
+            StatementKind::Struct { name, .. } => self.declare_item(name, Item::Struct, stmt.span),
+            StatementKind::Interface { name, .. } => {
+                self.declare_item(name, Item::Interface, stmt.span)
+            }
+            StatementKind::Impl {
+                interface,
+                target,
+                methods,
+            } => {
+                self.resolve_impl(interface, target, stmt.span);
+                for method in methods {
+                    self.function(method, true);
+                }
+            }
+            StatementKind::For {
+                name,
+                iterable,
+                body,
+            } => {
+                self.visit_expression(iterable);
+                self.bind(name, "loop variable", stmt.span);
+                self.visit_block(body);
+            }
+            StatementKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.visit_block(body);
+                if let Some(catch) = catch {
+                    if let Some(name) = &catch.name {
+                        self.bind(name, "catch variable", stmt.span);
+                    }
+                    self.visit_block(&catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.visit_block(finally);
+                }
+            }
+            _ => walk_statement(self, stmt),
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.resolve_variable(name, expr.span),
+            ExpressionKind::StringLiteral(str) => {
+                for name in interpolated_names(str) {
+                    self.resolve_variable(&name, expr.span);
+                }
+            }
+            ExpressionKind::CallExpr { name, .. } => {
+                self.resolve_call(name, expr.span);
+                walk_expression(self, expr);
+            }
+            _ => walk_expression(self, expr),
+        }
+    }
+}
+
+/// Whether the program imports anything, anywhere
+fn has_imports(nodes: &[Ast]) -> bool {
+    struct Imports(bool);
+    impl Visitor for Imports {
+        fn visit_statement(&mut self, stmt: &Statement) {
+            self.0 |= matches!(stmt.kind, StatementKind::Import(_));
+            walk_statement(self, stmt);
+        }
+    }
+
+    let mut imports = Imports(false);
+    imports.visit_block(nodes);
+    imports.0
+}
+
+/// `message`, with the names most like `name` suggested in place of it
+fn with_suggestions(message: String, name: &str, candidates: Vec<&str>) -> String {
+    let similar: Vec<String> = similar_names(name, candidates.into_iter())
+        .into_iter()
+        .map(|candidate| format!("`{}`", candidate))
+        .collect();
+    match similar.as_slice() {
+        [] => message,
+        [one] => format!("{}, did you mean {}?", message, one),
+        [rest @ .., last] => format!("{}, did you mean {} or {}?", message, rest.join(", "), last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_program;
+    use crate::syntax::parse::parse_source;
+
+    /// The message of each name error in `source`
+    fn errors(source: &str) -> Vec<String> {
+        let nodes = parse_source(source).expect("test source parses");
+        match resolve_program(&nodes) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|e| e.message).collect(),
+        }
+    }
+
+    #[test]
+    fn undefined_names() {
+        let source = r#"
+            println(missing);
+            nothing();
+            type Shape interface { fun area(self) > float }
+            impl Shape for Circle {}
+            Shape();
+            x := "${unknown}";
+        "#;
+        assert_eq!(
+            errors(source),
+            vec![
+                "Cannot find variable `missing`",
+                "Cannot find function `nothing`",
+                "Cannot find `Circle`",
+                "`Shape` is an interface, it cannot be called",
+                "Cannot find variable `unknown`",
+            ]
+        );
+    }
+
+    #[test]
+    fn imports_may_declare_any_name() {
+        let source = r#"
+            import "helpers.velo";
+            println(missing);
+            nothing();
+        "#;
+        assert_eq!(errors(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn use_before_declaration() {
+        let source = r#"
+            println(early);
+            early := 1;
+            p := Point(1);
+            struct Point { x int }
+            if true {
+                println(inner);
+            }
+            inner := 2;
+        "#;
+        assert_eq!(
+            errors(source),
+            vec![
+                "Variable `early` is used before it is declared on line 3",
+                "Struct `Point` is used before it is declared on line 5",
+                "Variable `inner` is used before it is declared on line 9",
+            ]
+        );
+    }
+
+    #[test]
+    fn function_bodies_see_later_declarations() {
+        let source = r#"
+            fun first() > int {
+                return second() + later;
+            }
+            fun second() > int { return 1; }
+            later := 2;
+        "#;
+        assert_eq!(errors(source), Vec::<String>::new());
+    }
+
+    #[test]
+    fn redeclaration() {
+        let source = r#"
+            x := 1;
+            x := 2;
+            if true {
+                x := 3;
+            }
+            immut limit := 10;
+            fun f() > int {
+                limit := 1;
+                return limit;
+            }
+            fun g(a int) {
+                a := 1;
+            }
+            fun g() {}
+            fun print() {}
+        "#;
+        assert_eq!(
+            errors(source),
+            vec![
+                "Cannot redeclare variable `x` declared on line 2",
+                "Cannot redeclare constant `limit` declared on line 7",
+                "Cannot redeclare parameter `a` declared on line 12",
+                "Cannot redeclare function `g` declared on line 12",
+                "Cannot redeclare built-in function `print`",
+            ]
+        );
+    }
+
+    #[test]
+    fn suggestions_are_ranked_by_distance() {
+        // `cont` is two edits from `countr`, the others one, and only the
+        // three closest are suggested
+        let source = r#"
+            cont := 1;
+            count := 2;
+            counter := 3;
+            counts := 4;
+            total := 5;
+            println(countr);
+            println(tota);
+            println(zzz);
+            prntln("x");
+        "#;
+        assert_eq!(
+            errors(source),
+            vec![
+                "Cannot find variable `countr`, did you mean `count`, `counter` or `counts`?",
+                "Cannot find variable `tota`, did you mean `total`?",
+                "Cannot find variable `zzz`",
+                "Cannot find function `prntln`, did you mean `println`?",
+            ]
+        );
+    }
+}
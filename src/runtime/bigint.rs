@@ -0,0 +1,443 @@
+//! Integers of any size, the values of type `bigint`. Velo has no
+//! dependencies, so this is a plain implementation: a sign and the
+//! magnitude in base 2^32 digits, schoolbook multiplication and bit by bit
+//! long division. It is fast enough for the numbers scripts work with.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigInt {
+    /// Never set for zero
+    negative: bool,
+    /// Least significant digit first, with no trailing zeros, so zero is
+    /// empty
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn from_i128(value: i128) -> BigInt {
+        let mut magnitude = Vec::new();
+        let mut rest = value.unsigned_abs();
+        while rest != 0 {
+            magnitude.push(rest as u32);
+            rest >>= 32;
+        }
+        BigInt {
+            negative: value < 0,
+            magnitude,
+        }
+    }
+
+    /// The value as an `i128`, if it is in range
+    pub fn to_i128(&self) -> Option<i128> {
+        if self.magnitude.len() > 4 {
+            return None;
+        }
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0u128, |acc, digit| (acc << 32) | *digit as u128);
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+
+    /// Parses an optional `-` followed by decimal digits
+    pub fn parse(text: &str) -> Option<BigInt> {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, text),
+        };
+        if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        // Nine digits at a time, the most that fit in a base 2^32 digit
+        let mut magnitude = Vec::new();
+        let first = match digits.len() % 9 {
+            0 => 9,
+            len => len,
+        };
+        let mut start = 0;
+        let mut end = first;
+        while start < digits.len() {
+            let chunk = &digits[start..end];
+            mul_small(&mut magnitude, 10u32.pow(chunk.len() as u32));
+            add_small(&mut magnitude, chunk.parse().unwrap());
+            start = end;
+            end += 9;
+        }
+        Some(BigInt::new(negative, magnitude))
+    }
+
+    /// The integer part of a finite float
+    pub fn from_f64(value: f64) -> Option<BigInt> {
+        if !value.is_finite() {
+            return None;
+        }
+        BigInt::parse(&format!("{:.0}", value.trunc()))
+    }
+
+    /// The nearest float, which is infinite when the value is too large
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+
+    /// `10^exponent`
+    pub fn pow10(exponent: u32) -> BigInt {
+        let mut magnitude = vec![1];
+        for _ in 0..exponent / 9 {
+            mul_small(&mut magnitude, 1_000_000_000);
+        }
+        mul_small(&mut magnitude, 10u32.pow(exponent % 9));
+        BigInt::new(false, magnitude)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.magnitude.first().is_some_and(|digit| digit & 1 == 1)
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::new(false, self.magnitude.clone())
+    }
+
+    /// The quotient rounded toward zero and the remainder, which has the
+    /// sign of `self`. `None` when dividing by zero.
+    pub fn div_rem(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &divisor.magnitude);
+        Some((
+            BigInt::new(self.negative != divisor.negative, quotient),
+            BigInt::new(self.negative, remainder),
+        ))
+    }
+
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        trim(&mut magnitude);
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            );
+        }
+        match compare_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &-other
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        let mut product = vec![0u32; self.magnitude.len() + other.magnitude.len()];
+        for (i, a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.magnitude.iter().enumerate() {
+                let digit = product[i + j] as u64 + *a as u64 * *b as u64 + carry;
+                product[i + j] = digit as u32;
+                carry = digit >> 32;
+            }
+            product[i + other.magnitude.len()] = carry as u32;
+        }
+        BigInt::new(self.negative != other.negative, product)
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => compare_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => compare_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Nine decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut rest = self.magnitude.clone();
+        while !rest.is_empty() {
+            chunks.push(div_small(&mut rest, 1_000_000_000));
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn compare_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let digit = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(digit as u32);
+        carry = digit >> 32;
+    }
+    sum.push(carry as u32);
+    trim(&mut sum);
+    sum
+}
+
+/// `a - b`, where `a` is at least `b`
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, a) in a.iter().enumerate() {
+        let mut digit = *a as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = (digit < 0) as i64;
+        if digit < 0 {
+            digit += 1 << 32;
+        }
+        difference.push(digit as u32);
+    }
+    trim(&mut difference);
+    difference
+}
+
+fn mul_small(magnitude: &mut Vec<u32>, factor: u32) {
+    let mut carry = 0u64;
+    for digit in magnitude.iter_mut() {
+        let product = *digit as u64 * factor as u64 + carry;
+        *digit = product as u32;
+        carry = product >> 32;
+    }
+    if carry != 0 {
+        magnitude.push(carry as u32);
+    }
+    trim(magnitude);
+}
+
+fn add_small(magnitude: &mut Vec<u32>, value: u32) {
+    let mut carry = value as u64;
+    for digit in magnitude.iter_mut() {
+        if carry == 0 {
+            return;
+        }
+        let sum = *digit as u64 + carry;
+        *digit = sum as u32;
+        carry = sum >> 32;
+    }
+    if carry != 0 {
+        magnitude.push(carry as u32);
+    }
+}
+
+/// Divides in place, returning the remainder
+fn div_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut remainder = 0u64;
+    for digit in magnitude.iter_mut().rev() {
+        let current = (remainder << 32) | *digit as u64;
+        *digit = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    trim(magnitude);
+    remainder as u32
+}
+
+fn div_rem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if let [divisor] = b {
+        let mut quotient = a.to_vec();
+        let remainder = div_small(&mut quotient, *divisor);
+        let mut remainder = vec![remainder];
+        trim(&mut remainder);
+        return (quotient, remainder);
+    }
+    if compare_magnitude(a, b) == Ordering::Less {
+        return (Vec::new(), a.to_vec());
+    }
+
+    // Bring down one bit of `a` at a time, subtracting `b` whenever the
+    // remainder reaches it
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for bit in (0..a.len() * 32).rev() {
+        mul_small(&mut remainder, 2);
+        if (a[bit / 32] >> (bit % 32)) & 1 == 1 {
+            add_small(&mut remainder, 1);
+        }
+        if compare_magnitude(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, b);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    trim(&mut quotient);
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BigInt;
+
+    fn big(text: &str) -> BigInt {
+        BigInt::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for text in [
+            "0",
+            "7",
+            "-7",
+            "4294967295",
+            "4294967296",
+            "-18446744073709551616",
+            "123456789012345678901234567890123456789",
+        ] {
+            assert_eq!(big(text).to_string(), text);
+        }
+        assert_eq!(big("-0").to_string(), "0");
+        assert_eq!(big("000123").to_string(), "123");
+        for text in ["", "-", "1.5", "12a", "+1", " 1"] {
+            assert_eq!(BigInt::parse(text), None, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn i128_round_trip() {
+        for value in [0, 1, -1, i64::MAX as i128, i128::MAX, i128::MIN] {
+            assert_eq!(BigInt::from_i128(value).to_i128(), Some(value));
+        }
+        assert_eq!(
+            big("170141183460469231731687303715884105728").to_i128(),
+            None
+        );
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = big("123456789012345678901234567890");
+        let b = big("-987654321098765432109876543210");
+        assert_eq!((&a + &b).to_string(), "-864197532086419753208641975320");
+        assert_eq!((&a - &b).to_string(), "1111111110111111111011111111100");
+        assert_eq!(
+            (&a * &b).to_string(),
+            "-121932631137021795226185032733622923332237463801111263526900"
+        );
+        assert_eq!((&a - &a).to_string(), "0");
+        assert!(!(&a - &a).is_negative());
+    }
+
+    #[test]
+    fn div_rem_signs() {
+        // The quotient rounds toward zero, the remainder has the sign of
+        // the dividend
+        for (a, b, quotient, remainder) in [
+            ("7", "2", "3", "1"),
+            ("-7", "2", "-3", "-1"),
+            ("7", "-2", "-3", "1"),
+            ("-7", "-2", "3", "-1"),
+            ("6", "-3", "-2", "0"),
+            ("1", "5", "0", "1"),
+        ] {
+            let (q, r) = big(a).div_rem(&big(b)).unwrap();
+            assert_eq!(
+                (q.to_string(), r.to_string()),
+                (quotient.to_string(), remainder.to_string()),
+                "{} / {}",
+                a,
+                b
+            );
+        }
+        assert_eq!(big("1").div_rem(&big("0")), None);
+    }
+
+    #[test]
+    fn div_rem_multi_digit() {
+        let a = big("121932631137021795226185032733622923332237463801111263526901");
+        let b = big("987654321098765432109876543210");
+        let (q, r) = a.div_rem(&b).unwrap();
+        assert_eq!(q.to_string(), "123456789012345678901234567890");
+        assert_eq!(r.to_string(), "1");
+    }
+
+    #[test]
+    fn ordering() {
+        let mut values: Vec<BigInt> = ["5", "-5", "0", "-100000000000", "100000000000"]
+            .into_iter()
+            .map(big)
+            .collect();
+        values.sort();
+        let sorted: Vec<String> = values.iter().map(BigInt::to_string).collect();
+        assert_eq!(sorted, ["-100000000000", "-5", "0", "5", "100000000000"]);
+    }
+}
@@ -0,0 +1,415 @@
+//! Exact decimal numbers, the values of type `decimal`. A decimal is an
+//! integer and how many of its digits come after the point, so `1.10d` is
+//! 110 with two places. Adding, subtracting and multiplying are exact and
+//! keep every place, only dividing and `round` round. Neither multiplying
+//! nor `round` gives a decimal more than `MAX_PLACES` places.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub};
+
+use super::bigint::BigInt;
+
+/// How many places a division keeps when the result does not end sooner
+pub const DIVISION_PLACES: u32 = 28;
+
+/// The most places that multiplying or `round` gives a decimal
+pub const MAX_PLACES: u32 = 1000;
+
+#[derive(Debug, Clone)]
+pub struct Decimal {
+    digits: BigInt,
+    places: u32,
+}
+
+/// Which way to go when a result has more places than are kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rounding {
+    /// To the nearest, ties to the even neighbour, as banks do
+    HalfEven,
+    /// To the nearest, ties away from zero, as taught in school
+    HalfUp,
+    /// To the nearest, ties toward zero
+    HalfDown,
+    /// Away from zero
+    Up,
+    /// Toward zero, dropping the extra places
+    Down,
+    Ceiling,
+    Floor,
+}
+
+impl Rounding {
+    pub const NAMES: [&'static str; 7] = [
+        "half_even",
+        "half_up",
+        "half_down",
+        "up",
+        "down",
+        "ceiling",
+        "floor",
+    ];
+
+    pub fn from_name(name: &str) -> Option<Rounding> {
+        Some(match name {
+            "half_even" => Rounding::HalfEven,
+            "half_up" => Rounding::HalfUp,
+            "half_down" => Rounding::HalfDown,
+            "up" => Rounding::Up,
+            "down" => Rounding::Down,
+            "ceiling" => Rounding::Ceiling,
+            "floor" => Rounding::Floor,
+            _ => return None,
+        })
+    }
+}
+
+impl Decimal {
+    pub fn from_integer(value: BigInt) -> Decimal {
+        Decimal {
+            digits: value,
+            places: 0,
+        }
+    }
+
+    /// Parses an optional `-`, digits, and optionally a `.` and more digits.
+    /// Every digit written is kept, so `"1.50"` has two places.
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        if !fraction.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(Decimal {
+            digits: BigInt::parse(&format!("{}{}", whole, fraction))?,
+            places: fraction.len() as u32,
+        })
+    }
+
+    /// The decimal written the way the float prints, so `0.1` is exactly
+    /// `0.1d` rather than the binary fraction nearest to it
+    pub fn from_f64(value: f64) -> Option<Decimal> {
+        if !value.is_finite() {
+            return None;
+        }
+        Decimal::parse(&value.to_string())
+    }
+
+    /// The nearest float
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+
+    /// The integer part, dropping the places
+    pub fn trunc(&self) -> BigInt {
+        self.digits.div_rem(&BigInt::pow10(self.places)).unwrap().0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_zero()
+    }
+
+    /// `self * other`, exactly. `None` when that needs more than
+    /// `MAX_PLACES` places, and more than either side has.
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        let places = self.places.checked_add(other.places)?;
+        if places > MAX_PLACES.max(self.places).max(other.places) {
+            return None;
+        }
+        Some(Decimal {
+            digits: &self.digits * &other.digits,
+            places,
+        })
+    }
+
+    /// `self / other` to `DIVISION_PLACES` places, rounding half to even,
+    /// then without the zeros past the places of either side. `None` when
+    /// dividing by zero.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        let places = DIVISION_PLACES.max(self.places);
+        let numerator = &self.digits * &BigInt::pow10(places + other.places - self.places);
+        let digits = divide_rounded(&numerator, &other.digits, Rounding::HalfEven);
+        Some(Decimal { digits, places }.trim_zeros(self.places.max(other.places)))
+    }
+
+    /// What is left over dividing `self` by `other` a whole number of times,
+    /// with the sign of `self`
+    pub fn checked_rem(&self, other: &Decimal) -> Option<Decimal> {
+        let (a, b, places) = align(self, other);
+        let (_, remainder) = a.div_rem(&b)?;
+        Some(Decimal {
+            digits: remainder,
+            places,
+        })
+    }
+
+    /// The value with exactly `places` places, rounding when that drops some
+    pub fn round(&self, places: u32, mode: Rounding) -> Decimal {
+        let digits = match places.cmp(&self.places) {
+            Ordering::Equal => self.digits.clone(),
+            Ordering::Greater => &self.digits * &BigInt::pow10(places - self.places),
+            Ordering::Less => {
+                divide_rounded(&self.digits, &BigInt::pow10(self.places - places), mode)
+            }
+        };
+        Decimal { digits, places }
+    }
+
+    /// Drops zeros at the end of the places, keeping at least `min` places
+    fn trim_zeros(mut self, min: u32) -> Decimal {
+        let ten = BigInt::from_i128(10);
+        while self.places > min {
+            let (quotient, remainder) = self.digits.div_rem(&ten).unwrap();
+            if !remainder.is_zero() {
+                break;
+            }
+            self.digits = quotient;
+            self.places -= 1;
+        }
+        self
+    }
+}
+
+impl Add for &Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: &Decimal) -> Decimal {
+        let (a, b, places) = align(self, other);
+        Decimal {
+            digits: &a + &b,
+            places,
+        }
+    }
+}
+
+impl Sub for &Decimal {
+    type Output = Decimal;
+
+    fn sub(self, other: &Decimal) -> Decimal {
+        let (a, b, places) = align(self, other);
+        Decimal {
+            digits: &a - &b,
+            places,
+        }
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Decimal) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Decimal {}
+
+/// Decimals compare by value, `1.5d` and `1.50d` being equal
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let (a, b, _) = align(self, other);
+        a.cmp(&b)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.digits.abs().to_string();
+        let places = self.places as usize;
+        let sign = if self.digits.is_negative() { "-" } else { "" };
+        if places == 0 {
+            return write!(f, "{}{}", sign, digits);
+        }
+
+        if digits.len() > places {
+            let (whole, fraction) = digits.split_at(digits.len() - places);
+            return write!(f, "{}{}.{}", sign, whole, fraction);
+        }
+        // Zeros between the point and the digits, written out rather than
+        // padded by `format!`, whose widths only go up to `u16::MAX`
+        let zeros = "0".repeat(places - digits.len());
+        write!(f, "{}0.{}{}", sign, zeros, digits)
+    }
+}
+
+/// The digits of both with the same number of places, and that number
+fn align(a: &Decimal, b: &Decimal) -> (BigInt, BigInt, u32) {
+    let places = a.places.max(b.places);
+    let scale = |value: &Decimal| &value.digits * &BigInt::pow10(places - value.places);
+    (scale(a), scale(b), places)
+}
+
+/// `numerator / denominator` as an integer, rounded by `mode`
+fn divide_rounded(numerator: &BigInt, denominator: &BigInt, mode: Rounding) -> BigInt {
+    let (quotient, remainder) = numerator.div_rem(denominator).unwrap();
+    if remainder.is_zero() {
+        return quotient;
+    }
+
+    let negative = numerator.is_negative() != denominator.is_negative();
+    // How the dropped part compares to a half
+    let half = (&remainder.abs() * &BigInt::from_i128(2)).cmp(&denominator.abs());
+    let away = match mode {
+        Rounding::Up => true,
+        Rounding::Down => false,
+        Rounding::Ceiling => !negative,
+        Rounding::Floor => negative,
+        Rounding::HalfUp => half != Ordering::Less,
+        Rounding::HalfDown => half == Ordering::Greater,
+        Rounding::HalfEven => {
+            half == Ordering::Greater || (half == Ordering::Equal && quotient.is_odd())
+        }
+    };
+    if !away {
+        return quotient;
+    }
+    let step = BigInt::from_i128(if negative { -1 } else { 1 });
+    &quotient + &step
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BigInt, Decimal, Rounding, MAX_PLACES};
+
+    fn dec(text: &str) -> Decimal {
+        Decimal::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for text in ["0", "1.10", "-0.05", "19.99", "-123.000", "0.0000001"] {
+            assert_eq!(dec(text).to_string(), text);
+        }
+        assert_eq!(dec("-0.0").to_string(), "0.0");
+        assert_eq!(dec(".5").to_string(), "0.5");
+        for text in ["", "1.2.3", "1e5", "1.-5", "abc", "-"] {
+            assert!(Decimal::parse(text).is_none(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn from_f64_is_the_printed_float() {
+        assert_eq!(Decimal::from_f64(0.1).unwrap().to_string(), "0.1");
+        assert_eq!(Decimal::from_f64(-2.5).unwrap().to_string(), "-2.5");
+        assert!(Decimal::from_f64(f64::NAN).is_none());
+        assert!(Decimal::from_f64(f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn exact_arithmetic() {
+        assert_eq!((&dec("0.1") + &dec("0.2")).to_string(), "0.3");
+        assert_eq!((&dec("1.10") - &dec("2.5")).to_string(), "-1.40");
+        let product = dec("1.5").checked_mul(&dec("-0.02")).unwrap();
+        assert_eq!(product.to_string(), "-0.030");
+        assert_eq!(dec("1.5"), dec("1.50"));
+        assert!(dec("-0.1") < dec("0.01"));
+        assert_eq!(dec("-7.9").trunc().to_string(), "-7");
+    }
+
+    #[test]
+    fn division() {
+        let third = dec("1").checked_div(&dec("3")).unwrap();
+        assert_eq!(third.to_string(), "0.3333333333333333333333333333");
+        let two_thirds = dec("2").checked_div(&dec("3")).unwrap();
+        assert_eq!(two_thirds.to_string(), "0.6666666666666666666666666667");
+        // Zeros past the places of either side are dropped
+        assert_eq!(
+            dec("1.00").checked_div(&dec("4")).unwrap().to_string(),
+            "0.25"
+        );
+        assert_eq!(dec("10").checked_div(&dec("4")).unwrap().to_string(), "2.5");
+        assert_eq!(dec("-9").checked_div(&dec("3")).unwrap().to_string(), "-3");
+        assert!(dec("1").checked_div(&dec("0.0")).is_none());
+    }
+
+    #[test]
+    fn remainder_has_the_sign_of_the_dividend() {
+        assert_eq!(
+            dec("7.5").checked_rem(&dec("2")).unwrap().to_string(),
+            "1.5"
+        );
+        assert_eq!(
+            dec("-7.5").checked_rem(&dec("2")).unwrap().to_string(),
+            "-1.5"
+        );
+        assert_eq!(
+            dec("7.5").checked_rem(&dec("-2")).unwrap().to_string(),
+            "1.5"
+        );
+        assert!(dec("1").checked_rem(&dec("0")).is_none());
+    }
+
+    #[test]
+    fn rounding_modes() {
+        // Each value rounded to no places by half_even, half_up,
+        // half_down, up, down, ceiling and floor
+        let cases = [
+            ("2.5", ["2", "3", "2", "3", "2", "3", "2"]),
+            ("3.5", ["4", "4", "3", "4", "3", "4", "3"]),
+            ("-2.5", ["-2", "-3", "-2", "-3", "-2", "-2", "-3"]),
+            ("2.4", ["2", "2", "2", "3", "2", "3", "2"]),
+            ("2.6", ["3", "3", "3", "3", "2", "3", "2"]),
+            ("-2.6", ["-3", "-3", "-3", "-3", "-2", "-2", "-3"]),
+            ("-0.4", ["0", "0", "0", "-1", "0", "0", "-1"]),
+            ("7", ["7", "7", "7", "7", "7", "7", "7"]),
+        ];
+        for (value, expected) in cases {
+            for (name, expected) in Rounding::NAMES.iter().zip(expected) {
+                let mode = Rounding::from_name(name).unwrap();
+                assert_eq!(
+                    dec(value).round(0, mode).to_string(),
+                    expected,
+                    "{} rounded {}",
+                    value,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rounding_places() {
+        assert_eq!(
+            dec("2.665").round(2, Rounding::HalfEven).to_string(),
+            "2.66"
+        );
+        assert_eq!(dec("2.665").round(2, Rounding::HalfUp).to_string(), "2.67");
+        assert_eq!(
+            dec("2.6651").round(2, Rounding::HalfDown).to_string(),
+            "2.67"
+        );
+        assert_eq!(dec("1.5").round(3, Rounding::Down).to_string(), "1.500");
+        assert!(Rounding::from_name("nearest").is_none());
+    }
+
+    #[test]
+    fn display_pads_any_number_of_places() {
+        assert_eq!(dec("-0.005").to_string(), "-0.005");
+        let tiny = Decimal {
+            digits: BigInt::from_i128(15),
+            places: 70_000,
+        };
+        let text = tiny.to_string();
+        assert_eq!(text.len(), 70_002);
+        assert!(text.starts_with("0.000"));
+        assert!(text.ends_with("00015"));
+    }
+
+    #[test]
+    fn products_keep_at_most_max_places() {
+        let wide = dec("1.5").round(MAX_PLACES / 2, Rounding::HalfEven);
+        assert!(wide.checked_mul(&wide).is_some());
+        assert!(wide.checked_mul(&dec("0.1")).is_some());
+        let wider = dec("1.5").round(MAX_PLACES / 2 + 1, Rounding::HalfEven);
+        assert!(wider.checked_mul(&wider).is_none());
+        // Places a decimal already has are kept
+        let written = dec(&format!("0.{}1", "0".repeat(MAX_PLACES as usize)));
+        assert!(written.checked_mul(&dec("2")).is_some());
+    }
+}
@@ -1,16 +1,61 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
 
-use crate::error::{ErrorType::RuntimeError, VeloError, ERROR_INDICATOR};
-use crate::syntax::ast::{Expression, Statement};
+use super::value::{Closure, Value};
+use crate::error::{ErrorType::RuntimeError, VeloError};
+use crate::package::PackageGraph;
+use crate::syntax::ast::{Annotation, Statement, StatementKind};
+use crate::syntax::span::Span;
+use crate::utils::expand_tilde;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Environment {
-    pub errors: Vec<VeloError>,
-    pub parent: Option<Box<Environment>>,
-    pub variables: HashMap<String, Expression>,
-    pub constants: Vec<Expression>,
-    pub functions: Vec<Statement>,
+    /// The scope of the block running now
+    pub scope: Rc<RefCell<Scope>>,
+    /// The scope of the top level of the program, around every other
+    pub globals: Rc<RefCell<Scope>>,
+    pub variable_annotations: HashMap<String, Vec<Annotation>>,
+    pub structs: Vec<Statement>,
+    pub interfaces: Vec<Statement>,
+    pub impls: Vec<Statement>,
     pub lib_functions: Vec<LibFunction>,
+    /// The `err` result that `?` is returning from the current function
+    pub propagating: Option<Value>,
+    pub packages: Option<PackageGraph>,
+    pub current_package: Option<usize>,
+    /// How many function calls are running, each inside the one before
+    pub depth: usize,
+    /// How deep calls may go before the next one throws a
+    /// `RecursionError`, by default `MAX_DEPTH`. Every call takes some of
+    /// the stack of the thread running the program, so a host running it
+    /// on a small stack may need to lower this.
+    pub max_depth: usize,
+}
+
+/// The variables, constants and functions declared in one block or
+/// function call.
+/// Names that are not found here are looked up in the parent, the scope
+/// that the block is written in, so an inner scope can shadow an outer one.
+/// Everything declared in a scope is dropped with it, unless a function
+/// declared in it is still around to see it.
+#[derive(Debug, Default)]
+pub struct Scope {
+    variables: HashMap<String, Value>,
+    /// Declared with `immut`, read like variables but never declared again
+    constants: HashMap<String, Value>,
+    functions: Vec<Rc<Statement>>,
+    parent: Option<Rc<RefCell<Scope>>>,
+}
+
+impl Scope {
+    pub fn child(parent: &Rc<RefCell<Scope>>) -> Rc<RefCell<Scope>> {
+        Rc::new(RefCell::new(Scope {
+            parent: Some(parent.clone()),
+            ..Scope::default()
+        }))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -19,22 +64,254 @@ pub struct LibFunction {
     pub param_len: Option<usize>,
 }
 
+/// How deep function calls may go by default
+pub const MAX_DEPTH: usize = 1000;
+
+/// The functions every program can call, with how many arguments they take
+/// at most. `None` takes any number.
+pub const BUILT_IN_FUNCTIONS: [(&str, Option<usize>); 22] = [
+    ("print", None),
+    ("println", None),
+    ("input", Some(2)),
+    ("error", Some(2)),
+    ("ok", Some(1)),
+    ("err", Some(1)),
+    ("read_file", Some(1)),
+    ("write_file", Some(2)),
+    ("parse_number", Some(1)),
+    ("wrapping_add", Some(2)),
+    ("wrapping_sub", Some(2)),
+    ("wrapping_mul", Some(2)),
+    ("saturating_add", Some(2)),
+    ("saturating_sub", Some(2)),
+    ("saturating_mul", Some(2)),
+    ("short", Some(1)),
+    ("int", Some(1)),
+    ("large", Some(1)),
+    ("bigint", Some(1)),
+    ("float", Some(1)),
+    ("decimal", Some(1)),
+    ("round", Some(3)),
+];
+
 impl Environment {
     pub fn init() -> Self {
-        let funcs = vec![("print", Some(1)), ("println", Some(1)), ("input", Some(2))];
         let mut lib_functions = Vec::new();
-        for f in funcs {
+        for f in BUILT_IN_FUNCTIONS {
             lib_functions.push(Self::mk_lib(f.0, f.1));
         }
 
+        let globals = Rc::new(RefCell::new(Scope::default()));
         Environment {
-            errors: Vec::new(),
-            parent: None,
-            variables: HashMap::new(),
-            constants: Vec::new(),
-            functions: Vec::new(),
+            scope: globals.clone(),
+            globals,
+            variable_annotations: HashMap::new(),
+            structs: Vec::new(),
+            interfaces: Vec::new(),
+            impls: Vec::new(),
             lib_functions,
+            propagating: None,
+            packages: None,
+            current_package: None,
+            depth: 0,
+            max_depth: MAX_DEPTH,
+        }
+    }
+
+    pub fn with_packages(packages: PackageGraph) -> Self {
+        let mut env = Self::init();
+        env.current_package = Some(packages.root_index());
+        env.packages = Some(packages);
+        env
+    }
+
+    /// Resolves the target of an `import`. Inside a package, a dependency
+    /// name imports that package's entry point and relative paths are
+    /// resolved against the package root. Returns the file to load and the
+    /// package it belongs to.
+    pub fn resolve_import(&self, path: &str) -> (PathBuf, Option<usize>) {
+        if let (Some(graph), Some(current)) = (&self.packages, self.current_package) {
+            if let Some(dep) = graph.dependency(current, path) {
+                return (graph.packages[dep].entry_path(), Some(dep));
+            }
+
+            let full_path = expand_tilde(path);
+            if full_path.is_relative() {
+                return (graph.packages[current].root.join(full_path), Some(current));
+            }
+            return (full_path, Some(current));
         }
+
+        (expand_tilde(path), None)
+    }
+
+    /// The value of variable or constant `name` in the innermost scope that
+    /// has one
+    pub fn variable(&self, name: &str) -> Option<Value> {
+        let mut scope = self.scope.clone();
+        loop {
+            let found = {
+                let scope = scope.borrow();
+                scope
+                    .variables
+                    .get(name)
+                    .or_else(|| scope.constants.get(name))
+                    .cloned()
+            };
+            if found.is_some() {
+                return found;
+            }
+            let parent = scope.borrow().parent.clone()?;
+            scope = parent;
+        }
+    }
+
+    /// Sets a variable in the current scope, whether or not it is declared
+    /// there already, e.g. a parameter or a loop variable
+    pub fn bind(&mut self, name: &str, value: Value) {
+        self.scope
+            .borrow_mut()
+            .variables
+            .insert(name.to_string(), value);
+    }
+
+    /// Whether `name` is a constant in the current scope or one around it
+    pub fn is_constant(&self, name: &str) -> bool {
+        let mut scope = self.scope.clone();
+        loop {
+            if scope.borrow().constants.contains_key(name) {
+                return true;
+            }
+            let parent = match scope.borrow().parent.clone() {
+                Some(parent) => parent,
+                None => return false,
+            };
+            scope = parent;
+        }
+    }
+
+    pub fn declare_function(&mut self, function: &Statement) {
+        self.scope
+            .borrow_mut()
+            .functions
+            .push(Rc::new(function.clone()));
+    }
+
+    /// The most recent declaration of function `name` in the innermost
+    /// scope that has one, with that scope
+    pub fn find_function(&self, name: &str) -> Option<Closure> {
+        let mut scope = self.scope.clone();
+        loop {
+            let function = scope
+                .borrow()
+                .functions
+                .iter()
+                .rev()
+                .find(|f| matches!(&f.kind, StatementKind::Function { name: n, .. } if n == name))
+                .cloned();
+            if let Some(function) = function {
+                return Some(Closure { function, scope });
+            }
+            let parent = scope.borrow().parent.clone()?;
+            scope = parent;
+        }
+    }
+
+    /// Runs `f` in a new scope inside the current one, such as the body of
+    /// an `if`, and leaves it afterwards however `f` ends
+    pub fn scoped<T>(&mut self, f: impl FnOnce(&mut Environment) -> T) -> T {
+        let parent = self.scope.clone();
+        self.in_scope(&parent, f)
+    }
+
+    /// Runs `f` in a new scope inside `parent`, such as a function call
+    /// inside the scope the function was declared in, then returns to the
+    /// current scope
+    pub fn in_scope<T>(
+        &mut self,
+        parent: &Rc<RefCell<Scope>>,
+        f: impl FnOnce(&mut Environment) -> T,
+    ) -> T {
+        let outer = std::mem::replace(&mut self.scope, Scope::child(parent));
+        let result = f(self);
+        self.scope = outer;
+        result
+    }
+
+    /// The most recent declaration of struct `name`
+    pub fn find_struct(&self, name: &str) -> Option<&Statement> {
+        self.structs
+            .iter()
+            .rev()
+            .find(|s| matches!(&s.kind, StatementKind::Struct { name: n, .. } if n == name))
+    }
+
+    /// The most recent declaration of interface `name`
+    pub fn find_interface(&self, name: &str) -> Option<&Statement> {
+        self.interfaces
+            .iter()
+            .rev()
+            .find(|i| matches!(&i.kind, StatementKind::Interface { name: n, .. } if n == name))
+    }
+
+    /// Whether a struct or interface called `name` has been declared
+    pub fn type_exists(&self, name: &str) -> bool {
+        self.find_struct(name).is_some() || self.find_interface(name).is_some()
+    }
+
+    /// Whether struct `target` has an `impl` block for `interface`
+    pub fn implements(&self, target: &str, interface: &str) -> bool {
+        self.impls.iter().any(|i| {
+            matches!(&i.kind, StatementKind::Impl { interface: n, target: t, .. } if n == interface && t == target)
+        })
+    }
+
+    /// Method `name` of struct `target`, from the most recent `impl` block
+    /// that has one
+    pub fn find_method(&self, target: &str, name: &str) -> Option<&Statement> {
+        self.impls.iter().rev().find_map(|i| match &i.kind {
+            StatementKind::Impl {
+                target: t, methods, ..
+            } if t == target => methods
+                .iter()
+                .find(|m| matches!(&m.kind, StatementKind::Function { name: n, .. } if n == name)),
+            _ => None,
+        })
+    }
+
+    /// The annotations on the function, struct or variable called `name`.
+    /// This is how host applications read their own annotations.
+    pub fn annotations(&self, name: &str) -> Option<Vec<Annotation>> {
+        if let Some(closure) = self.find_function(name) {
+            return Some(closure.function.annotations.clone());
+        }
+        if let Some(declaration) = self.find_struct(name) {
+            return Some(declaration.annotations.clone());
+        }
+
+        self.variable_annotations.get(name).cloned()
+    }
+
+    /// The top level functions that carry annotation `name`, e.g. every
+    /// `@test`
+    pub fn annotated(&self, name: &str) -> Vec<Rc<Statement>> {
+        self.globals
+            .borrow()
+            .functions
+            .iter()
+            .filter(|f| f.annotation(name).is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// Declares `value` as a global constant called `name`. This is how a
+    /// host application hands a program its values, maps and native
+    /// handles included.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.globals
+            .borrow_mut()
+            .constants
+            .insert(name.to_string(), value);
     }
 
     pub fn mk_lib(name: &str, len: Option<usize>) -> LibFunction {
@@ -47,29 +324,41 @@ impl Environment {
     pub fn declare_variable(
         &mut self,
         name: String,
-        value: Expression,
+        value: Value,
         constant: bool,
-    ) -> Expression {
+        span: Span,
+    ) -> Result<(), VeloError> {
+        // A constant cannot be declared again anywhere it can be seen, a
+        // variable only in the same scope, so an outer one can be shadowed
+        if self.is_constant(&name) {
+            return Err(self.throw_error(span, format!("Cannot redeclare constant `{}`", name)));
+        }
+        let mut scope = self.scope.borrow_mut();
+        if scope.variables.contains_key(&name) {
+            let message = format!(
+                "Variable with name '{}' already exists, did you mean to use `:=` instead of `=`?",
+                name
+            );
+            drop(scope);
+            return Err(self.throw_error(span, message));
+        }
         if constant {
-            self.constants.push(value)
-            // yk i would make constants a hash set
-            // but that doesnt work with f32 for some weird reason
-            // i love rust :)
+            scope.constants.insert(name, value);
         } else {
-            if self.variables.contains_key(&name) {
-                let message = format!("Variable with name '{}' already exists, did you mean to use `:=` instead of `=`?", &name);
-                self.throw_error(message)
-            } else {
-                self.variables.insert(name, value);
-            }
+            scope.variables.insert(name, value);
         }
 
-        Expression::Null
+        Ok(())
+    }
+
+    /// A runtime error at `span`, to be returned
+    pub fn throw_error(&self, span: Span, message: String) -> VeloError {
+        self.raise(span, RuntimeError.name(), message)
     }
 
-    fn throw_error(&mut self, message: String) {
-        let message = format!("{} \x1b[1m{}\x1b[0m", ERROR_INDICATOR, message);
-        self.errors
-            .push(VeloError::error(0, &message, RuntimeError));
+    /// Like `throw_error`, for an error of a more specific `kind`, e.g.
+    /// "IndexError"
+    pub fn raise(&self, span: Span, kind: &str, message: String) -> VeloError {
+        VeloError::error(span, &message, RuntimeError).with_kind(kind)
     }
 }
@@ -0,0 +1,68 @@
+//! Error values, which `throw` raises and `catch` binds
+
+use super::super::environment::Environment;
+use super::super::value::Value;
+use crate::error::VeloError;
+use crate::syntax::span::Span;
+
+/// The kind of an error thrown as a plain string, or made by `error`
+/// without one
+pub const DEFAULT_KIND: &str = "Error";
+
+/// What `catch` binds for a caught error
+pub fn error_value(error: &VeloError) -> Value {
+    Value::Error {
+        kind: error.kind.clone(),
+        message: error.message.clone(),
+    }
+}
+
+/// `throw value`, for an error value or a message string
+pub fn throw_value(value: Value, span: Span, env: &mut Environment) -> VeloError {
+    match value {
+        Value::Error { kind, message } => env.raise(span, &kind, message),
+        Value::String(message) => env.raise(span, DEFAULT_KIND, message),
+        _ => env.throw_error(
+            span,
+            format!(
+                "Cannot throw a value of type {}, expected an error or a string",
+                value.type_name()
+            ),
+        ),
+    }
+}
+
+/// `error(message)` or `error(message, kind)`
+pub fn make_error(
+    args: Vec<(Value, Span)>,
+    span: Span,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let mut strings = Vec::new();
+    for (arg, arg_span) in &args {
+        match arg {
+            Value::String(str) => strings.push(str.clone()),
+            _ => {
+                return Err(env.throw_error(
+                    *arg_span,
+                    format!("Function `error` takes strings, found {}", arg),
+                ))
+            }
+        }
+    }
+
+    let (message, kind) = match strings.as_slice() {
+        [message] => (message.clone(), DEFAULT_KIND.to_string()),
+        [message, kind] => (message.clone(), kind.clone()),
+        _ => {
+            return Err(env.throw_error(
+                span,
+                format!(
+                    "Function `error` takes 1 or 2 argument(s) but {} were given",
+                    args.len()
+                ),
+            ))
+        }
+    };
+    Ok(Value::Error { kind, message })
+}
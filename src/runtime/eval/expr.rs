@@ -1,144 +1,1071 @@
-use super::super::environment::Environment;
-use crate::syntax::ast::{ConditionType, Expression, Statement};
-use crate::syntax::lexer::TokenType;
+use super::super::bigint::BigInt;
+use super::super::decimal::{Decimal, Rounding, MAX_PLACES};
+use super::super::environment::{Environment, LibFunction};
+use super::super::interpreter::{execute_block, execute_scoped, Flow};
+use super::super::value::{Closure, Value};
+use super::errors::make_error;
+use super::interfaces::check_implements;
+use super::numbers::{self, as_i64, ArithmeticError};
+use super::results::{err, err_kind, evaluate_propagate, ok, result_method};
+use super::sequence::{evaluate_index, evaluate_range, range_step};
+use super::structs::{construct_struct, field_value};
+use super::types::{
+    accepts_null, bind_type_params, check_type_exists, coerce, fits, mentions_params, substitute,
+    unify, value_type, Bindings,
+};
+use crate::error::VeloError;
+use crate::syntax::ast::{
+    ConditionType, Expression, ExpressionKind, NamedArg, Param, StatementKind,
+};
+use crate::syntax::lexer::{TokenType, Type};
+use crate::syntax::printer;
+use crate::syntax::span::Span;
 use crate::utils::interpolate_string;
 
-use std::io;
+use std::fs;
+use std::io::{self, Write};
+use std::rc::Rc;
 
-#[allow(unused)]
-pub fn eval_call_expr(call_expr: &Expression, env: &mut Environment, var: Option<&Statement>) {
-    let mut nm = String::new();
-    let mut pm: Vec<Expression> = Vec::new();
+/// Evaluates an expression to the value it has
+pub fn eval_expr(expr: &Expression, env: &mut Environment) -> Result<Value, VeloError> {
+    match &expr.kind {
+        ExpressionKind::Short(value) => Ok(Value::Short(*value)),
+        ExpressionKind::Int(value) => Ok(Value::Int(*value)),
+        ExpressionKind::Large(value) => Ok(Value::Large(*value)),
+        ExpressionKind::Float(value) => Ok(Value::Float(*value)),
+        ExpressionKind::BigInt(_) | ExpressionKind::Decimal(_) => {
+            Ok(numbers::literal_value(&expr.kind).unwrap())
+        }
+        ExpressionKind::Bool(value) => Ok(Value::Bool(*value)),
+        ExpressionKind::Null => Ok(Value::Null),
+        ExpressionKind::StringLiteral(str) => {
+            Ok(Value::String(interpolate_string(str, env, expr.span)?))
+        }
+        // A function's name is a value too, which can be called later
+        ExpressionKind::Identifier(name) => match env.variable(name) {
+            Some(value) => Ok(value),
+            None => match env.find_function(name) {
+                Some(function) => Ok(Value::Function(function)),
+                None => {
+                    Err(env.throw_error(expr.span, format!("Cannot locate variable `{}`", name)))
+                }
+            },
+        },
+        ExpressionKind::BinaryOp { lhs, op, rhs } => eval_binary(expr, lhs, op, rhs, env),
+        ExpressionKind::Conditional { lhs, op, rhs } => eval_comparison(expr, lhs, op, rhs, env),
+        ExpressionKind::CallExpr { .. } => eval_call_expr(expr, env),
+        ExpressionKind::MethodCall { .. } => eval_method_call(expr, env),
+        ExpressionKind::Field {
+            target,
+            name,
+            optional,
+        } => eval_field(expr, target, name, *optional, env),
+        ExpressionKind::Propagate(_) => evaluate_propagate(expr, env),
+        ExpressionKind::Spread(_) => Err(env.throw_error(
+            expr.span,
+            "`...` can only spread the arguments of a call".to_string(),
+        )),
+        ExpressionKind::Coalesce { lhs, rhs } => match eval_expr(lhs, env)? {
+            Value::Null => eval_expr(rhs, env),
+            value => Ok(value),
+        },
+        ExpressionKind::Array(items) => {
+            let mut values = Vec::new();
+            for item in items {
+                values.push(eval_expr(item, env)?);
+            }
+            Ok(Value::Array(values))
+        }
+        ExpressionKind::Index { .. } => evaluate_index(expr, env),
+        ExpressionKind::Range { .. } => evaluate_range(expr, env),
+        ExpressionKind::If {
+            condition,
+            body,
+            else_body,
+        } => {
+            let branch = if eval_condition(condition, env)? {
+                body
+            } else {
+                else_body
+            };
+            // The parser guarantees every branch ends in an expression and
+            // contains no `return`
+            match execute_scoped(branch, env)? {
+                Flow::Next(Some(value)) => Ok(value),
+                _ => Ok(Value::Null),
+            }
+        }
+    }
+}
+
+/// `lhs op rhs` for an arithmetic or bitwise `op`, or `+` on strings
+fn eval_binary(
+    expr: &Expression,
+    lhs: &Expression,
+    op: &TokenType,
+    rhs: &Expression,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let lhs = eval_expr(lhs, env)?;
+    let rhs = eval_expr(rhs, env)?;
+    match (lhs, rhs) {
+        (Value::String(lhs), Value::String(rhs)) if *op == TokenType::Add => {
+            Ok(Value::String(lhs + &rhs))
+        }
+        (lhs, rhs) => numbers::binary(op, &lhs, &rhs).map_err(|error| {
+            let operation = format!("{} {} {}", lhs, printer::operator(op), rhs);
+            arithmetic_error(error, op, &operation, &lhs, &rhs, expr.span, env)
+        }),
+    }
+}
+
+/// `lhs op rhs` for a comparison `op`
+fn eval_comparison(
+    expr: &Expression,
+    lhs: &Expression,
+    op: &ConditionType,
+    rhs: &Expression,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let lhs = eval_expr(lhs, env)?;
+    let rhs = eval_expr(rhs, env)?;
+    match op {
+        ConditionType::Equal => Ok(Value::Bool(lhs == rhs)),
+        ConditionType::NotEqual => Ok(Value::Bool(lhs != rhs)),
+        _ => match numbers::ordering(op, &lhs, &rhs) {
+            Ok(result) => Ok(Value::Bool(result)),
+            Err(_) => Err(env.throw_error(
+                expr.span,
+                format!(
+                    "Cannot compare a value of type {} and a value of type {} with `{}`",
+                    lhs.type_name(),
+                    rhs.type_name(),
+                    printer::comparison(op)
+                ),
+            )),
+        },
+    }
+}
 
-    match call_expr {
-        Expression::CallExpr { name, params } => {
-            nm = name.to_string();
-            pm = params.to_vec();
+/// Field `name` of `target`, null from `?.` on null
+fn eval_field(
+    expr: &Expression,
+    target: &Expression,
+    name: &str,
+    optional: bool,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let target = eval_expr(target, env)?;
+    if let Some(value) = field_value(&target, name) {
+        return Ok(value);
+    }
+    match target {
+        Value::Null if optional => Ok(target),
+        Value::Null => Err(env.throw_error(
+            expr.span,
+            format!(
+                "Cannot read field `{}` of null, use `?.` to allow a null value",
+                name
+            ),
+        )),
+        _ => Err(env.throw_error(
+            expr.span,
+            format!(
+                "Cannot find field `{}` for a value of type {}",
+                name,
+                target.type_name()
+            ),
+        )),
+    }
+}
+
+/// Evaluates the condition of an `if`, which must be a bool
+pub fn eval_condition(expr: &Expression, env: &mut Environment) -> Result<bool, VeloError> {
+    match eval_expr(expr, env)? {
+        Value::Bool(value) => Ok(value),
+        value => Err(env.throw_error(
+            expr.span,
+            format!(
+                "Expected a condition, found a value of type {}",
+                value.type_name()
+            ),
+        )),
+    }
+}
+
+/// `round(value, places, mode)`, a decimal rounded to `places` places,
+/// by default none, in one of the `Rounding` modes, by default half to even
+fn round(args: Vec<(Value, Span)>, span: Span, env: &mut Environment) -> Result<Value, VeloError> {
+    let mut args = args.into_iter();
+    let value = match args.next() {
+        // Integers widen to decimals, floats must be converted
+        Some((value, _))
+            if numbers::is_number(&value) && numbers::fits_type(&value, &Type::Decimal) =>
+        {
+            match numbers::convert(&value, &Type::Decimal) {
+                Ok(Value::Decimal(value)) => value,
+                _ => unreachable!(),
+            }
+        }
+        Some((value, value_span)) => {
+            return Err(env.throw_error(
+                value_span,
+                format!(
+                    "Function `round` takes a decimal, found a value of type {}",
+                    value.type_name()
+                ),
+            ))
+        }
+        None => {
+            return Err(env.throw_error(
+                span,
+                "Function `round` takes at least 1 argument but 0 were given".to_string(),
+            ))
+        }
+    };
+    let places = match args.next() {
+        None | Some((Value::Null, _)) => 0,
+        Some((places, places_span)) => match as_i64(&places).map(u32::try_from) {
+            Some(Ok(places)) if places <= MAX_PLACES => places,
+            Some(Ok(_)) => {
+                return Err(env.throw_error(
+                    places_span,
+                    format!(
+                        "Cannot round to {} places, a decimal has at most {}",
+                        places, MAX_PLACES
+                    ),
+                ))
+            }
+            _ => {
+                return Err(env.throw_error(
+                    places_span,
+                    format!(
+                        "The number of places to round to must be an integer from 0, found {}",
+                        places
+                    ),
+                ))
+            }
+        },
+    };
+    let mode = match args.next() {
+        None | Some((Value::Null, _)) => Rounding::HalfEven,
+        Some((mode, mode_span)) => {
+            let rounding = match &mode {
+                Value::String(name) => Rounding::from_name(name),
+                _ => None,
+            };
+            match rounding {
+                Some(rounding) => rounding,
+                None => {
+                    return Err(env.throw_error(
+                        mode_span,
+                        format!(
+                            "Unknown rounding mode `{}`, expected one of {}",
+                            mode,
+                            Rounding::NAMES.join(", ")
+                        ),
+                    ))
+                }
+            }
         }
+    };
+    Ok(Value::Decimal(value.round(places, mode)))
+}
+
+/// The error for an operation on numbers that has no result. `operation`
+/// is how it was written, with values in place of the operands.
+fn arithmetic_error(
+    error: ArithmeticError,
+    op: &TokenType,
+    operation: &str,
+    lhs: &Value,
+    rhs: &Value,
+    span: Span,
+    env: &mut Environment,
+) -> VeloError {
+    match error {
+        ArithmeticError::Operands => env.throw_error(
+            span,
+            format!(
+                "Cannot apply `{}` to a value of type {} and a value of type {}",
+                printer::operator(op),
+                lhs.type_name(),
+                rhs.type_name()
+            ),
+        ),
+        ArithmeticError::Overflow(t) => env.raise(
+            span,
+            "OverflowError",
+            format!(
+                "`{}` overflows, the result does not fit in `{}`",
+                operation,
+                printer::type_name(&t)
+            ),
+        ),
+        ArithmeticError::DivisionByZero => env.raise(
+            span,
+            "ZeroDivisionError",
+            format!("`{}` divides by zero", operation),
+        ),
+        ArithmeticError::ShiftAmount(t) => env.raise(
+            span,
+            "OverflowError",
+            format!(
+                "`{}` shifts by {}, a `{}` can only be shifted by 0 up to {}",
+                operation,
+                rhs,
+                printer::type_name(&t),
+                match t {
+                    Type::Short => 15,
+                    Type::Int => 31,
+                    _ => 63,
+                }
+            ),
+        ),
+        ArithmeticError::Places => env.raise(
+            span,
+            "OverflowError",
+            format!(
+                "`{}` overflows, the result has more than {} places",
+                operation, MAX_PLACES
+            ),
+        ),
+    }
+}
+
+pub fn eval_call_expr(call_expr: &Expression, env: &mut Environment) -> Result<Value, VeloError> {
+    let (name, params, named) = match &call_expr.kind {
+        ExpressionKind::CallExpr {
+            name,
+            params,
+            named,
+        } => (name, params, named),
         _ => unreachable!(),
+    };
+
+    let args = evaluate_args(params, env)?;
+    let mut named_args = Vec::new();
+    for arg in named {
+        named_args.push((arg, eval_expr(&arg.value, env)?));
     }
-    let mut is_lib = false;
 
-    let name = nm.clone();
-    let params = pm.clone();
+    if let Some(lib) = env.lib_functions.iter().find(|lib| lib.name == *name) {
+        let lib = lib.clone();
+        return call_built_in(&lib, call_expr.span, args, named, env);
+    }
 
-    for lib in env.lib_functions.iter() {
-        if name == lib.name {
-            is_lib = true
-        } else {
-            continue;
+    if let Some(function) = env.find_function(name) {
+        return call_function(&function, call_expr.span, args, named_args, None, env);
+    }
+
+    if let Some(declaration) = env.find_struct(name).cloned() {
+        return construct_struct(&declaration, call_expr.span, args, named_args, env);
+    }
+
+    // A variable holding a function
+    match env.variable(name) {
+        Some(Value::Function(function)) => {
+            call_function(&function, call_expr.span, args, named_args, None, env)
         }
+        Some(value) => Err(env.throw_error(
+            call_expr.span,
+            format!(
+                "`{}` is a value of type {}, it cannot be called",
+                name,
+                value.type_name()
+            ),
+        )),
+        None => Err(env.throw_error(call_expr.span, format!("Cannot find function `{}`", name))),
     }
+}
+
+/// A call to built-in function `lib`
+#[inline(never)]
+fn call_built_in(
+    lib: &LibFunction,
+    span: Span,
+    args: Vec<Arg>,
+    named: &[NamedArg],
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let name = &lib.name;
+    if let Some(arg) = named.first() {
+        return Err(env.throw_error(
+            arg.span,
+            format!("Built-in function `{}` does not take named arguments", name),
+        ));
+    }
+    if let Some(len) = lib.param_len {
+        if args.len() > len {
+            return Err(env.throw_error(
+                span,
+                format!(
+                    "Function `{}` takes at most {} argument(s) but {} were given",
+                    name,
+                    len,
+                    args.len()
+                ),
+            ));
+        }
+    }
+    call_lib_function(name, span, args, env)
+}
 
-    if is_lib {
-        match name.as_str() {
-            "print" | "println" => {
-                let mut line = false;
-                if name.as_str() == "println" {
-                    line = true
+/// Evaluates positional arguments, expanding `...items` into one argument
+/// per element. Each value comes with the span of the argument it came from.
+fn evaluate_args(
+    params: &[Expression],
+    env: &mut Environment,
+) -> Result<Vec<(Value, Span)>, VeloError> {
+    let mut args = Vec::new();
+    for param in params {
+        match &param.kind {
+            ExpressionKind::Spread(items) => match eval_expr(items, env)? {
+                Value::Array(items) => {
+                    args.extend(items.into_iter().map(|item| (item, param.span)))
                 }
-                match &params[0] {
-                    Expression::Identifier(ident) => {
-                        if let Some(expr) = env.variables.get(ident) {
-                            match expr {
-                                Expression::StringLiteral(str) => {
-                                    if line {
-                                        let interpolated = interpolate_string(str, env);
-                                        println!("{}", interpolated)
-                                    } else {
-                                        let interpolated = interpolate_string(str, env);
-                                        print!("{}", interpolated)
-                                    }
-                                }
-                                _ => {
-                                    println!("{:#?}", expr);
-                                    todo!()
-                                }
-                            }
-                        } else {
-                            std::process::exit(1)
-                        }
-                    }
-                    Expression::StringLiteral(ident) => {
-                        if line {
-                            let interpolated = interpolate_string(ident, env);
-                            println!("{}", interpolated)
-                        } else {
-                            let interpolated = interpolate_string(ident, env);
-                            print!("{}", interpolated)
-                        }
-                    }
-                    _ => todo!(),
+                value => {
+                    return Err(env.throw_error(
+                        param.span,
+                        format!(
+                            "Cannot spread a value of type {}, expected an array",
+                            value.type_name()
+                        ),
+                    ))
+                }
+            },
+            _ => args.push((eval_expr(param, env)?, param.span)),
+        }
+    }
+
+    Ok(args)
+}
+
+fn eval_method_call(method_call: &Expression, env: &mut Environment) -> Result<Value, VeloError> {
+    let (target, name, params, optional) = match &method_call.kind {
+        ExpressionKind::MethodCall {
+            target,
+            name,
+            params,
+            optional,
+        } => (target, name, params, *optional),
+        _ => unreachable!(),
+    };
+
+    let target = eval_expr(target, env)?;
+    if target == Value::Null {
+        // `?.` skips the call, arguments included
+        if optional {
+            return Ok(target);
+        }
+        return Err(env.throw_error(
+            method_call.span,
+            format!(
+                "Cannot call method `{}` on null, use `?.` to allow a null value",
+                name
+            ),
+        ));
+    }
+
+    let args = evaluate_args(params, env)?;
+
+    // Methods from `impl` blocks are looked up on the struct the value
+    // turns out to be, whatever type it was declared with
+    if let Value::Struct {
+        name: target_name, ..
+    } = &target
+    {
+        if let Some(method) = env.find_method(target_name, name).cloned() {
+            // Methods are declared at the top level, so they run in the
+            // global scope
+            let method = Closure {
+                function: Rc::new(method),
+                scope: env.globals.clone(),
+            };
+            return call_function(
+                &method,
+                method_call.span,
+                args,
+                Vec::new(),
+                Some(target),
+                env,
+            );
+        }
+    }
+
+    match (&target, name.as_str()) {
+        (Value::Range { .. }, "step") => range_step(target, args, method_call.span, env),
+        (Value::Result { .. }, "is_ok" | "is_err" | "unwrap" | "unwrap_or") => {
+            let args = args.into_iter().map(|(arg, _)| arg).collect();
+            result_method(target, name, args, method_call.span, env)
+        }
+        _ => Err(env.throw_error(
+            method_call.span,
+            format!(
+                "Cannot find method `{}` for a value of type {}",
+                name,
+                target.type_name()
+            ),
+        )),
+    }
+}
+
+fn call_lib_function(
+    name: &str,
+    span: Span,
+    args: Vec<(Value, Span)>,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    match name {
+        "print" | "println" => {
+            let text: Vec<String> = args.iter().map(|(arg, _)| arg.to_string()).collect();
+            let text = text.join(" ");
+            if name == "println" {
+                println!("{}", text)
+            } else {
+                print!("{}", text)
+            }
+            Ok(Value::Null)
+        }
+        "input" => {
+            if let Some((prompt, _)) = args.first() {
+                print!("{}", prompt);
+                if let Err(error) = io::stdout().flush() {
+                    return Err(env.raise(
+                        span,
+                        "IOError",
+                        format!("Failed to write the prompt: {}", error),
+                    ));
                 }
             }
-            "input" => {
-                if var.is_none() {
-                    println!("`input` requires a variable to store to");
+            let mut buffer = String::new();
+            match io::stdin().read_line(&mut buffer) {
+                Ok(0) => {
+                    return Err(env.raise(span, "IOError", "Reached the end of input".to_string()))
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    return Err(env.raise(
+                        span,
+                        "IOError",
+                        format!("Failed to read input: {}", error),
+                    ))
                 }
-                let mut buffer = String::new();
-                io::stdin()
-                    .read_line(&mut buffer)
-                    .expect("Failed to read line");
-                let buffer = buffer.trim().to_string();
-
-                let var = var.unwrap();
-                match var {
-                    Statement::VariableAssignment {
-                        constant,
+            }
+
+            Ok(Value::String(buffer.trim().to_string()))
+        }
+        "error" => make_error(args, span, env),
+        "ok" => Ok(ok(args
+            .into_iter()
+            .next()
+            .map_or(Value::Null, |(arg, _)| arg))),
+        "err" => match args.into_iter().next() {
+            Some((value, _)) => Ok(err(value)),
+            None => Err(env.throw_error(
+                span,
+                "Function `err` takes 1 argument but 0 were given".to_string(),
+            )),
+        },
+        "wrapping_add" | "wrapping_sub" | "wrapping_mul" | "saturating_add" | "saturating_sub"
+        | "saturating_mul" => {
+            let (lhs, rhs) = match args.as_slice() {
+                [(lhs, _), (rhs, _)] => (lhs, rhs),
+                _ => {
+                    return Err(env.throw_error(
+                        span,
+                        format!(
+                            "Function `{}` takes 2 argument(s) but {} were given",
+                            name,
+                            args.len()
+                        ),
+                    ))
+                }
+            };
+            let (kind, op) = name.split_once('_').unwrap();
+            let op = match op {
+                "add" => TokenType::Add,
+                "sub" => TokenType::Sub,
+                _ => TokenType::Mul,
+            };
+            let result = match kind {
+                "wrapping" => numbers::wrapping(&op, lhs, rhs),
+                _ => numbers::saturating(&op, lhs, rhs),
+            };
+            result.map_err(|_| {
+                env.throw_error(
+                    span,
+                    format!(
+                        "Function `{}` takes `short`, `int` or `large` values, found a value of type {} and a value of type {}",
                         name,
-                        value: _,
-                    } => env.declare_variable(
-                        format!("{}", &name),
-                        Expression::StringLiteral(buffer),
-                        *constant,
+                        lhs.type_name(),
+                        rhs.type_name()
                     ),
+                )
+            })
+        }
+        "short" | "int" | "large" | "bigint" | "float" | "decimal" => {
+            let (value, arg_span) = match args.as_slice() {
+                [(value, arg_span)] => (value, *arg_span),
+                _ => {
+                    return Err(env.throw_error(
+                        span,
+                        format!(
+                            "Function `{}` takes 1 argument(s) but {} were given",
+                            name,
+                            args.len()
+                        ),
+                    ))
+                }
+            };
+            let to = Type::from_string(name).unwrap();
+            // Exact numbers can be written out in full in a string
+            if let (Value::String(text), Type::BigInt | Type::Decimal) = (value, &to) {
+                let number = match to {
+                    Type::BigInt => BigInt::parse(text.trim()).map(Value::BigInt),
+                    _ => Decimal::parse(text.trim()).map(Value::Decimal),
+                };
+                return number.ok_or_else(|| {
+                    env.raise(
+                        arg_span,
+                        "ParseError",
+                        format!("Cannot parse {:?} as a {}", text, name),
+                    )
+                });
+            }
+            numbers::convert(value, &to).map_err(|error| match error {
+                ArithmeticError::Overflow(t) => env.raise(
+                    arg_span,
+                    "OverflowError",
+                    format!("`{}` does not fit in `{}`", value, printer::type_name(&t)),
+                ),
+                _ => env.throw_error(
+                    arg_span,
+                    format!(
+                        "Function `{}` takes a number{}, found a value of type {}",
+                        name,
+                        if matches!(to, Type::BigInt | Type::Decimal) {
+                            " or a string"
+                        } else {
+                            ""
+                        },
+                        value.type_name()
+                    ),
+                ),
+            })
+        }
+        "round" => round(args, span, env),
+        "read_file" | "write_file" | "parse_number" => {
+            let mut strings = Vec::new();
+            for (arg, arg_span) in &args {
+                match arg {
+                    Value::String(str) => strings.push(str.clone()),
                     _ => {
-                        println!("Idk how this error happens, but if someone gets it, explain what you did please");
-                        std::process::exit(1)
+                        return Err(env.throw_error(
+                            *arg_span,
+                            format!(
+                                "Function `{}` takes strings, found a value of type {}",
+                                name,
+                                arg.type_name()
+                            ),
+                        ))
                     }
-                };
+                }
             }
-            _ => unimplemented!(),
+            call_fallible(name, &strings, span, env)
         }
+        // A host can list a function here that Velo has no body for
+        _ => Err(env.throw_error(span, format!("Cannot find built-in function `{}`", name))),
     }
 }
 
-pub fn evaluate_binary(expr: &Expression, env: &Environment) -> f32 {
-    match expr {
-        Expression::Float(val) => *val,
-        Expression::Identifier(val) => match env.variables.get(val) {
-            Some(Expression::Float(val)) => *val,
-            _ => todo!(),
-        },
-        Expression::BinaryOp { lhs, op, rhs } => {
-            let lhs = evaluate_binary(lhs, env);
-            let rhs = evaluate_binary(rhs, env);
-            match op {
-                TokenType::Add => lhs + rhs,
-                TokenType::Sub => lhs - rhs,
-                TokenType::Mul => lhs * rhs,
-                TokenType::Div => lhs / rhs,
-                _ => unreachable!(),
-            }
+/// Built-in functions that return a result rather than throw
+fn call_fallible(
+    name: &str,
+    args: &[String],
+    span: Span,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    match (name, args) {
+        ("read_file", [path]) => Ok(match fs::read_to_string(path) {
+            Ok(contents) => ok(Value::String(contents)),
+            Err(error) => err_kind("IOError", format!("Cannot read '{}': {}", path, error)),
+        }),
+        ("write_file", [path, contents]) => Ok(match fs::write(path, contents) {
+            Ok(()) => ok(Value::Null),
+            Err(error) => err_kind("IOError", format!("Cannot write '{}': {}", path, error)),
+        }),
+        ("parse_number", [text]) => {
+            let number = match text.trim().parse::<i64>() {
+                Ok(number) => Some(numbers::integer_literal(number)),
+                Err(_) => text.trim().parse::<f64>().ok().map(Value::Float),
+            };
+            Ok(match number {
+                Some(number) => ok(number),
+                None => err_kind("ParseError", format!("Cannot parse {:?} as a number", text)),
+            })
         }
         _ => {
-            println!("{:#?}", expr);
-            unreachable!()
+            let expected = if name == "write_file" { 2 } else { 1 };
+            Err(env.throw_error(
+                span,
+                format!(
+                    "Function `{}` takes {} argument(s) but {} were given",
+                    name,
+                    expected,
+                    args.len()
+                ),
+            ))
         }
     }
 }
 
-pub fn evaluate_conditional(expr: &Expression, env: &Environment) -> Expression {
-    match expr {
-        Expression::Conditional { lhs, op, rhs } => {
-            let lhs = evaluate_binary(lhs, env);
-            let rhs = evaluate_binary(rhs, env);
-            match op {
-                ConditionType::Equal => Expression::Bool(lhs == rhs),
-                ConditionType::NotEqual => Expression::Bool(lhs != rhs),
-                ConditionType::Unary => todo!(),
+/// Runs a user function, or a method with `receiver` bound to `self`
+pub fn call_function(
+    function: &Closure,
+    call_span: Span,
+    args: Vec<(Value, Span)>,
+    named_args: Vec<(&NamedArg, Value)>,
+    receiver: Option<Value>,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    if env.depth >= env.max_depth {
+        let name = match &function.function.kind {
+            StatementKind::Function { name, .. } => name,
+            _ => unreachable!(),
+        };
+        return Err(env.raise(
+            call_span,
+            "RecursionError",
+            format!(
+                "Cannot call function `{}`, {} calls are already running",
+                name, env.max_depth
+            ),
+        ));
+    }
+    env.depth += 1;
+    let value = run_call(function, call_span, args, named_args, receiver, env);
+    env.depth -= 1;
+    value
+}
+
+/// `call_function` once the call is known not to go too deep. What each
+/// step keeps is in a function of its own, so that a call takes as little
+/// of the stack as it can.
+fn run_call(
+    function: &Closure,
+    call_span: Span,
+    args: Vec<(Value, Span)>,
+    named_args: Vec<(&NamedArg, Value)>,
+    receiver: Option<Value>,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let (name, params, body, ret_type) = match &function.function.kind {
+        StatementKind::Function {
+            name,
+            params,
+            body,
+            ret_type,
+            ..
+        } => (name, params, body, ret_type),
+        _ => unreachable!(),
+    };
+    // The parser only allows a rest parameter last
+    let (fixed, rest) = match params.split_last() {
+        Some((last, fixed)) if last.rest => (fixed, Some(last)),
+        _ => (params.as_slice(), None),
+    };
+    let all_required = fixed.iter().all(|param| param.default.is_none());
+    if args.len() > fixed.len() && rest.is_none() {
+        let at_most = if all_required { "" } else { "at most " };
+        return Err(env.throw_error(
+            call_span,
+            format!(
+                "Function `{}` takes {}{} argument(s) but {} were given",
+                name,
+                at_most,
+                fixed.len(),
+                args.len()
+            ),
+        ));
+    }
+
+    let arg_count = args.len();
+    let no_named = named_args.is_empty();
+    let (slots, rest_args) = arg_slots(name, fixed, rest, args, named_args, env)?;
+
+    let mut bindings = Bindings::new();
+    // The body runs in a new scope inside the one the function was
+    // declared in, so it sees what was in scope there and nothing of the
+    // caller's
+    let value = env.in_scope(&function.scope, |env| {
+        if let Some(receiver) = receiver {
+            env.bind("self", receiver);
+        }
+        let call = Call {
+            name,
+            fixed,
+            rest,
+            span: call_span,
+            arg_count,
+            no_named,
+        };
+        bind_params(&call, slots, rest_args, &mut bindings, env)?;
+
+        match execute_block(body.stmts(), env) {
+            Ok(Flow::Return(value)) => Ok(value),
+            Ok(Flow::Next(_)) => Ok(Value::Null),
+            Err(error) => match env.propagating.take() {
+                Some(value) => Ok(value),
+                None => Err(error),
+            },
+        }
+    })?;
+    check_return(name, ret_type, &value, &bindings, call_span, env)?;
+    Ok(coerce(value, ret_type))
+}
+
+/// An argument's value and the span of the argument that gave it
+type Arg = (Value, Span);
+
+/// Each fixed parameter's argument, if it was given, then the arguments
+/// left over for the rest parameter
+#[inline(never)]
+fn arg_slots(
+    name: &str,
+    fixed: &[Param],
+    rest: Option<&Param>,
+    args: Vec<Arg>,
+    named_args: Vec<(&NamedArg, Value)>,
+    env: &mut Environment,
+) -> Result<(Vec<Option<Arg>>, Vec<Arg>), VeloError> {
+    let mut args = args.into_iter();
+    let mut slots: Vec<Option<Arg>> = Vec::new();
+    for _ in fixed {
+        slots.push(args.next());
+    }
+    let rest_args: Vec<Arg> = args.collect();
+
+    for (arg, value) in named_args {
+        match fixed.iter().position(|param| param.name == arg.name) {
+            Some(i) if slots[i].is_some() => {
+                return Err(env.throw_error(
+                    arg.span,
+                    format!(
+                        "Argument `{}` of function `{}` is given both by position and by name",
+                        arg.name, name
+                    ),
+                ))
+            }
+            Some(i) => slots[i] = Some((value, arg.span)),
+            None if rest.is_some_and(|rest| rest.name == arg.name) => {
+                return Err(env.throw_error(
+                    arg.span,
+                    format!(
+                        "Rest parameter `{}` of function `{}` cannot be given by name",
+                        arg.name, name
+                    ),
+                ))
+            }
+            None => {
+                return Err(env.throw_error(
+                    arg.span,
+                    format!("Function `{}` has no parameter named `{}`", name, arg.name),
+                ))
             }
         }
-        _ => unreachable!(),
     }
+    Ok((slots, rest_args))
+}
+
+/// What binding the parameters of a call needs to know about it
+struct Call<'a> {
+    name: &'a str,
+    fixed: &'a [Param],
+    rest: Option<&'a Param>,
+    span: Span,
+    /// How many arguments were given by position
+    arg_count: usize,
+    no_named: bool,
+}
+
+/// Binds the parameters of `call` in the current scope, checked against
+/// their declared types
+fn bind_params(
+    call: &Call,
+    slots: Vec<Option<Arg>>,
+    rest_args: Vec<Arg>,
+    bindings: &mut Bindings,
+    env: &mut Environment,
+) -> Result<(), VeloError> {
+    let all_required = call.fixed.iter().all(|param| param.default.is_none());
+    let owner = format!("function `{}`", call.name);
+    for (param, slot) in call.fixed.iter().zip(slots) {
+        let (value, span) = match (slot, &param.default) {
+            (Some(slot), _) => slot,
+            // Defaults are evaluated in the call, after the parameters
+            // before them are bound
+            (None, Some(default)) => (eval_expr(default, env)?, default.span),
+            (None, None) if all_required && call.no_named => {
+                let at_least = if call.rest.is_some() { "at least " } else { "" };
+                return Err(env.throw_error(
+                    call.span,
+                    format!(
+                        "Function `{}` takes {}{} argument(s) but {} were given",
+                        call.name,
+                        at_least,
+                        call.fixed.len(),
+                        call.arg_count
+                    ),
+                ));
+            }
+            (None, None) => {
+                return Err(env.throw_error(
+                    call.span,
+                    format!(
+                        "Missing argument `{}` in call to function `{}`",
+                        param.name, call.name
+                    ),
+                ))
+            }
+        };
+
+        check_not_null(call.name, &param.name, &param.param_type, &value, span, env)?;
+        let what = format!("parameter `{}` of {}", param.name, owner);
+        check_type_exists(&param.param_type, &what, call.span, env)?;
+        check_param_type(call.name, &param.name, &param.param_type, &value, span, env)?;
+        check_implements(&param.param_type, &value, &what, span, env)?;
+        bind_type_params(&owner, &param.param_type, &value, span, bindings, env)?;
+        let value = coerce(value, &param.param_type);
+        env.bind(&param.name, value);
+    }
+
+    if let Some(rest) = call.rest {
+        let element = match &rest.param_type {
+            Type::Array(element) => element,
+            _ => unreachable!(),
+        };
+        let what = format!("parameter `{}` of {}", rest.name, owner);
+        check_type_exists(element, &what, call.span, env)?;
+        let mut items = Vec::new();
+        for (value, span) in rest_args {
+            check_not_null(call.name, &rest.name, element, &value, span, env)?;
+            check_param_type(call.name, &rest.name, element, &value, span, env)?;
+            check_implements(element, &value, &what, span, env)?;
+            bind_type_params(&owner, element, &value, span, bindings, env)?;
+            items.push(coerce(value, element));
+        }
+        env.bind(&rest.name, Value::Array(items));
+    }
+    Ok(())
+}
+
+/// What function `name` returned must be of its declared `ret_type`
+#[inline(never)]
+fn check_return(
+    name: &str,
+    ret_type: &Type,
+    value: &Value,
+    bindings: &Bindings,
+    call_span: Span,
+    env: &mut Environment,
+) -> Result<(), VeloError> {
+    if *ret_type == Type::Void && *value != Value::Null {
+        return Err(env.throw_error(
+            call_span,
+            format!(
+                "Function `{}` does not return a value, but returned `{}`",
+                name,
+                printer::type_name(&value_type(value))
+            ),
+        ));
+    }
+    if !mentions_params(ret_type) && !fits(value, ret_type, env) {
+        return Err(env.throw_error(
+            call_span,
+            format!(
+                "Function `{}` must return `{}`, but returned `{}`",
+                name,
+                printer::type_name(ret_type),
+                printer::type_name(&value_type(value))
+            ),
+        ));
+    }
+    if *value == Value::Null && !accepts_null(ret_type) {
+        return Err(env.throw_error(
+            call_span,
+            format!(
+                "Function `{}` must return `{}`, but returned null",
+                name,
+                printer::type_name(ret_type)
+            ),
+        ));
+    }
+    // What a generic function returns must agree with the type arguments
+    // inferred from its arguments
+    if mentions_params(ret_type) && *value != Value::Null {
+        let expected = substitute(ret_type, bindings);
+        if unify(&value_type(value), &expected).is_none() {
+            return Err(env.throw_error(
+                call_span,
+                format!(
+                    "Function `{}` must return `{}`, which is `{}` here, but returned `{}`",
+                    name,
+                    printer::type_name(ret_type),
+                    printer::type_name(&expected),
+                    printer::type_name(&value_type(value))
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Arguments must fit the declared type of their parameter, numbers
+/// widening
+fn check_param_type(
+    function: &str,
+    param: &str,
+    param_type: &Type,
+    value: &Value,
+    span: Span,
+    env: &mut Environment,
+) -> Result<(), VeloError> {
+    if fits(value, param_type, env) {
+        return Ok(());
+    }
+    Err(env.throw_error(
+        span,
+        format!(
+            "Function `{}` expects `{}` for parameter `{}`, found `{}`",
+            function,
+            printer::type_name(param_type),
+            param,
+            printer::type_name(&value_type(value))
+        ),
+    ))
+}
+
+/// Declared types exclude null unless they are written `T?`
+fn check_not_null(
+    function: &str,
+    param: &str,
+    param_type: &Type,
+    value: &Value,
+    span: Span,
+    env: &mut Environment,
+) -> Result<(), VeloError> {
+    if *value == Value::Null && !accepts_null(param_type) {
+        return Err(env.throw_error(
+            span,
+            format!(
+                "Function `{}` expects `{}` for parameter `{}`, found null",
+                function,
+                printer::type_name(param_type),
+                param
+            ),
+        ));
+    }
+
+    Ok(())
 }
@@ -0,0 +1,165 @@
+//! Interfaces and the `impl` blocks that make structs implement them.
+//!
+//! A value of an interface type is always a struct instance, so a method
+//! call on it is dispatched on the struct it turns out to be at runtime.
+
+use super::super::environment::Environment;
+use super::super::value::Value;
+use crate::error::VeloError;
+use crate::syntax::ast::{Param, Statement, StatementKind};
+use crate::syntax::lexer::Type;
+use crate::syntax::printer::{self, method_signature};
+use crate::syntax::span::Span;
+
+/// Checks an `impl` block against its interface and records it. Every
+/// method of the interface must be there with the same parameter and
+/// return types, and nothing else.
+pub fn declare_impl(stmt: &Statement, env: &mut Environment) -> Result<(), VeloError> {
+    let (interface, target, methods) = match &stmt.kind {
+        StatementKind::Impl {
+            interface,
+            target,
+            methods,
+        } => (interface, target, methods),
+        _ => unreachable!(),
+    };
+    let block = format!("impl {} for {}", interface, target);
+
+    let required = match env.find_interface(interface).map(|i| &i.kind) {
+        Some(StatementKind::Interface { methods, .. }) => methods.clone(),
+        _ if env.find_struct(interface).is_some() => {
+            return Err(env.throw_error(
+                stmt.span,
+                format!(
+                    "Cannot implement `{}` for `{}`, `{}` is a struct, not an interface",
+                    interface, target, interface
+                ),
+            ))
+        }
+        _ => {
+            return Err(env.throw_error(
+                stmt.span,
+                format!("Cannot find interface `{}` in `{}`", interface, block),
+            ))
+        }
+    };
+    if env.find_struct(target).is_none() {
+        return Err(env.throw_error(
+            stmt.span,
+            format!("Cannot find struct `{}` in `{}`", target, block),
+        ));
+    }
+
+    for method in methods {
+        let name = match &method.kind {
+            StatementKind::Function { name, .. } => name,
+            _ => unreachable!(),
+        };
+        if !required.iter().any(|m| m.name == *name) {
+            return Err(env.throw_error(
+                method.span,
+                format!(
+                    "Method `{}` in `{}` is not part of interface `{}`",
+                    name, block, interface
+                ),
+            ));
+        }
+    }
+
+    for signature in &required {
+        let method = methods.iter().find(
+            |m| matches!(&m.kind, StatementKind::Function { name, .. } if *name == signature.name),
+        );
+        let (params, ret_type, span) = match method.map(|m| (&m.kind, m.span)) {
+            Some((
+                StatementKind::Function {
+                    params, ret_type, ..
+                },
+                span,
+            )) => (params, ret_type, span),
+            _ => {
+                return Err(env.throw_error(
+                    stmt.span,
+                    format!(
+                        "`{}` is missing method `{}` of interface `{}`",
+                        block, signature.name, interface
+                    ),
+                ))
+            }
+        };
+
+        if !same_params(params, &signature.params) || *ret_type != signature.ret_type {
+            return Err(env.throw_error(
+                span,
+                format!(
+                    "Method `{}` in `{}` does not match `{}` from interface `{}`",
+                    signature.name,
+                    block,
+                    method_signature(signature),
+                    interface
+                ),
+            ));
+        }
+    }
+
+    env.impls.push(stmt.clone());
+    Ok(())
+}
+
+/// Parameter names may differ, their types and kinds may not
+fn same_params(params: &[Param], expected: &[Param]) -> bool {
+    params.len() == expected.len()
+        && params
+            .iter()
+            .zip(expected)
+            .all(|(a, b)| a.param_type == b.param_type && a.rest == b.rest)
+}
+
+/// Checks that a value given for `declared` implements the interfaces it
+/// names. `what` says where the type was written, e.g. "parameter `s` of
+/// function `draw`".
+pub fn check_implements(
+    declared: &Type,
+    value: &Value,
+    what: &str,
+    span: Span,
+    env: &mut Environment,
+) -> Result<(), VeloError> {
+    let interface = match declared {
+        Type::Optional(inner) if *value != Value::Null => {
+            return check_implements(inner, value, what, span, env)
+        }
+        Type::Array(element) => {
+            if let Value::Array(items) = value {
+                for item in items {
+                    check_implements(element, item, what, span, env)?;
+                }
+            }
+            return Ok(());
+        }
+        Type::Named { name, .. } if env.find_interface(name).is_some() => name,
+        _ => return Ok(()),
+    };
+
+    let implemented = match value {
+        Value::Struct { name, .. } => env.implements(name, interface),
+        _ => false,
+    };
+    if implemented {
+        return Ok(());
+    }
+    Err(env.throw_error(
+        span,
+        format!(
+            "Expected `{}` for {}, found {} `{}`, which does not implement it",
+            printer::type_name(declared),
+            what,
+            if matches!(value, Value::Struct { .. }) {
+                "struct"
+            } else {
+                "a value of type"
+            },
+            value.type_name()
+        ),
+    ))
+}
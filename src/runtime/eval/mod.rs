@@ -1 +1,8 @@
+pub mod errors;
 pub mod expr;
+pub mod interfaces;
+pub mod numbers;
+pub mod results;
+pub mod sequence;
+pub mod structs;
+pub mod types;
@@ -0,0 +1,613 @@
+//! Arithmetic on numbers.
+//!
+//! Integers are `short`, `int` and `large` (16, 32 and 64 bits), and
+//! `float` is 64 bits. When the two sides of an operator differ, the
+//! narrower one is promoted first: integers to the wider integer, and any
+//! integer to `float` when the other side is a `float`. Integer arithmetic
+//! is checked, a result that does not fit its type is an `OverflowError`
+//! rather than wrapping around. `/` on integers divides and drops the
+//! fraction. Shifts keep the type of the value that is shifted.
+//!
+//! `bigint` and `decimal` are exact and never overflow. Integers promote
+//! to `bigint`, and integers and `bigint`s to `decimal`. A `bigint` with a
+//! `float` gives a `float`, but a `decimal` and a `float` do not mix, the
+//! point of a `decimal` being that it is exact.
+//!
+//! `<`, `<=`, `>` and `>=` compare numbers by value across the types, with
+//! the same rule that a `decimal` and a `float` do not mix.
+
+use std::cmp::Ordering;
+
+use super::super::bigint::BigInt;
+use super::super::decimal::Decimal;
+use super::super::value::Value;
+use crate::syntax::ast::{ConditionType, ExpressionKind};
+use crate::syntax::lexer::{TokenType, Type};
+
+/// Why an operation on numbers has no result
+#[derive(Debug, PartialEq)]
+pub enum ArithmeticError {
+    /// The operator does not take values of these types
+    Operands,
+    /// The result does not fit the type it would have
+    Overflow(Type),
+    DivisionByZero,
+    /// Shifting by a negative amount, or by the width of the type or more
+    ShiftAmount(Type),
+    /// A decimal product with more than `MAX_PLACES` places
+    Places,
+}
+
+/// `lhs op rhs` for two numbers
+pub fn binary(op: &TokenType, lhs: &Value, rhs: &Value) -> Result<Value, ArithmeticError> {
+    if matches!(op, TokenType::ShiftLeft | TokenType::ShiftRight) {
+        return shift(op, lhs, rhs);
+    }
+
+    match (integer(lhs), integer(rhs)) {
+        (Some((lhs, lhs_type)), Some((rhs, rhs_type))) => {
+            let t = wider(lhs_type, rhs_type);
+            let result = match op {
+                TokenType::Add => lhs + rhs,
+                TokenType::Sub => lhs - rhs,
+                TokenType::Mul => lhs * rhs,
+                TokenType::Div | TokenType::Mod if rhs == 0 => {
+                    return Err(ArithmeticError::DivisionByZero)
+                }
+                TokenType::Div => lhs / rhs,
+                TokenType::Mod => lhs % rhs,
+                TokenType::BitwiseAnd => lhs & rhs,
+                TokenType::BitwiseOr => lhs | rhs,
+                TokenType::BitwiseXor => lhs ^ rhs,
+                _ => return Err(ArithmeticError::Operands),
+            };
+            from_integer(result, &t).ok_or(ArithmeticError::Overflow(t))
+        }
+        _ if is_decimal(lhs) || is_decimal(rhs) => {
+            let (lhs, rhs) = match (decimal(lhs), decimal(rhs)) {
+                (Some(lhs), Some(rhs)) => (lhs, rhs),
+                _ => return Err(ArithmeticError::Operands),
+            };
+            let result = match op {
+                TokenType::Add => Some(&lhs + &rhs),
+                TokenType::Sub => Some(&lhs - &rhs),
+                TokenType::Mul => {
+                    return lhs
+                        .checked_mul(&rhs)
+                        .map(Value::Decimal)
+                        .ok_or(ArithmeticError::Places)
+                }
+                TokenType::Div => lhs.checked_div(&rhs),
+                TokenType::Mod => lhs.checked_rem(&rhs),
+                _ => return Err(ArithmeticError::Operands),
+            };
+            result
+                .map(Value::Decimal)
+                .ok_or(ArithmeticError::DivisionByZero)
+        }
+        _ if matches!(lhs, Value::BigInt(_)) || matches!(rhs, Value::BigInt(_)) => {
+            let (lhs, rhs) = match (big(lhs), big(rhs)) {
+                (Some(lhs), Some(rhs)) => (lhs, rhs),
+                _ => return float_binary(op, lhs, rhs),
+            };
+            let result = match op {
+                TokenType::Add => &lhs + &rhs,
+                TokenType::Sub => &lhs - &rhs,
+                TokenType::Mul => &lhs * &rhs,
+                TokenType::Div | TokenType::Mod => {
+                    let (quotient, remainder) =
+                        lhs.div_rem(&rhs).ok_or(ArithmeticError::DivisionByZero)?;
+                    if *op == TokenType::Div {
+                        quotient
+                    } else {
+                        remainder
+                    }
+                }
+                _ => return Err(ArithmeticError::Operands),
+            };
+            Ok(Value::BigInt(result))
+        }
+        _ => float_binary(op, lhs, rhs),
+    }
+}
+
+fn float_binary(op: &TokenType, lhs: &Value, rhs: &Value) -> Result<Value, ArithmeticError> {
+    let (lhs, rhs) = match (float(lhs), float(rhs)) {
+        (Some(lhs), Some(rhs)) => (lhs, rhs),
+        _ => return Err(ArithmeticError::Operands),
+    };
+    Ok(Value::Float(match op {
+        TokenType::Add => lhs + rhs,
+        TokenType::Sub => lhs - rhs,
+        TokenType::Mul => lhs * rhs,
+        TokenType::Div => lhs / rhs,
+        TokenType::Mod => lhs % rhs,
+        _ => return Err(ArithmeticError::Operands),
+    }))
+}
+
+/// `lhs << rhs` and `lhs >> rhs`, which need integers on both sides
+fn shift(op: &TokenType, lhs: &Value, rhs: &Value) -> Result<Value, ArithmeticError> {
+    let ((lhs, t), (rhs, _)) = match (integer(lhs), integer(rhs)) {
+        (Some(lhs), Some(rhs)) => (lhs, rhs),
+        _ => return Err(ArithmeticError::Operands),
+    };
+    if rhs < 0 || rhs >= bits(&t) as i128 {
+        return Err(ArithmeticError::ShiftAmount(t));
+    }
+
+    let result = match op {
+        TokenType::ShiftLeft => lhs << rhs,
+        _ => lhs >> rhs,
+    };
+    from_integer(result, &t).ok_or(ArithmeticError::Overflow(t))
+}
+
+/// `wrapping_add` and the like, whose result wraps around to fit instead
+/// of overflowing
+pub fn wrapping(op: &TokenType, lhs: &Value, rhs: &Value) -> Result<Value, ArithmeticError> {
+    let (result, t) = unbounded(op, lhs, rhs)?;
+    Ok(match t {
+        Type::Short => Value::Short(result as i16),
+        Type::Int => Value::Int(result as i32),
+        _ => Value::Large(result as i64),
+    })
+}
+
+/// `saturating_add` and the like, whose result stops at the smallest or
+/// largest value of its type instead of overflowing
+pub fn saturating(op: &TokenType, lhs: &Value, rhs: &Value) -> Result<Value, ArithmeticError> {
+    let (result, t) = unbounded(op, lhs, rhs)?;
+    let (min, max) = match t {
+        Type::Short => (i16::MIN as i128, i16::MAX as i128),
+        Type::Int => (i32::MIN as i128, i32::MAX as i128),
+        _ => (i64::MIN as i128, i64::MAX as i128),
+    };
+    Ok(from_integer(result.clamp(min, max), &t).unwrap())
+}
+
+/// The exact result of adding, subtracting or multiplying two integers,
+/// and the type it should have
+fn unbounded(op: &TokenType, lhs: &Value, rhs: &Value) -> Result<(i128, Type), ArithmeticError> {
+    let ((lhs, lhs_type), (rhs, rhs_type)) = match (integer(lhs), integer(rhs)) {
+        (Some(lhs), Some(rhs)) => (lhs, rhs),
+        _ => return Err(ArithmeticError::Operands),
+    };
+    let result = match op {
+        TokenType::Add => lhs + rhs,
+        TokenType::Sub => lhs - rhs,
+        _ => lhs * rhs,
+    };
+    Ok((result, wider(lhs_type, rhs_type)))
+}
+
+/// A number as type `to`. A `float` or `decimal` made into an integer
+/// drops its fraction, and must be in range of it.
+pub fn convert(value: &Value, to: &Type) -> Result<Value, ArithmeticError> {
+    let overflow = || ArithmeticError::Overflow(to.clone());
+    match to {
+        Type::Float => {
+            return match value {
+                Value::Decimal(value) => Ok(Value::Float(value.to_f64())),
+                _ => float(value)
+                    .map(Value::Float)
+                    .ok_or(ArithmeticError::Operands),
+            }
+        }
+        Type::Decimal => {
+            return match value {
+                Value::Float(value) => Decimal::from_f64(*value)
+                    .map(Value::Decimal)
+                    .ok_or_else(overflow),
+                _ => decimal(value)
+                    .map(Value::Decimal)
+                    .ok_or(ArithmeticError::Operands),
+            }
+        }
+        _ => {}
+    }
+
+    let whole = match value {
+        Value::Float(value) => BigInt::from_f64(*value).ok_or_else(overflow)?,
+        Value::Decimal(value) => value.trunc(),
+        _ => big(value).ok_or(ArithmeticError::Operands)?,
+    };
+    if *to == Type::BigInt {
+        return Ok(Value::BigInt(whole));
+    }
+    whole
+        .to_i128()
+        .and_then(|whole| from_integer(whole, to))
+        .ok_or_else(overflow)
+}
+
+/// Whether a number can be given where a `to` is expected without
+/// converting it. Numbers widen along the tower, and an integer narrows to
+/// an integer type that it is in range of.
+pub fn fits_type(value: &Value, to: &Type) -> bool {
+    match (value, to) {
+        (Value::Float(_), _) => *to == Type::Float,
+        (Value::Decimal(_), _) => *to == Type::Decimal,
+        (_, Type::Float | Type::Decimal | Type::BigInt) => true,
+        _ => convert(value, to).is_ok(),
+    }
+}
+
+/// Whether two numbers are equal once promoted to the same type
+pub fn equal(lhs: &Value, rhs: &Value) -> bool {
+    if let (Some((lhs, _)), Some((rhs, _))) = (integer(lhs), integer(rhs)) {
+        return lhs == rhs;
+    }
+    if let (Some(lhs), Some(rhs)) = (decimal(lhs), decimal(rhs)) {
+        return lhs == rhs;
+    }
+    let approximate = |value: &Value| match value {
+        Value::Decimal(value) => Some(value.to_f64()),
+        _ => float(value),
+    };
+    approximate(lhs) == approximate(rhs)
+}
+
+/// `lhs op rhs` for `<`, `<=`, `>` and `>=`. Nothing is ordered with NaN.
+pub fn ordering(op: &ConditionType, lhs: &Value, rhs: &Value) -> Result<bool, ArithmeticError> {
+    let ordering = compare(lhs, rhs)?;
+    Ok(match op {
+        ConditionType::Less => ordering == Some(Ordering::Less),
+        ConditionType::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        ConditionType::Greater => ordering == Some(Ordering::Greater),
+        ConditionType::GreaterEqual => {
+            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+        }
+        _ => return Err(ArithmeticError::Operands),
+    })
+}
+
+/// How two numbers compare by value, exactly unless one is a `float`
+fn compare(lhs: &Value, rhs: &Value) -> Result<Option<Ordering>, ArithmeticError> {
+    if let (Some((lhs, _)), Some((rhs, _))) = (integer(lhs), integer(rhs)) {
+        return Ok(Some(lhs.cmp(&rhs)));
+    }
+    if is_decimal(lhs) || is_decimal(rhs) {
+        return match (decimal(lhs), decimal(rhs)) {
+            (Some(lhs), Some(rhs)) => Ok(Some(lhs.cmp(&rhs))),
+            _ => Err(ArithmeticError::Operands),
+        };
+    }
+    if let (Some(lhs), Some(rhs)) = (big(lhs), big(rhs)) {
+        return Ok(Some(lhs.cmp(&rhs)));
+    }
+    match (float(lhs), float(rhs)) {
+        (Some(lhs), Some(rhs)) => Ok(lhs.partial_cmp(&rhs)),
+        _ => Err(ArithmeticError::Operands),
+    }
+}
+
+pub fn is_number_type(t: &Type) -> bool {
+    matches!(
+        t,
+        Type::Short | Type::Int | Type::Large | Type::BigInt | Type::Float | Type::Decimal
+    )
+}
+
+pub fn is_number(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Short(_)
+            | Value::Int(_)
+            | Value::Large(_)
+            | Value::BigInt(_)
+            | Value::Float(_)
+            | Value::Decimal(_)
+    )
+}
+
+/// The value of an integer, for counting and indexing. Floats are not
+/// integers, even whole ones.
+pub fn as_i64(value: &Value) -> Option<i64> {
+    match value {
+        Value::BigInt(value) => value.to_i128().and_then(|value| i64::try_from(value).ok()),
+        _ => integer(value).map(|(value, _)| value as i64),
+    }
+}
+
+/// An integer literal, as the narrowest of `int` and `large` that holds it
+pub fn integer_literal(value: i64) -> Value {
+    match i32::try_from(value) {
+        Ok(value) => Value::Int(value),
+        Err(_) => Value::Large(value),
+    }
+}
+
+/// The value of a number literal
+pub fn literal_value(kind: &ExpressionKind) -> Option<Value> {
+    match kind {
+        ExpressionKind::Short(value) => Some(Value::Short(*value)),
+        ExpressionKind::Int(value) => Some(Value::Int(*value)),
+        ExpressionKind::Large(value) => Some(Value::Large(*value)),
+        ExpressionKind::Float(value) => Some(Value::Float(*value)),
+        ExpressionKind::BigInt(text) => BigInt::parse(text).map(Value::BigInt),
+        ExpressionKind::Decimal(text) => Decimal::parse(text).map(Value::Decimal),
+        _ => None,
+    }
+}
+
+/// The literal that evaluates to a number
+pub fn value_literal(value: &Value) -> Option<ExpressionKind> {
+    match value {
+        Value::Short(value) => Some(ExpressionKind::Short(*value)),
+        Value::Int(value) => Some(ExpressionKind::Int(*value)),
+        Value::Large(value) => Some(ExpressionKind::Large(*value)),
+        Value::Float(value) => Some(ExpressionKind::Float(*value)),
+        Value::BigInt(value) => Some(ExpressionKind::BigInt(value.to_string())),
+        Value::Decimal(value) => Some(ExpressionKind::Decimal(value.to_string())),
+        _ => None,
+    }
+}
+
+/// An integer and its type. Every integer fits in an `i128` with room for
+/// the result of any operation on two of them.
+fn integer(value: &Value) -> Option<(i128, Type)> {
+    match value {
+        Value::Short(value) => Some((*value as i128, Type::Short)),
+        Value::Int(value) => Some((*value as i128, Type::Int)),
+        Value::Large(value) => Some((*value as i128, Type::Large)),
+        _ => None,
+    }
+}
+
+/// A number as a float, for arithmetic with one. A `decimal` is not,
+/// converting it would lose the exactness it is for.
+fn float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Float(value) => Some(*value),
+        Value::BigInt(value) => Some(value.to_f64()),
+        _ => integer(value).map(|(value, _)| value as f64),
+    }
+}
+
+/// An integer of any type as a `bigint`
+fn big(value: &Value) -> Option<BigInt> {
+    match value {
+        Value::BigInt(value) => Some(value.clone()),
+        _ => integer(value).map(|(value, _)| BigInt::from_i128(value)),
+    }
+}
+
+/// An integer or `decimal` as a `decimal`
+fn decimal(value: &Value) -> Option<Decimal> {
+    match value {
+        Value::Decimal(value) => Some(value.clone()),
+        _ => big(value).map(Decimal::from_integer),
+    }
+}
+
+fn is_decimal(value: &Value) -> bool {
+    matches!(value, Value::Decimal(_))
+}
+
+/// `value` as an integer of type `t`, if it is in range
+fn from_integer(value: i128, t: &Type) -> Option<Value> {
+    match t {
+        Type::Short => i16::try_from(value).ok().map(Value::Short),
+        Type::Int => i32::try_from(value).ok().map(Value::Int),
+        _ => i64::try_from(value).ok().map(Value::Large),
+    }
+}
+
+fn wider(a: Type, b: Type) -> Type {
+    match (a, b) {
+        (Type::Large, _) | (_, Type::Large) => Type::Large,
+        (Type::Int, _) | (_, Type::Int) => Type::Int,
+        _ => Type::Short,
+    }
+}
+
+fn bits(t: &Type) -> u32 {
+    match t {
+        Type::Short => 16,
+        Type::Int => 32,
+        _ => 64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{binary, convert, ordering, saturating, wrapping, ArithmeticError};
+    use crate::runtime::bigint::BigInt;
+    use crate::runtime::decimal::Decimal;
+    use crate::runtime::value::Value;
+    use crate::syntax::ast::ConditionType;
+    use crate::syntax::lexer::{TokenType, Type};
+
+    fn dec(text: &str) -> Value {
+        Value::Decimal(Decimal::parse(text).unwrap())
+    }
+
+    fn big(value: i128) -> Value {
+        Value::BigInt(BigInt::from_i128(value))
+    }
+
+    /// Zero, of the type of `value`
+    fn zero_like(value: &Value) -> Value {
+        match value {
+            Value::Short(_) => Value::Short(0),
+            Value::Int(_) => Value::Int(0),
+            _ => Value::Large(0),
+        }
+    }
+
+    #[test]
+    fn checked_overflow() {
+        let overflows = |op, lhs, rhs, t| {
+            assert_eq!(binary(&op, &lhs, &rhs), Err(ArithmeticError::Overflow(t)))
+        };
+        overflows(
+            TokenType::Add,
+            Value::Short(i16::MAX),
+            Value::Short(1),
+            Type::Short,
+        );
+        overflows(
+            TokenType::Sub,
+            Value::Int(i32::MIN),
+            Value::Int(1),
+            Type::Int,
+        );
+        overflows(
+            TokenType::Mul,
+            Value::Large(i64::MAX),
+            Value::Large(2),
+            Type::Large,
+        );
+        // The wider type is checked, not the narrower one
+        assert_eq!(
+            binary(&TokenType::Add, &Value::Short(i16::MAX), &Value::Int(1)),
+            Ok(Value::Int(i16::MAX as i32 + 1))
+        );
+        assert_eq!(
+            binary(&TokenType::Div, &Value::Int(7), &Value::Int(0)),
+            Err(ArithmeticError::DivisionByZero)
+        );
+        assert_eq!(
+            binary(&TokenType::Mod, &big(7), &big(0)),
+            Err(ArithmeticError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn min_divided_by_minus_one() {
+        let cases = [
+            (Value::Short(i16::MIN), Value::Short(-1), Type::Short),
+            (Value::Int(i32::MIN), Value::Int(-1), Type::Int),
+            (Value::Large(i64::MIN), Value::Large(-1), Type::Large),
+        ];
+        for (min, minus_one, t) in cases {
+            assert_eq!(
+                binary(&TokenType::Div, &min, &minus_one),
+                Err(ArithmeticError::Overflow(t))
+            );
+            assert_eq!(
+                binary(&TokenType::Mod, &min, &minus_one),
+                Ok(zero_like(&min))
+            );
+        }
+    }
+
+    #[test]
+    fn shift_amounts() {
+        let shift = |op, lhs, rhs| binary(&op, &lhs, &rhs);
+        assert_eq!(
+            shift(TokenType::ShiftLeft, Value::Short(1), Value::Int(14)),
+            Ok(Value::Short(1 << 14))
+        );
+        assert_eq!(
+            shift(TokenType::ShiftLeft, Value::Short(1), Value::Int(15)),
+            Err(ArithmeticError::Overflow(Type::Short))
+        );
+        assert_eq!(
+            shift(TokenType::ShiftLeft, Value::Short(1), Value::Int(16)),
+            Err(ArithmeticError::ShiftAmount(Type::Short))
+        );
+        assert_eq!(
+            shift(TokenType::ShiftRight, Value::Int(-8), Value::Int(31)),
+            Ok(Value::Int(-1))
+        );
+        assert_eq!(
+            shift(TokenType::ShiftRight, Value::Int(8), Value::Int(32)),
+            Err(ArithmeticError::ShiftAmount(Type::Int))
+        );
+        assert_eq!(
+            shift(TokenType::ShiftRight, Value::Large(8), Value::Int(-1)),
+            Err(ArithmeticError::ShiftAmount(Type::Large))
+        );
+        // The type of the shifted value is kept, whatever the amount's
+        assert_eq!(
+            shift(TokenType::ShiftRight, Value::Short(8), Value::Large(3)),
+            Ok(Value::Short(1))
+        );
+        assert_eq!(
+            shift(TokenType::ShiftLeft, Value::Float(1.0), Value::Int(1)),
+            Err(ArithmeticError::Operands)
+        );
+    }
+
+    #[test]
+    fn wrapping_and_saturating() {
+        assert_eq!(
+            wrapping(&TokenType::Add, &Value::Short(i16::MAX), &Value::Short(1)),
+            Ok(Value::Short(i16::MIN))
+        );
+        assert_eq!(
+            wrapping(&TokenType::Mul, &Value::Int(i32::MAX), &Value::Int(2)),
+            Ok(Value::Int(-2))
+        );
+        assert_eq!(
+            wrapping(&TokenType::Sub, &Value::Large(i64::MIN), &Value::Large(1)),
+            Ok(Value::Large(i64::MAX))
+        );
+        assert_eq!(
+            saturating(&TokenType::Add, &Value::Short(i16::MAX), &Value::Short(1)),
+            Ok(Value::Short(i16::MAX))
+        );
+        assert_eq!(
+            saturating(&TokenType::Mul, &Value::Int(i32::MIN), &Value::Int(2)),
+            Ok(Value::Int(i32::MIN))
+        );
+        assert_eq!(
+            saturating(&TokenType::Sub, &Value::Large(i64::MIN), &Value::Large(1)),
+            Ok(Value::Large(i64::MIN))
+        );
+        // Results that fit are the same as checked arithmetic's
+        assert_eq!(
+            saturating(&TokenType::Add, &Value::Short(1), &Value::Int(2)),
+            Ok(Value::Int(3))
+        );
+        assert_eq!(
+            wrapping(&TokenType::Add, &Value::Float(1.0), &Value::Int(2)),
+            Err(ArithmeticError::Operands)
+        );
+    }
+
+    #[test]
+    fn promotion() {
+        let add = |lhs, rhs| binary(&TokenType::Add, &lhs, &rhs);
+        assert_eq!(add(Value::Short(1), Value::Large(2)), Ok(Value::Large(3)));
+        assert_eq!(add(Value::Int(1), Value::Float(2.5)), Ok(Value::Float(3.5)));
+        assert_eq!(add(Value::Large(1), big(2)), Ok(big(3)));
+        assert_eq!(add(big(1), Value::Float(0.5)), Ok(Value::Float(1.5)));
+        assert_eq!(add(Value::Int(1), dec("0.25")), Ok(dec("1.25")));
+        assert_eq!(add(big(1), dec("0.5")), Ok(dec("1.5")));
+        // A decimal is exact and a float is not, so they do not mix
+        assert_eq!(
+            add(dec("1.5"), Value::Float(1.0)),
+            Err(ArithmeticError::Operands)
+        );
+        assert_eq!(
+            ordering(&ConditionType::Less, &dec("1.5"), &Value::Float(2.0)),
+            Err(ArithmeticError::Operands)
+        );
+        assert_eq!(
+            ordering(&ConditionType::Less, &Value::Short(1), &Value::Float(1.5)),
+            Ok(true)
+        );
+        assert_eq!(
+            binary(&TokenType::Div, &Value::Int(7), &Value::Int(2)),
+            Ok(Value::Int(3))
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(convert(&Value::Float(2.9), &Type::Int), Ok(Value::Int(2)));
+        assert_eq!(convert(&dec("-2.9"), &Type::Short), Ok(Value::Short(-2)));
+        assert_eq!(
+            convert(&Value::Int(40_000), &Type::Short),
+            Err(ArithmeticError::Overflow(Type::Short))
+        );
+        assert_eq!(
+            convert(&Value::Float(f64::NAN), &Type::Large),
+            Err(ArithmeticError::Overflow(Type::Large))
+        );
+        assert_eq!(convert(&Value::Float(0.1), &Type::Decimal), Ok(dec("0.1")));
+    }
+}
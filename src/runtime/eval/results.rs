@@ -0,0 +1,109 @@
+//! Result values, `ok(value)` and `err(value)`, and the `?` that unwraps
+//! them.
+//!
+//! `?` on an `err` has to leave the function it is in from the middle of an
+//! expression. It stores the result in `Environment::propagating` and
+//! unwinds like an error, which the call the function was in turns back
+//! into the return value.
+
+use super::super::environment::Environment;
+use super::super::value::Value;
+use super::errors::DEFAULT_KIND;
+use super::expr::eval_expr;
+use crate::error::{ErrorType::RuntimeError, VeloError};
+use crate::syntax::ast::{Expression, ExpressionKind};
+use crate::syntax::span::Span;
+
+pub fn ok(value: Value) -> Value {
+    Value::Result {
+        ok: true,
+        value: Box::new(value),
+    }
+}
+
+pub fn err(value: Value) -> Value {
+    Value::Result {
+        ok: false,
+        value: Box::new(value),
+    }
+}
+
+/// `err(error)` with an error value of `kind`, for built-in functions that
+/// fail
+pub fn err_kind(kind: &str, message: String) -> Value {
+    err(Value::Error {
+        kind: kind.to_string(),
+        message,
+    })
+}
+
+/// `value?`
+pub fn evaluate_propagate(expr: &Expression, env: &mut Environment) -> Result<Value, VeloError> {
+    let value = match &expr.kind {
+        ExpressionKind::Propagate(value) => value,
+        _ => unreachable!(),
+    };
+
+    let result = eval_expr(value, env)?;
+    match result {
+        Value::Result { ok: true, value } => Ok(*value),
+        Value::Result { ok: false, .. } => {
+            env.propagating = Some(result);
+            // Never reported, the call that is being returned from stops it
+            Err(VeloError::error(
+                expr.span,
+                "`?` returned from the function",
+                RuntimeError,
+            ))
+        }
+        _ => Err(env.throw_error(
+            expr.span,
+            format!(
+                "`?` expects a result, found a value of type {}",
+                result.type_name()
+            ),
+        )),
+    }
+}
+
+/// `is_ok()`, `is_err()`, `unwrap()` and `unwrap_or(default)`
+pub fn result_method(
+    result: Value,
+    name: &str,
+    args: Vec<Value>,
+    span: Span,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let expected = if name == "unwrap_or" { 1 } else { 0 };
+    if args.len() != expected {
+        return Err(env.throw_error(
+            span,
+            format!(
+                "`{}` takes {} argument(s) but {} were given",
+                name,
+                expected,
+                args.len()
+            ),
+        ));
+    }
+
+    let display = result.to_string();
+    let (ok, value) = match result {
+        Value::Result { ok, value } => (ok, *value),
+        _ => unreachable!(),
+    };
+    match name {
+        "is_ok" => Ok(Value::Bool(ok)),
+        "is_err" => Ok(Value::Bool(!ok)),
+        "unwrap" if ok => Ok(value),
+        // Unwrapping an error throws it
+        "unwrap" => match value {
+            Value::Error { kind, message } => Err(env.raise(span, &kind, message)),
+            Value::String(message) => Err(env.raise(span, DEFAULT_KIND, message)),
+            _ => Err(env.throw_error(span, format!("Called `unwrap` on {}", display))),
+        },
+        "unwrap_or" if ok => Ok(value),
+        "unwrap_or" => Ok(args.into_iter().next().unwrap()),
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,265 @@
+//! Ranges, and the things that take them: indexing, slicing and `for`
+//! loops over ranges, arrays, strings and maps.
+
+use super::super::environment::Environment;
+use super::super::value::Value;
+use super::expr::eval_expr;
+use super::numbers::{as_i64, integer_literal};
+use crate::error::VeloError;
+use crate::syntax::ast::{Expression, ExpressionKind};
+use crate::syntax::span::Span;
+
+/// Evaluates the bounds of a range, which must be integers
+pub fn evaluate_range(expr: &Expression, env: &mut Environment) -> Result<Value, VeloError> {
+    let (start, end, inclusive, step) = match &expr.kind {
+        ExpressionKind::Range {
+            start,
+            end,
+            inclusive,
+            step,
+        } => (start, end, *inclusive, step),
+        _ => unreachable!(),
+    };
+
+    let mut bound = |bound: &Option<Box<Expression>>| -> Result<_, VeloError> {
+        match bound {
+            Some(bound) => {
+                let value = eval_expr(bound, env)?;
+                match as_i64(&value) {
+                    Some(value) => Ok(Some(value)),
+                    None => Err(env.throw_error(
+                        bound.span,
+                        format!("Range bounds must be integers, found {}", value),
+                    )),
+                }
+            }
+            None => Ok(None),
+        }
+    };
+
+    Ok(Value::Range {
+        start: bound(start)?,
+        end: bound(end)?,
+        inclusive,
+        step: bound(step)?,
+    })
+}
+
+/// `range.step(n)`, a copy of the range that counts up in steps of `n`
+pub fn range_step(
+    range: Value,
+    args: Vec<(Value, Span)>,
+    span: Span,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let step = match args.as_slice() {
+        [(arg, arg_span)] => match as_i64(arg) {
+            Some(step) if step >= 1 => step,
+            _ => {
+                return Err(env.throw_error(
+                    *arg_span,
+                    format!("A range step must be an integer above 0, found {}", arg),
+                ))
+            }
+        },
+        _ => {
+            return Err(env.throw_error(
+                span,
+                format!("`step` takes 1 argument but {} were given", args.len()),
+            ))
+        }
+    };
+
+    match range {
+        Value::Range {
+            start,
+            end,
+            inclusive,
+            ..
+        } => Ok(Value::Range {
+            start,
+            end,
+            inclusive,
+            step: Some(step),
+        }),
+        _ => unreachable!(),
+    }
+}
+
+/// `target[index]`. A number picks out one element, a range takes a slice
+/// and a string looks up a key of a map.
+pub fn evaluate_index(expr: &Expression, env: &mut Environment) -> Result<Value, VeloError> {
+    let (target, index, optional) = match &expr.kind {
+        ExpressionKind::Index {
+            target,
+            index,
+            optional,
+        } => (target, index, *optional),
+        _ => unreachable!(),
+    };
+    let index_span = index.span;
+    let target = eval_expr(target, env)?;
+    if target == Value::Null {
+        if optional {
+            return Ok(target);
+        }
+        return Err(env.throw_error(
+            expr.span,
+            "Cannot index into null, use `?.[` to allow a null value".to_string(),
+        ));
+    }
+    let index = eval_expr(index, env)?;
+
+    let len = match &target {
+        Value::String(str) => str.chars().count(),
+        Value::Array(items) => items.len(),
+        Value::Map(entries) => {
+            return match &index {
+                Value::String(key) => match entries.get(key) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(env.raise(
+                        index_span,
+                        "KeyError",
+                        format!("Key {:?} is not in this map", key),
+                    )),
+                },
+                _ => Err(env.throw_error(
+                    index_span,
+                    format!(
+                        "Cannot index a map with a value of type {}, expected a string",
+                        index.type_name()
+                    ),
+                )),
+            }
+        }
+        _ => {
+            return Err(env.throw_error(
+                expr.span,
+                format!("Cannot index into a value of type {}", target.type_name()),
+            ))
+        }
+    };
+
+    match &index {
+        Value::Short(_) | Value::Int(_) | Value::Large(_) | Value::BigInt(_) => {
+            let i = match as_i64(&index).map(usize::try_from) {
+                Some(Ok(i)) if i < len => i,
+                _ => {
+                    return Err(env.raise(
+                        index_span,
+                        "IndexError",
+                        format!(
+                            "Index {} is out of bounds for this {} of length {}",
+                            index,
+                            target.type_name(),
+                            len
+                        ),
+                    ))
+                }
+            };
+            Ok(match target {
+                Value::String(str) => Value::String(str.chars().nth(i).unwrap().to_string()),
+                Value::Array(mut items) => items.swap_remove(i),
+                _ => unreachable!(),
+            })
+        }
+        Value::Range { .. } => {
+            let indices = match slice_indices(&index, len) {
+                Some(indices) => indices,
+                None => {
+                    return Err(env.raise(
+                        index_span,
+                        "IndexError",
+                        format!(
+                            "Slice {} is out of bounds for this {} of length {}",
+                            index,
+                            target.type_name(),
+                            len
+                        ),
+                    ))
+                }
+            };
+            Ok(match target {
+                Value::String(str) => {
+                    let chars: Vec<char> = str.chars().collect();
+                    Value::String(indices.map(|i| chars[i]).collect())
+                }
+                Value::Array(items) => Value::Array(indices.map(|i| items[i].clone()).collect()),
+                _ => unreachable!(),
+            })
+        }
+        _ => Err(env.throw_error(
+            index_span,
+            format!(
+                "Cannot index with a value of type {}, expected an integer or a range",
+                index.type_name()
+            ),
+        )),
+    }
+}
+
+/// The positions an evaluated range selects from a sequence of `len`
+/// elements, or `None` when it reaches outside of it
+fn slice_indices(range: &Value, len: usize) -> Option<impl Iterator<Item = usize>> {
+    let (start, end, step) = bounds(range);
+    let start = start.unwrap_or(0);
+    let end = end.unwrap_or(len as i64);
+    if start < 0 || start > end || end > len as i64 {
+        return None;
+    }
+
+    Some((start as usize..end as usize).step_by(step))
+}
+
+/// The values a `for` loop takes from `iterable`, one at a time. A map
+/// gives its keys.
+pub fn iterate(
+    iterable: Value,
+    span: Span,
+    env: &mut Environment,
+) -> Result<Box<dyn Iterator<Item = Value>>, VeloError> {
+    match iterable {
+        Value::Range { .. } => match bounds(&iterable) {
+            (Some(start), Some(end), step) => {
+                Ok(Box::new((start..end).step_by(step).map(integer_literal)))
+            }
+            (None, _, _) => Err(env.throw_error(
+                span,
+                format!("Cannot loop over {}, it has no start", iterable),
+            )),
+            (_, None, _) => Err(env.throw_error(
+                span,
+                format!("Cannot loop over {}, it has no end", iterable),
+            )),
+        },
+        Value::Array(items) => Ok(Box::new(items.into_iter())),
+        Value::String(str) => {
+            let chars: Vec<char> = str.chars().collect();
+            Ok(Box::new(
+                chars.into_iter().map(|c| Value::String(c.to_string())),
+            ))
+        }
+        Value::Map(entries) => Ok(Box::new(entries.into_keys().map(Value::String))),
+        _ => Err(env.throw_error(
+            span,
+            format!("Cannot loop over a value of type {}", iterable.type_name()),
+        )),
+    }
+}
+
+/// The start, exclusive end and step of an evaluated range
+fn bounds(range: &Value) -> (Option<i64>, Option<i64>, usize) {
+    match range {
+        Value::Range {
+            start,
+            end,
+            inclusive,
+            step,
+        } => {
+            let end = end.map(|end| if *inclusive { end + 1 } else { end });
+            let step = step.map_or(1, |step| step as usize);
+            (*start, end, step)
+        }
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,129 @@
+//! Building struct values and reading their fields
+
+use super::super::environment::Environment;
+use super::super::value::Value;
+use super::interfaces::check_implements;
+use super::types::{accepts_null, bind_type_params, check_type_exists, Bindings};
+use crate::error::VeloError;
+use crate::syntax::ast::{NamedArg, Statement, StatementKind};
+use crate::syntax::lexer::Type;
+use crate::syntax::printer::type_name;
+use crate::syntax::span::Span;
+
+/// `Name(values)`, which takes a value for every field, by position or by
+/// name, and infers the struct's type arguments from them
+pub fn construct_struct(
+    declaration: &Statement,
+    call_span: Span,
+    args: Vec<(Value, Span)>,
+    named_args: Vec<(&NamedArg, Value)>,
+    env: &mut Environment,
+) -> Result<Value, VeloError> {
+    let (name, type_params, fields) = match &declaration.kind {
+        StatementKind::Struct {
+            name,
+            type_params,
+            fields,
+        } => (name, type_params, fields),
+        _ => unreachable!(),
+    };
+    if args.len() > fields.len() {
+        return Err(env.throw_error(
+            call_span,
+            format!(
+                "Struct `{}` has {} field(s) but {} values were given",
+                name,
+                fields.len(),
+                args.len()
+            ),
+        ));
+    }
+
+    // Each field's value and the span of the argument that gave it
+    let mut slots: Vec<Option<(Value, Span)>> = vec![None; fields.len()];
+    for (i, arg) in args.into_iter().enumerate() {
+        slots[i] = Some(arg);
+    }
+    for (arg, value) in named_args {
+        match fields.iter().position(|(field, _)| *field == arg.name) {
+            Some(i) if slots[i].is_some() => {
+                return Err(env.throw_error(
+                    arg.span,
+                    format!(
+                        "Field `{}` of struct `{}` is given both by position and by name",
+                        arg.name, name
+                    ),
+                ))
+            }
+            Some(i) => slots[i] = Some((value, arg.span)),
+            None => {
+                return Err(env.throw_error(
+                    arg.span,
+                    format!("Struct `{}` has no field named `{}`", name, arg.name),
+                ))
+            }
+        }
+    }
+
+    let owner = format!("struct `{}`", name);
+    let mut bindings = Bindings::new();
+    let mut values = Vec::new();
+    for ((field, field_type), slot) in fields.iter().zip(slots) {
+        let (value, span) = match slot {
+            Some(slot) => slot,
+            None => {
+                return Err(env.throw_error(
+                    call_span,
+                    format!("Missing value for field `{}` of struct `{}`", field, name),
+                ))
+            }
+        };
+
+        if value == Value::Null && !accepts_null(field_type) {
+            return Err(env.throw_error(
+                span,
+                format!(
+                    "Struct `{}` expects `{}` for field `{}`, found null",
+                    name,
+                    type_name(field_type),
+                    field
+                ),
+            ));
+        }
+        let what = format!("field `{}` of struct `{}`", field, name);
+        check_type_exists(field_type, &what, call_span, env)?;
+        check_implements(field_type, &value, &what, span, env)?;
+        bind_type_params(&owner, field_type, &value, span, &mut bindings, env)?;
+        values.push((field.clone(), value));
+    }
+
+    let type_args = type_params
+        .iter()
+        .map(|param| bindings.get(param).cloned().unwrap_or(Type::Any))
+        .collect();
+    Ok(Value::Struct {
+        name: name.clone(),
+        type_args,
+        fields: values,
+    })
+}
+
+/// The value of field `name`, if `value` is a struct that has one or an
+/// error, which has a `kind` and a `message`
+pub fn field_value(value: &Value, name: &str) -> Option<Value> {
+    match value {
+        Value::Error { kind, message } => {
+            let field = match name {
+                "kind" => kind,
+                "message" => message,
+                _ => return None,
+            };
+            Some(Value::String(field.clone()))
+        }
+        Value::Struct { fields, .. } => fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone()),
+        _ => None,
+    }
+}
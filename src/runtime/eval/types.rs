@@ -0,0 +1,237 @@
+//! The types of runtime values, and inference of type parameters from them.
+//!
+//! Inference only looks at what it can see in the values. A value whose
+//! type cannot be told, like null or an empty array, has type `any`, which
+//! matches everything.
+
+use std::collections::HashMap;
+
+use super::super::environment::Environment;
+use super::super::value::Value;
+use super::numbers::{convert, fits_type, is_number, is_number_type};
+use crate::error::VeloError;
+use crate::syntax::lexer::Type;
+use crate::syntax::printer::type_name;
+use crate::syntax::span::Span;
+
+/// What each type parameter has been bound to so far
+pub type Bindings = HashMap<String, Type>;
+
+/// The type of a value, as far as it can be told from the value alone
+pub fn value_type(value: &Value) -> Type {
+    match value {
+        Value::Short(_) => Type::Short,
+        Value::Int(_) => Type::Int,
+        Value::Large(_) => Type::Large,
+        Value::BigInt(_) => Type::BigInt,
+        Value::Float(_) => Type::Float,
+        Value::Decimal(_) => Type::Decimal,
+        Value::Bool(_) => Type::Bool,
+        Value::String(_) => Type::String,
+        Value::Error { .. } => Type::Error,
+        Value::Result { .. } => Type::Result,
+        Value::Array(items) => {
+            let mut element = Type::Any;
+            for item in items {
+                match unify(&element, &value_type(item)) {
+                    Some(t) => element = t,
+                    None => return Type::Array(Box::new(Type::Any)),
+                }
+            }
+            Type::Array(Box::new(element))
+        }
+        Value::Struct {
+            name, type_args, ..
+        } => Type::Named {
+            name: name.clone(),
+            args: type_args.clone(),
+        },
+        _ => Type::Any,
+    }
+}
+
+/// The most specific type that both `a` and `b` fit, if there is one
+pub fn unify(a: &Type, b: &Type) -> Option<Type> {
+    match (a, b) {
+        (Type::Any, t) | (t, Type::Any) => Some(t.clone()),
+        (Type::Array(a), Type::Array(b)) => Some(Type::Array(Box::new(unify(a, b)?))),
+        (Type::Optional(a), Type::Optional(b)) => Some(Type::Optional(Box::new(unify(a, b)?))),
+        (
+            Type::Named { name, args },
+            Type::Named {
+                name: other,
+                args: other_args,
+            },
+        ) if name == other && args.len() == other_args.len() => {
+            let mut unified = Vec::new();
+            for (a, b) in args.iter().zip(other_args) {
+                unified.push(unify(a, b)?);
+            }
+            Some(Type::Named {
+                name: name.clone(),
+                args: unified,
+            })
+        }
+        (a, b) if a == b => Some(a.clone()),
+        _ => None,
+    }
+}
+
+/// Matches a declared type against the type of the value given for it and
+/// binds the type parameters it mentions. On a conflict, returns the
+/// parameter and the two types it would have to be.
+pub fn infer(
+    pattern: &Type,
+    actual: &Type,
+    bindings: &mut Bindings,
+) -> Result<(), (String, Type, Type)> {
+    match (pattern, actual) {
+        (_, Type::Any) => Ok(()),
+        (Type::Param(name), actual) => {
+            let bound = bindings.get(name).cloned().unwrap_or(Type::Any);
+            match unify(&bound, actual) {
+                Some(t) => {
+                    bindings.insert(name.clone(), t);
+                    Ok(())
+                }
+                None => Err((name.clone(), bound, actual.clone())),
+            }
+        }
+        (Type::Array(pattern), Type::Array(actual)) => infer(pattern, actual, bindings),
+        (Type::Optional(pattern), actual) => infer(pattern, actual, bindings),
+        (
+            Type::Named { name, args },
+            Type::Named {
+                name: other,
+                args: actual_args,
+            },
+        ) if name == other && args.len() == actual_args.len() => {
+            for (pattern, actual) in args.iter().zip(actual_args) {
+                infer(pattern, actual, bindings)?;
+            }
+            Ok(())
+        }
+        // Anything else is a plain type mismatch, not an inference failure
+        _ => Ok(()),
+    }
+}
+
+/// `t` with each type parameter replaced by what it is bound to. Unbound
+/// parameters become `any`.
+pub fn substitute(t: &Type, bindings: &Bindings) -> Type {
+    match t {
+        Type::Param(name) => bindings.get(name).cloned().unwrap_or(Type::Any),
+        Type::Array(element) => Type::Array(Box::new(substitute(element, bindings))),
+        Type::Optional(inner) => Type::Optional(Box::new(substitute(inner, bindings))),
+        Type::Named { name, args } => Type::Named {
+            name: name.clone(),
+            args: args.iter().map(|arg| substitute(arg, bindings)).collect(),
+        },
+        _ => t.clone(),
+    }
+}
+
+pub fn mentions_params(t: &Type) -> bool {
+    match t {
+        Type::Param(_) => true,
+        Type::Array(inner) | Type::Optional(inner) => mentions_params(inner),
+        Type::Named { args, .. } => args.iter().any(mentions_params),
+        _ => false,
+    }
+}
+
+/// The struct and interface names that `t` refers to
+pub fn named_types(t: &Type) -> Vec<&str> {
+    match t {
+        Type::Array(inner) | Type::Optional(inner) => named_types(inner),
+        Type::Named { name, args } => {
+            let mut names = vec![name.as_str()];
+            names.extend(args.iter().flat_map(named_types));
+            names
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Whether `value` can be given where `declared` is expected. Numbers
+/// widen, and an integer can be given for an integer type that it is in
+/// range of. Null, type parameters and interfaces are checked apart.
+pub fn fits(value: &Value, declared: &Type, env: &Environment) -> bool {
+    match (declared, value) {
+        (Type::Any | Type::Param(_) | Type::Tuple(_), _) | (_, Value::Null) => true,
+        (Type::Optional(inner), _) => fits(value, inner, env),
+        (Type::Void, _) => false,
+        (Type::Array(element), Value::Array(items)) => {
+            items.iter().all(|item| fits(item, element, env))
+        }
+        (Type::Named { name, .. }, Value::Struct { name: found, .. }) => {
+            name == found || env.find_interface(name).is_some()
+        }
+        (Type::Named { name, .. }, _) => env.find_interface(name).is_some(),
+        (_, _) if is_number_type(declared) && is_number(value) => fits_type(value, declared),
+        (declared, _) => matches!(value_type(value), Type::Any) || *declared == value_type(value),
+    }
+}
+
+/// A value that `fits` a declared type, with its numbers made that type
+pub fn coerce(value: Value, declared: &Type) -> Value {
+    match (declared, value) {
+        (Type::Optional(inner), value) => coerce(value, inner),
+        (Type::Array(element), Value::Array(items)) => Value::Array(
+            items
+                .into_iter()
+                .map(|item| coerce(item, element))
+                .collect(),
+        ),
+        (declared, value) if is_number_type(declared) && is_number(&value) => {
+            convert(&value, declared).unwrap_or(value)
+        }
+        (_, value) => value,
+    }
+}
+
+/// Declared types exclude null unless they are written `T?`
+pub fn accepts_null(t: &Type) -> bool {
+    matches!(t, Type::Optional(_) | Type::Void | Type::Any)
+}
+
+/// Infers the type parameters of `owner`, e.g. "function `first`", from a
+/// value given for `declared`
+pub fn bind_type_params(
+    owner: &str,
+    declared: &Type,
+    value: &Value,
+    span: Span,
+    bindings: &mut Bindings,
+    env: &mut Environment,
+) -> Result<(), VeloError> {
+    infer(declared, &value_type(value), bindings).map_err(|(param, bound, found)| {
+        env.throw_error(
+            span,
+            format!(
+                "Type parameter `{}` of {} cannot be both `{}` and `{}`",
+                param,
+                owner,
+                type_name(&bound),
+                type_name(&found)
+            ),
+        )
+    })
+}
+
+/// Checks that every struct or interface `declared` refers to exists. `what` says where
+/// the type was written, e.g. "parameter `b` of function `f`".
+pub fn check_type_exists(
+    declared: &Type,
+    what: &str,
+    span: Span,
+    env: &mut Environment,
+) -> Result<(), VeloError> {
+    for name in named_types(declared) {
+        if !env.type_exists(name) {
+            return Err(env.throw_error(span, format!("Cannot find type `{}` of {}", name, what)));
+        }
+    }
+
+    Ok(())
+}
@@ -1,93 +1,306 @@
+use super::environment::Environment;
+use super::eval::errors::{error_value, throw_value};
 use super::eval::expr::*;
-use super::{environment::Environment, eval};
+use super::eval::interfaces::declare_impl;
+use super::eval::sequence::iterate;
+use super::value::{Closure, Value};
 use crate::{
-    syntax::ast::{Ast, Expression, Statement},
-    syntax::{lexer::Lexer, parse::Parser},
-    utils::{expand_tilde, interpolate_string},
+    error::{ErrorType::RuntimeError, VeloError},
+    passes::analyze,
+    syntax::ast::{Ast, CatchClause, Expression, Statement, StatementKind},
+    syntax::parse::parse_source,
 };
+use std::rc::Rc;
 
-pub fn evaluate(nodes: Vec<Ast>, debug: bool, env: &mut Environment) {
+/// How control leaves a block
+pub enum Flow {
+    /// Ran to the end, with the value of the trailing expression, if any
+    Next(Option<Value>),
+    Return(Value),
+}
+
+/// Runs a program. Returns the value of the last node if it is an
+/// expression, which the REPL echoes.
+pub fn evaluate(
+    nodes: Vec<Ast>,
+    debug: bool,
+    env: &mut Environment,
+) -> Result<Option<Value>, VeloError> {
+    let flow = execute_block(&nodes, env)?;
+
+    if debug {
+        println!("\n{:#?}", env);
+    }
+
+    match flow {
+        Flow::Next(value) => Ok(value),
+        Flow::Return(value) => Ok(Some(value)),
+    }
+}
+
+/// Calls a top level function that takes no arguments, the way `@test`
+/// functions are run
+pub fn run_function(function: &Rc<Statement>, env: &mut Environment) -> Result<Value, VeloError> {
+    let closure = Closure {
+        function: function.clone(),
+        scope: env.globals.clone(),
+    };
+    call_function(&closure, function.span, Vec::new(), Vec::new(), None, env)
+}
+
+/// Runs a block in a scope of its own
+pub fn execute_scoped(nodes: &[Ast], env: &mut Environment) -> Result<Flow, VeloError> {
+    env.scoped(|env| execute_block(nodes, env))
+}
+
+pub fn execute_block(nodes: &[Ast], env: &mut Environment) -> Result<Flow, VeloError> {
+    let mut last = None;
     for node in nodes {
         match node {
-            Ast::Expression(expr) => match expr {
-                Expression::CallExpr {
-                    name: _,
-                    params: _,
-                    /*
-                    ref name,
-                    ref params,
-                    */
-                } => eval_call_expr(&expr, env, None),
-                Expression::Null => {}
-                _ => unimplemented!(), // sticking out your gyat
+            Ast::Expression(expr) => last = Some(eval_expr(expr, env)?),
+            Ast::Statement(stmt) => match execute_statement(stmt, env)? {
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+                // An `if` statement passes on the value of its block
+                Flow::Next(value) => last = value,
             },
-            Ast::Statement(stmt) => match stmt {
-                Statement::VariableAssignment {
-                    constant,
-                    ref name,
-                    ref value,
-                } => match value {
-                    Expression::CallExpr { name: _, params: _ } => {
-                        eval_call_expr(&value, env, Some(&stmt))
-                    }
-                    #[allow(unused)]
-                    Expression::BinaryOp { lhs, op, rhs } => {
-                        let eval = evaluate_binary(&value, env);
-                        env.declare_variable(
-                            name.to_string(),
-                            Expression::Float(eval.clone()),
-                            constant,
-                        );
-                    }
-                    #[allow(unused)]
-                    Expression::Conditional { lhs, op, rhs } => {
-                        let eval = evaluate_conditional(&value, env);
-                        env.declare_variable(name.to_string(), eval.clone(), constant);
-                    }
-                    _ => {
-                        let v = match value {
-                            Expression::StringLiteral(str) => {
-                                let parsed = interpolate_string(str, &env);
-                                Expression::StringLiteral(parsed)
-                            }
-                            _ => value.clone(),
-                        };
-                        env.declare_variable(name.to_string(), v.clone(), constant);
-                    }
-                },
-                Statement::IfStatement { condition, body } => {
-                    let eval = evaluate_conditional(&condition, env);
-                    println!("{:#?}\n{:#?}", eval, body);
-                    match eval {
-                        Expression::Bool(val) => {
-                            if val {
-                                evaluate(body, debug, env);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                Statement::Import(path) => {
-                    println!("Importing file: {}", path);
-                    let full_path = expand_tilde(&path);
+        }
+    }
+
+    Ok(Flow::Next(last))
+}
+
+/// Runs one statement. Statements that need many locals, like `try`, run in
+/// functions of their own kept out of line, since every call in a program
+/// has a frame of this function on the stack.
+fn execute_statement(stmt: &Statement, env: &mut Environment) -> Result<Flow, VeloError> {
+    match &stmt.kind {
+        StatementKind::VariableAssignment {
+            constant,
+            name,
+            value,
+        } => {
+            let value = eval_expr(value, env)?;
+            env.declare_variable(name.to_string(), value, *constant, stmt.span)?;
+            if !stmt.annotations.is_empty() {
+                env.variable_annotations
+                    .insert(name.clone(), stmt.annotations.clone());
+            }
+        }
+        StatementKind::IfStatement {
+            condition,
+            body,
+            else_body,
+        } => {
+            if eval_condition(condition, env)? {
+                return execute_scoped(body, env);
+            } else if let Some(else_body) = else_body {
+                return execute_scoped(else_body, env);
+            }
+        }
+        StatementKind::For {
+            name,
+            iterable,
+            body,
+        } => return execute_for(name, iterable, body, env),
+        StatementKind::Try {
+            body,
+            catch,
+            finally,
+        } => return execute_try(body, catch.as_ref(), finally.as_deref(), env),
+        StatementKind::Throw(value) => {
+            let value = eval_expr(value, env)?;
+            return Err(throw_value(value, stmt.span, env));
+        }
+        StatementKind::Import(path) => execute_import(stmt, path, env)?,
+        StatementKind::Function { .. } => env.declare_function(stmt),
+        StatementKind::Struct { .. } => env.structs.push(stmt.clone()),
+        StatementKind::Interface { .. } => env.interfaces.push(stmt.clone()),
+        StatementKind::Impl { .. } => declare_impl(stmt, env)?,
+        StatementKind::Return(value) => {
+            let value = match value {
+                Some(value) => eval_expr(value, env)?,
+                None => Value::Null,
+            };
+            return Ok(Flow::Return(value));
+        }
+        StatementKind::ExprStmt(expr) => {
+            eval_expr(expr, env)?;
+        }
+    }
+
+    Ok(Flow::Next(None))
+}
 
-                    let contents = std::fs::read_to_string(&full_path).unwrap();
-                    let mut lexer = Lexer::new(&contents);
-                    let tokens = lexer.tokenize().tokens;
+/// `for name in iterable { body }`
+#[inline(never)]
+fn execute_for(
+    name: &str,
+    iterable: &Expression,
+    body: &[Ast],
+    env: &mut Environment,
+) -> Result<Flow, VeloError> {
+    let value = eval_expr(iterable, env)?;
+    let items = iterate(value, iterable.span, env)?;
 
-                    let mut parser = Parser::new(tokens);
-                    let _ = parser.parse();
+    // Each pass has a scope of its own, so the body can declare
+    // with `:=` every time round
+    for item in items {
+        let flow = env.scoped(|env| {
+            env.bind(name, item);
+            execute_block(body, env)
+        })?;
+        if let Flow::Return(value) = flow {
+            return Ok(Flow::Return(value));
+        }
+    }
+    Ok(Flow::Next(None))
+}
 
-                    evaluate(parser.nodes, debug, env);
-                    continue;
+/// `try { body } catch name { ... } finally { ... }`
+#[inline(never)]
+fn execute_try(
+    body: &[Ast],
+    catch: Option<&CatchClause>,
+    finally: Option<&[Ast]>,
+    env: &mut Environment,
+) -> Result<Flow, VeloError> {
+    let mut flow = execute_scoped(body, env);
+    if let (Err(error), Some(catch)) = (&flow, catch) {
+        // Parse errors in imported files are already reported, they arrive
+        // here as runtime errors. `?` unwinding is not an error at all.
+        if error.error_type == RuntimeError && env.propagating.is_none() {
+            let error = error.clone();
+            flow = env.scoped(|env| {
+                if let Some(name) = &catch.name {
+                    env.bind(name, error_value(&error));
                 }
-                _ => todo!(),
-            },
+                execute_block(&catch.body, env)
+            });
         }
     }
-    if debug {
-        println!("\n{:#?}", env);
-    } else {
-        println!("\0")
+
+    // A `return` or an error in `finally` replaces however the rest of the
+    // statement ended
+    if let Some(finally) = finally {
+        if let Flow::Return(value) = execute_scoped(finally, env)? {
+            return Ok(Flow::Return(value));
+        }
+    }
+    flow
+}
+
+/// `import path`, which runs the file it names in the current scope
+#[inline(never)]
+fn execute_import(stmt: &Statement, path: &str, env: &mut Environment) -> Result<(), VeloError> {
+    let (full_path, package) = env.resolve_import(path);
+
+    let contents = match std::fs::read_to_string(&full_path) {
+        Ok(contents) => contents,
+        Err(_) => {
+            return Err(env.raise(
+                stmt.span,
+                "IOError",
+                format!(
+                    "Cannot import '{}', no such file '{}'",
+                    path,
+                    full_path.display()
+                ),
+            ))
+        }
+    };
+    let filename = full_path.display().to_string();
+    let nodes = match parse_source(&contents) {
+        Ok(nodes) => nodes,
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}\n", error.render(Some(&contents), &filename));
+            }
+            return Err(env.throw_error(
+                stmt.span,
+                format!("Failed to parse imported file '{}'", filename),
+            ));
+        }
+    };
+    let errors = analyze(&nodes).errors;
+    if !errors.is_empty() {
+        for error in errors {
+            eprintln!("{}\n", error.render(Some(&contents), &filename));
+        }
+        return Err(env.throw_error(
+            stmt.span,
+            format!("Imported file '{}' has errors", filename),
+        ));
+    }
+
+    let previous = env.current_package;
+    env.current_package = package;
+    // Errors inside the imported file refer to its own source, so
+    // they are reported here rather than by the caller
+    if let Err(error) = execute_block(&nodes, env) {
+        env.current_package = previous;
+        eprintln!("{}\n", error.render(Some(&contents), &filename));
+        return Err(VeloError::error(
+            stmt.span,
+            &format!("Error while running imported file '{}'", filename),
+            RuntimeError,
+        ));
+    }
+    env.current_package = previous;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+    use crate::runtime::environment::{Environment, LibFunction};
+    use crate::runtime::value::Value;
+    use crate::syntax::parse::parse_source;
+
+    const COUNT_DOWN: &str = "fun f(n int) > int { if n == 0 { return 0; } return f(n - 1) + 1; }";
+
+    fn run(source: &str, max_depth: usize) -> Result<Option<Value>, (String, String)> {
+        let nodes = parse_source(source).expect("test source parses");
+        let mut env = Environment::init();
+        env.max_depth = max_depth;
+        evaluate(nodes, false, &mut env).map_err(|error| (error.kind, error.message))
+    }
+
+    #[test]
+    fn calls_go_as_deep_as_allowed() {
+        let source = format!("{} f(49);", COUNT_DOWN);
+        assert_eq!(run(&source, 50), Ok(Some(Value::Int(49))));
+        let source = format!("{} f(50);", COUNT_DOWN);
+        assert_eq!(
+            run(&source, 50),
+            Err((
+                "RecursionError".to_string(),
+                "Cannot call function `f`, 50 calls are already running".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn recursion_errors_can_be_caught() {
+        let source = format!("{} try {{ f(100); }} catch {{}} f(40);", COUNT_DOWN);
+        // The calls that were running when the error was thrown are no
+        // longer counted once it is caught
+        assert_eq!(run(&source, 50), Ok(Some(Value::Int(40))));
+        let source = format!("{} try {{ f(100); }} catch e {{ e.kind; }}", COUNT_DOWN);
+        assert_eq!(
+            run(&source, 50),
+            Ok(Some(Value::String("RecursionError".to_string())))
+        );
+    }
+
+    #[test]
+    fn unknown_built_in_functions_throw() {
+        let nodes = parse_source("missing(1);").expect("test source parses");
+        let mut env = Environment::init();
+        env.lib_functions.push(LibFunction {
+            name: "missing".to_string(),
+            param_len: None,
+        });
+        let error = evaluate(nodes, false, &mut env).unwrap_err();
+        assert_eq!(error.message, "Cannot find built-in function `missing`");
     }
 }
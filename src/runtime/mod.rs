@@ -1,3 +1,6 @@
+pub mod bigint;
+pub mod decimal;
 pub mod environment;
 pub mod eval;
 pub mod interpreter;
+pub mod value;
@@ -0,0 +1,280 @@
+//! The values a running program works with. The AST only describes the
+//! program, evaluating an `Expression` always gives one of these.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use super::bigint::BigInt;
+use super::decimal::Decimal;
+use super::environment::Scope;
+use super::eval::numbers;
+use crate::syntax::ast::{Statement, StatementKind};
+use crate::syntax::lexer::Type;
+use crate::syntax::printer::type_name;
+use crate::utils::format_float;
+
+/// Numbers of different types are equal when their values are, e.g. `1`
+/// and `1.0`
+#[derive(Debug, Clone)]
+pub enum Value {
+    Short(i16),
+    Int(i32),
+    Large(i64),
+    BigInt(BigInt),
+    Float(f64),
+    Decimal(Decimal),
+    String(String),
+    Bool(bool),
+    Null,
+    Array(Vec<Value>),
+    /// String keys to values. There is no literal for a map, host
+    /// applications make them.
+    Map(BTreeMap<String, Value>),
+    /// A declared function, given by using its name as a value
+    Function(Closure),
+    /// A value of a struct type, with the type arguments inferred when it
+    /// was built
+    Struct {
+        name: String,
+        type_args: Vec<Type>,
+        fields: Vec<(String, Value)>,
+    },
+    /// An error, made by `error(message, kind)` or by a failure that
+    /// `catch` caught
+    Error {
+        kind: String,
+        message: String,
+    },
+    /// `ok(value)` or `err(value)`
+    Result {
+        ok: bool,
+        value: Box<Value>,
+    },
+    /// An evaluated range, whose bounds are whole numbers. `step` is only
+    /// set by `range.step(n)`.
+    Range {
+        start: Option<i64>,
+        end: Option<i64>,
+        inclusive: bool,
+        step: Option<i64>,
+    },
+    /// Data that belongs to a host application, which Velo passes around
+    /// without looking inside
+    Native(NativeHandle),
+}
+
+/// A function and the scope it was declared in, which its body sees when
+/// it is called. The scope is shared, not copied, so the function sees the
+/// variables there as they are when it runs.
+#[derive(Clone)]
+pub struct Closure {
+    pub function: Rc<Statement>,
+    pub scope: Rc<RefCell<Scope>>,
+}
+
+impl Closure {
+    pub fn name(&self) -> &str {
+        match &self.function.kind {
+            StatementKind::Function { name, .. } => name,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Only the name, the scope may well hold the function itself
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Closure({})", self.name())
+    }
+}
+
+/// The same function declared by the same run of a block
+impl PartialEq for Closure {
+    fn eq(&self, other: &Closure) -> bool {
+        Rc::ptr_eq(&self.function, &other.function) && Rc::ptr_eq(&self.scope, &other.scope)
+    }
+}
+
+/// A host application's own data, shared rather than copied. Two handles
+/// are equal when they share the same data.
+#[derive(Clone)]
+pub struct NativeHandle {
+    /// What the data is, shown when the value is printed
+    pub name: String,
+    pub data: Rc<dyn Any>,
+}
+
+impl NativeHandle {
+    pub fn new(name: &str, data: impl Any) -> NativeHandle {
+        NativeHandle {
+            name: name.to_string(),
+            data: Rc::new(data),
+        }
+    }
+}
+
+impl fmt::Debug for NativeHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NativeHandle({})", self.name)
+    }
+}
+
+impl PartialEq for NativeHandle {
+    fn eq(&self, other: &NativeHandle) -> bool {
+        Rc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (a, b) if numbers::is_number(a) && numbers::is_number(b) => numbers::equal(a, b),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (
+                Value::Struct {
+                    name: a,
+                    fields: a_fields,
+                    ..
+                },
+                Value::Struct {
+                    name: b,
+                    fields: b_fields,
+                    ..
+                },
+            ) => a == b && a_fields == b_fields,
+            (
+                Value::Error {
+                    kind: a,
+                    message: a_message,
+                },
+                Value::Error {
+                    kind: b,
+                    message: b_message,
+                },
+            ) => a == b && a_message == b_message,
+            (
+                Value::Result {
+                    ok: a,
+                    value: a_value,
+                },
+                Value::Result {
+                    ok: b,
+                    value: b_value,
+                },
+            ) => a == b && a_value == b_value,
+            (
+                Value::Range {
+                    start: a_start,
+                    end: a_end,
+                    inclusive: a_inclusive,
+                    step: a_step,
+                },
+                Value::Range {
+                    start: b_start,
+                    end: b_end,
+                    inclusive: b_inclusive,
+                    step: b_step,
+                },
+            ) => (a_start, a_end, a_inclusive, a_step) == (b_start, b_end, b_inclusive, b_step),
+            (Value::Native(a), Value::Native(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    /// The name of a value's type, for error messages
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Short(_) => type_name(&Type::Short),
+            Value::Int(_) => type_name(&Type::Int),
+            Value::Large(_) => type_name(&Type::Large),
+            Value::BigInt(_) => type_name(&Type::BigInt),
+            Value::Float(_) => type_name(&Type::Float),
+            Value::Decimal(_) => type_name(&Type::Decimal),
+            Value::String(_) => "string".to_string(),
+            Value::Bool(_) => "bool".to_string(),
+            Value::Null => "null".to_string(),
+            Value::Array(_) => "array".to_string(),
+            Value::Map(_) => "map".to_string(),
+            Value::Function(_) => "function".to_string(),
+            Value::Struct { name, .. } => name.clone(),
+            Value::Error { .. } => "error".to_string(),
+            Value::Result { .. } => "result".to_string(),
+            Value::Range { .. } => "range".to_string(),
+            Value::Native(handle) => handle.name.clone(),
+        }
+    }
+
+    /// Strings inside other values are shown quoted
+    fn nested(&self) -> String {
+        match self {
+            Value::String(str) => format!("{:?}", str),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// What `print` shows for a value
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Short(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Large(value) => write!(f, "{}", value),
+            Value::BigInt(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{}", format_float(*value)),
+            Value::Decimal(value) => write!(f, "{}", value),
+            Value::String(str) => write!(f, "{}", str),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Null => write!(f, "null"),
+            Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(Value::nested).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+            Value::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{:?}: {}", key, value.nested()))
+                    .collect();
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+            Value::Function(closure) => write!(f, "<function {}>", closure.name()),
+            Value::Struct { name, fields, .. } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", field, value.nested()))
+                    .collect();
+                write!(f, "{}({})", name, fields.join(", "))
+            }
+            Value::Error { kind, message } => write!(f, "{}: {}", kind, message),
+            Value::Result { ok, value } => {
+                let name = if *ok { "ok" } else { "err" };
+                write!(f, "{}({})", name, value.nested())
+            }
+            Value::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => {
+                let bound = |bound: &Option<i64>| bound.map_or(String::new(), |b| b.to_string());
+                let op = if *inclusive { "..=" } else { ".." };
+                let range = format!("{}{}{}", bound(start), op, bound(end));
+                match step {
+                    Some(_) => write!(f, "({}).step({})", range, bound(step)),
+                    None => write!(f, "{}", range),
+                }
+            }
+            Value::Native(handle) => write!(f, "<{}>", handle.name),
+        }
+    }
+}
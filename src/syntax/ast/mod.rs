@@ -0,0 +1,302 @@
+#![allow(dead_code)]
+#![allow(unused_variables)]
+pub mod visit;
+
+use super::lexer::{TokenType, Type};
+use super::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ast {
+    Expression(Expression),
+    Statement(Statement),
+}
+
+impl Ast {
+    pub fn span(&self) -> Span {
+        match self {
+            Ast::Expression(expr) => expr.span,
+            Ast::Statement(stmt) => stmt.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+    /// Only functions, structs and variable declarations have any
+    pub annotations: Vec<Annotation>,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Statement {
+        Statement {
+            kind,
+            span,
+            annotations: Vec::new(),
+        }
+    }
+
+    pub fn annotation(&self, name: &str) -> Option<&Annotation> {
+        self.annotations.iter().find(|a| a.name == name)
+    }
+}
+
+/// `@name` or `@name(args)` before a declaration. `test`, `deprecated` and
+/// `inline` mean something to Velo itself, any other name is kept for tools
+/// and host applications to read. The arguments are literal values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub name: String,
+    pub args: Vec<Expression>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    VariableAssignment {
+        constant: bool,
+        name: String,
+        value: Expression,
+    },
+    /// `elif` is stored as an `else_body` holding a single nested if
+    IfStatement {
+        condition: Expression,
+        body: Vec<Ast>,
+        else_body: Option<Vec<Ast>>,
+    },
+    Function {
+        name: String,
+        type_params: Vec<String>,
+        params: Vec<Param>,
+        body: FunctionBody,
+        ret_type: Type,
+    },
+    /// `for name in iterable { body }`
+    For {
+        name: String,
+        iterable: Expression,
+        body: Vec<Ast>,
+    },
+    /// `struct Name<T> { field type, ... }`, built by calling it like a
+    /// function with a value for each field
+    Struct {
+        name: String,
+        type_params: Vec<String>,
+        fields: Vec<(String, Type)>,
+    },
+    /// `type Name interface { fun method(self, ...) > type ... }`
+    Interface {
+        name: String,
+        methods: Vec<MethodSignature>,
+    },
+    /// `impl Interface for Struct { methods }`. Each method is a
+    /// `Function` whose `params` leave out `self`.
+    Impl {
+        interface: String,
+        target: String,
+        methods: Vec<Statement>,
+    },
+    /// `try { body } catch name { ... } finally { ... }`, with a `catch`,
+    /// a `finally` or both
+    Try {
+        body: Vec<Ast>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<Ast>>,
+    },
+    /// `throw value;`, where the value is an error or a message string
+    Throw(Expression),
+    Import(String),
+    Return(Option<Expression>),
+    ExprStmt(Expression),
+}
+
+/// `name type`, or `name type = default` for a parameter that may be left
+/// out of a call. A rest parameter, `...name` or `...name type`, comes
+/// last and collects the remaining positional arguments into an array, its
+/// `param_type` is that array's type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub param_type: Type,
+    pub default: Option<Expression>,
+    pub rest: bool,
+}
+
+/// `catch name { body }`, the name binds the caught error and may be left
+/// out
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatchClause {
+    pub name: Option<String>,
+    pub body: Vec<Ast>,
+}
+
+/// A method an interface requires, `params` leaves out `self`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSignature {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub ret_type: Type,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionBody {
+    pub stmts: Vec<Ast>,
+}
+
+impl FunctionBody {
+    pub fn new(stmts: Vec<Ast>) -> FunctionBody {
+        FunctionBody { stmts }
+    }
+
+    pub fn stmts(&self) -> &[Ast] {
+        &self.stmts
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Expression {
+        Expression { kind, span }
+    }
+
+    /// An expression that did not come from source, e.g. a value computed
+    /// at runtime
+    pub fn synthetic(kind: ExpressionKind) -> Expression {
+        Expression {
+            kind,
+            span: Span::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionKind {
+    Short(i16),
+    Int(i32),
+    Large(i64),
+    /// A `bigint` literal such as `10n`, as its digits
+    BigInt(String),
+    Float(f64),
+    /// A `decimal` literal such as `1.50d`, as written without the `d`
+    Decimal(String),
+    Bool(bool),
+    StringLiteral(String),
+    Identifier(String),
+    Null,
+
+    Array(Vec<Expression>),
+
+    /// Positional arguments come first, then any named ones. Positional
+    /// arguments may be spread.
+    CallExpr {
+        name: String,
+        params: Vec<Expression>,
+        named: Vec<NamedArg>,
+    },
+
+    /// `target.name(params)`, or `target?.name(params)` when `optional`
+    MethodCall {
+        target: Box<Expression>,
+        name: String,
+        params: Vec<Expression>,
+        optional: bool,
+    },
+
+    /// `target.name`, or `target?.name` when `optional`
+    Field {
+        target: Box<Expression>,
+        name: String,
+        optional: bool,
+    },
+
+    /// `target[index]`, where the index may be a range to take a slice.
+    /// `target?.[index]` when `optional`.
+    Index {
+        target: Box<Expression>,
+        index: Box<Expression>,
+        optional: bool,
+    },
+
+    /// `value?`, the value inside an `ok` result. An `err` result is
+    /// returned from the enclosing function instead.
+    Propagate(Box<Expression>),
+
+    /// `...items` in call arguments, which passes each element of an array
+    /// as its own argument
+    Spread(Box<Expression>),
+
+    /// `lhs ?? rhs`, the value of `lhs` unless it is null
+    Coalesce {
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+
+    /// `start..end` or `start..=end`, either bound may be left off. The
+    /// parser never sets `step`, `(0..10).step(2)` is a method call that
+    /// evaluates to a range value with one.
+    Range {
+        start: Option<Box<Expression>>,
+        end: Option<Box<Expression>>,
+        inclusive: bool,
+        step: Option<Box<Expression>>,
+    },
+
+    BinaryOp {
+        lhs: Box<Expression>,
+        op: TokenType,
+        rhs: Box<Expression>,
+    },
+
+    Conditional {
+        lhs: Box<Expression>,
+        op: ConditionType,
+        rhs: Box<Expression>,
+    },
+
+    /// An `if` used as a value. Its value is the trailing expression of the
+    /// chosen block, so it always needs an `else`.
+    If {
+        condition: Box<Expression>,
+        body: Vec<Ast>,
+        else_body: Vec<Ast>,
+    },
+}
+
+/// `name: value` in a call
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedArg {
+    pub name: String,
+    pub value: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionType {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Unary,
+}
+
+impl ConditionType {
+    /// `<`, `<=`, `>` and `>=`, which only numbers have
+    pub fn is_ordering(&self) -> bool {
+        matches!(
+            self,
+            ConditionType::Less
+                | ConditionType::LessEqual
+                | ConditionType::Greater
+                | ConditionType::GreaterEqual
+        )
+    }
+}
@@ -0,0 +1,566 @@
+//! Traversal of the syntax tree.
+//!
+//! `Visitor` walks the tree by reference, `VisitorMut` walks it by mutable
+//! reference and `Fold` rebuilds it by value. Every method has a default
+//! that calls the matching `walk_*`/`fold_*` function, so a pass only
+//! overrides the nodes it cares about and calls the walk function itself
+//! when it still wants the children visited.
+
+use super::{
+    Ast, CatchClause, Expression, ExpressionKind, FunctionBody, NamedArg, Param, Statement,
+    StatementKind,
+};
+
+pub trait Visitor {
+    fn visit_ast(&mut self, node: &Ast) {
+        walk_ast(self, node)
+    }
+
+    fn visit_block(&mut self, block: &[Ast]) {
+        walk_block(self, block)
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) {
+        walk_statement(self, stmt)
+    }
+
+    fn visit_function_body(&mut self, body: &FunctionBody) {
+        walk_function_body(self, body)
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) {
+        walk_expression(self, expr)
+    }
+}
+
+pub fn walk_ast<V: Visitor + ?Sized>(visitor: &mut V, node: &Ast) {
+    match node {
+        Ast::Expression(expr) => visitor.visit_expression(expr),
+        Ast::Statement(stmt) => visitor.visit_statement(stmt),
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, block: &[Ast]) {
+    for node in block {
+        visitor.visit_ast(node);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, stmt: &Statement) {
+    match &stmt.kind {
+        StatementKind::VariableAssignment { value, .. } => visitor.visit_expression(value),
+        StatementKind::IfStatement {
+            condition,
+            body,
+            else_body,
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block(body);
+            if let Some(else_body) = else_body {
+                visitor.visit_block(else_body);
+            }
+        }
+        StatementKind::For { iterable, body, .. } => {
+            visitor.visit_expression(iterable);
+            visitor.visit_block(body);
+        }
+        StatementKind::Function { params, body, .. } => {
+            for default in params.iter().filter_map(|param| param.default.as_ref()) {
+                visitor.visit_expression(default);
+            }
+            visitor.visit_function_body(body);
+        }
+        StatementKind::Impl { methods, .. } => {
+            for method in methods {
+                visitor.visit_statement(method);
+            }
+        }
+        StatementKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            visitor.visit_block(body);
+            if let Some(catch) = catch {
+                visitor.visit_block(&catch.body);
+            }
+            if let Some(finally) = finally {
+                visitor.visit_block(finally);
+            }
+        }
+        StatementKind::Throw(value) => visitor.visit_expression(value),
+        StatementKind::Struct { .. }
+        | StatementKind::Interface { .. }
+        | StatementKind::Import(_) => {}
+        StatementKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expression(value);
+            }
+        }
+        StatementKind::ExprStmt(expr) => visitor.visit_expression(expr),
+    }
+}
+
+pub fn walk_function_body<V: Visitor + ?Sized>(visitor: &mut V, body: &FunctionBody) {
+    visitor.visit_block(&body.stmts);
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expression) {
+    match &expr.kind {
+        ExpressionKind::Short(_)
+        | ExpressionKind::Int(_)
+        | ExpressionKind::Large(_)
+        | ExpressionKind::BigInt(_)
+        | ExpressionKind::Float(_)
+        | ExpressionKind::Decimal(_)
+        | ExpressionKind::Bool(_)
+        | ExpressionKind::StringLiteral(_)
+        | ExpressionKind::Identifier(_)
+        | ExpressionKind::Null => {}
+        ExpressionKind::Array(items) => {
+            for item in items {
+                visitor.visit_expression(item);
+            }
+        }
+        ExpressionKind::CallExpr { params, named, .. } => {
+            for param in params {
+                visitor.visit_expression(param);
+            }
+            for arg in named {
+                visitor.visit_expression(&arg.value);
+            }
+        }
+        ExpressionKind::MethodCall { target, params, .. } => {
+            visitor.visit_expression(target);
+            for param in params {
+                visitor.visit_expression(param);
+            }
+        }
+        ExpressionKind::Field { target, .. }
+        | ExpressionKind::Spread(target)
+        | ExpressionKind::Propagate(target) => visitor.visit_expression(target),
+        ExpressionKind::Index { target, index, .. } => {
+            visitor.visit_expression(target);
+            visitor.visit_expression(index);
+        }
+        ExpressionKind::Range {
+            start, end, step, ..
+        } => {
+            for bound in [start, end, step].into_iter().flatten() {
+                visitor.visit_expression(bound);
+            }
+        }
+        ExpressionKind::BinaryOp { lhs, rhs, .. }
+        | ExpressionKind::Conditional { lhs, rhs, .. }
+        | ExpressionKind::Coalesce { lhs, rhs } => {
+            visitor.visit_expression(lhs);
+            visitor.visit_expression(rhs);
+        }
+        ExpressionKind::If {
+            condition,
+            body,
+            else_body,
+        } => {
+            visitor.visit_expression(condition);
+            visitor.visit_block(body);
+            visitor.visit_block(else_body);
+        }
+    }
+}
+
+pub trait VisitorMut {
+    fn visit_ast_mut(&mut self, node: &mut Ast) {
+        walk_ast_mut(self, node)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Vec<Ast>) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_statement_mut(&mut self, stmt: &mut Statement) {
+        walk_statement_mut(self, stmt)
+    }
+
+    fn visit_function_body_mut(&mut self, body: &mut FunctionBody) {
+        walk_function_body_mut(self, body)
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr)
+    }
+}
+
+pub fn walk_ast_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Ast) {
+    match node {
+        Ast::Expression(expr) => visitor.visit_expression_mut(expr),
+        Ast::Statement(stmt) => visitor.visit_statement_mut(stmt),
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, block: &mut Vec<Ast>) {
+    for node in block {
+        visitor.visit_ast_mut(node);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &mut Statement) {
+    match &mut stmt.kind {
+        StatementKind::VariableAssignment { value, .. } => visitor.visit_expression_mut(value),
+        StatementKind::IfStatement {
+            condition,
+            body,
+            else_body,
+        } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_block_mut(body);
+            if let Some(else_body) = else_body {
+                visitor.visit_block_mut(else_body);
+            }
+        }
+        StatementKind::For { iterable, body, .. } => {
+            visitor.visit_expression_mut(iterable);
+            visitor.visit_block_mut(body);
+        }
+        StatementKind::Function { params, body, .. } => {
+            for default in params.iter_mut().filter_map(|param| param.default.as_mut()) {
+                visitor.visit_expression_mut(default);
+            }
+            visitor.visit_function_body_mut(body);
+        }
+        StatementKind::Impl { methods, .. } => {
+            for method in methods {
+                visitor.visit_statement_mut(method);
+            }
+        }
+        StatementKind::Try {
+            body,
+            catch,
+            finally,
+        } => {
+            visitor.visit_block_mut(body);
+            if let Some(catch) = catch {
+                visitor.visit_block_mut(&mut catch.body);
+            }
+            if let Some(finally) = finally {
+                visitor.visit_block_mut(finally);
+            }
+        }
+        StatementKind::Throw(value) => visitor.visit_expression_mut(value),
+        StatementKind::Struct { .. }
+        | StatementKind::Interface { .. }
+        | StatementKind::Import(_) => {}
+        StatementKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expression_mut(value);
+            }
+        }
+        StatementKind::ExprStmt(expr) => visitor.visit_expression_mut(expr),
+    }
+}
+
+pub fn walk_function_body_mut<V: VisitorMut + ?Sized>(visitor: &mut V, body: &mut FunctionBody) {
+    visitor.visit_block_mut(&mut body.stmts);
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match &mut expr.kind {
+        ExpressionKind::Short(_)
+        | ExpressionKind::Int(_)
+        | ExpressionKind::Large(_)
+        | ExpressionKind::BigInt(_)
+        | ExpressionKind::Float(_)
+        | ExpressionKind::Decimal(_)
+        | ExpressionKind::Bool(_)
+        | ExpressionKind::StringLiteral(_)
+        | ExpressionKind::Identifier(_)
+        | ExpressionKind::Null => {}
+        ExpressionKind::Array(items) => {
+            for item in items {
+                visitor.visit_expression_mut(item);
+            }
+        }
+        ExpressionKind::CallExpr { params, named, .. } => {
+            for param in params {
+                visitor.visit_expression_mut(param);
+            }
+            for arg in named {
+                visitor.visit_expression_mut(&mut arg.value);
+            }
+        }
+        ExpressionKind::MethodCall { target, params, .. } => {
+            visitor.visit_expression_mut(target);
+            for param in params {
+                visitor.visit_expression_mut(param);
+            }
+        }
+        ExpressionKind::Field { target, .. }
+        | ExpressionKind::Spread(target)
+        | ExpressionKind::Propagate(target) => visitor.visit_expression_mut(target),
+        ExpressionKind::Index { target, index, .. } => {
+            visitor.visit_expression_mut(target);
+            visitor.visit_expression_mut(index);
+        }
+        ExpressionKind::Range {
+            start, end, step, ..
+        } => {
+            for bound in [start, end, step].into_iter().flatten() {
+                visitor.visit_expression_mut(bound);
+            }
+        }
+        ExpressionKind::BinaryOp { lhs, rhs, .. }
+        | ExpressionKind::Conditional { lhs, rhs, .. }
+        | ExpressionKind::Coalesce { lhs, rhs } => {
+            visitor.visit_expression_mut(lhs);
+            visitor.visit_expression_mut(rhs);
+        }
+        ExpressionKind::If {
+            condition,
+            body,
+            else_body,
+        } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_block_mut(body);
+            visitor.visit_block_mut(else_body);
+        }
+    }
+}
+
+/// Rebuilds the tree bottom-up. Returning a different node from any
+/// `fold_*` method replaces it in the output.
+pub trait Fold {
+    fn fold_ast(&mut self, node: Ast) -> Ast {
+        fold_ast(self, node)
+    }
+
+    fn fold_block(&mut self, block: Vec<Ast>) -> Vec<Ast> {
+        fold_block(self, block)
+    }
+
+    fn fold_statement(&mut self, stmt: Statement) -> Statement {
+        fold_statement(self, stmt)
+    }
+
+    fn fold_function_body(&mut self, body: FunctionBody) -> FunctionBody {
+        fold_function_body(self, body)
+    }
+
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        fold_expression(self, expr)
+    }
+}
+
+pub fn fold_ast<F: Fold + ?Sized>(folder: &mut F, node: Ast) -> Ast {
+    match node {
+        Ast::Expression(expr) => Ast::Expression(folder.fold_expression(expr)),
+        Ast::Statement(stmt) => Ast::Statement(folder.fold_statement(stmt)),
+    }
+}
+
+pub fn fold_block<F: Fold + ?Sized>(folder: &mut F, block: Vec<Ast>) -> Vec<Ast> {
+    block
+        .into_iter()
+        .map(|node| folder.fold_ast(node))
+        .collect()
+}
+
+pub fn fold_statement<F: Fold + ?Sized>(folder: &mut F, stmt: Statement) -> Statement {
+    let kind = match stmt.kind {
+        StatementKind::VariableAssignment {
+            constant,
+            name,
+            value,
+        } => StatementKind::VariableAssignment {
+            constant,
+            name,
+            value: folder.fold_expression(value),
+        },
+        StatementKind::IfStatement {
+            condition,
+            body,
+            else_body,
+        } => StatementKind::IfStatement {
+            condition: folder.fold_expression(condition),
+            body: folder.fold_block(body),
+            else_body: else_body.map(|else_body| folder.fold_block(else_body)),
+        },
+        StatementKind::Function {
+            name,
+            type_params,
+            params,
+            body,
+            ret_type,
+        } => StatementKind::Function {
+            name,
+            type_params,
+            params: params
+                .into_iter()
+                .map(|param| Param {
+                    default: param.default.map(|default| folder.fold_expression(default)),
+                    ..param
+                })
+                .collect(),
+            body: folder.fold_function_body(body),
+            ret_type,
+        },
+        StatementKind::For {
+            name,
+            iterable,
+            body,
+        } => StatementKind::For {
+            name,
+            iterable: folder.fold_expression(iterable),
+            body: folder.fold_block(body),
+        },
+        StatementKind::Impl {
+            interface,
+            target,
+            methods,
+        } => StatementKind::Impl {
+            interface,
+            target,
+            methods: methods
+                .into_iter()
+                .map(|method| folder.fold_statement(method))
+                .collect(),
+        },
+        StatementKind::Try {
+            body,
+            catch,
+            finally,
+        } => StatementKind::Try {
+            body: folder.fold_block(body),
+            catch: catch.map(|catch| CatchClause {
+                body: folder.fold_block(catch.body),
+                ..catch
+            }),
+            finally: finally.map(|finally| folder.fold_block(finally)),
+        },
+        StatementKind::Throw(value) => StatementKind::Throw(folder.fold_expression(value)),
+        kind @ (StatementKind::Struct { .. }
+        | StatementKind::Interface { .. }
+        | StatementKind::Import(_)) => kind,
+        StatementKind::Return(value) => {
+            StatementKind::Return(value.map(|value| folder.fold_expression(value)))
+        }
+        StatementKind::ExprStmt(expr) => StatementKind::ExprStmt(folder.fold_expression(expr)),
+    };
+
+    Statement {
+        kind,
+        span: stmt.span,
+        annotations: stmt.annotations,
+    }
+}
+
+pub fn fold_function_body<F: Fold + ?Sized>(folder: &mut F, body: FunctionBody) -> FunctionBody {
+    FunctionBody::new(folder.fold_block(body.stmts))
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expr: Expression) -> Expression {
+    let kind = match expr.kind {
+        ExpressionKind::Array(items) => ExpressionKind::Array(
+            items
+                .into_iter()
+                .map(|item| folder.fold_expression(item))
+                .collect(),
+        ),
+        ExpressionKind::CallExpr {
+            name,
+            params,
+            named,
+        } => ExpressionKind::CallExpr {
+            name,
+            params: params
+                .into_iter()
+                .map(|param| folder.fold_expression(param))
+                .collect(),
+            named: named
+                .into_iter()
+                .map(|arg| NamedArg {
+                    value: folder.fold_expression(arg.value),
+                    ..arg
+                })
+                .collect(),
+        },
+        ExpressionKind::MethodCall {
+            target,
+            name,
+            params,
+            optional,
+        } => ExpressionKind::MethodCall {
+            target: Box::new(folder.fold_expression(*target)),
+            name,
+            params: params
+                .into_iter()
+                .map(|param| folder.fold_expression(param))
+                .collect(),
+            optional,
+        },
+        ExpressionKind::Field {
+            target,
+            name,
+            optional,
+        } => ExpressionKind::Field {
+            target: Box::new(folder.fold_expression(*target)),
+            name,
+            optional,
+        },
+        ExpressionKind::Index {
+            target,
+            index,
+            optional,
+        } => ExpressionKind::Index {
+            target: Box::new(folder.fold_expression(*target)),
+            index: Box::new(folder.fold_expression(*index)),
+            optional,
+        },
+        ExpressionKind::Spread(inner) => {
+            ExpressionKind::Spread(Box::new(folder.fold_expression(*inner)))
+        }
+        ExpressionKind::Propagate(inner) => {
+            ExpressionKind::Propagate(Box::new(folder.fold_expression(*inner)))
+        }
+        ExpressionKind::Coalesce { lhs, rhs } => ExpressionKind::Coalesce {
+            lhs: Box::new(folder.fold_expression(*lhs)),
+            rhs: Box::new(folder.fold_expression(*rhs)),
+        },
+        ExpressionKind::Range {
+            start,
+            end,
+            inclusive,
+            step,
+        } => {
+            let mut fold_bound = |bound: Option<Box<Expression>>| {
+                bound.map(|b| Box::new(folder.fold_expression(*b)))
+            };
+            ExpressionKind::Range {
+                start: fold_bound(start),
+                end: fold_bound(end),
+                inclusive,
+                step: fold_bound(step),
+            }
+        }
+        ExpressionKind::BinaryOp { lhs, op, rhs } => ExpressionKind::BinaryOp {
+            lhs: Box::new(folder.fold_expression(*lhs)),
+            op,
+            rhs: Box::new(folder.fold_expression(*rhs)),
+        },
+        ExpressionKind::Conditional { lhs, op, rhs } => ExpressionKind::Conditional {
+            lhs: Box::new(folder.fold_expression(*lhs)),
+            op,
+            rhs: Box::new(folder.fold_expression(*rhs)),
+        },
+        ExpressionKind::If {
+            condition,
+            body,
+            else_body,
+        } => ExpressionKind::If {
+            condition: Box::new(folder.fold_expression(*condition)),
+            body: folder.fold_block(body),
+            else_body: folder.fold_block(else_body),
+        },
+        kind => kind,
+    };
+
+    Expression::new(kind, expr.span)
+}
@@ -1,5 +1,7 @@
-use crate::error::ERROR_INDICATOR;
+use super::span::Span;
+use crate::error::{ErrorType::ParseError, VeloError};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TokenType {
     // Literal Types
@@ -20,6 +22,7 @@ pub enum TokenType {
     Break,
     Continue,
     Return,
+    In,
 
     True,
     False,
@@ -29,6 +32,11 @@ pub enum TokenType {
     Struct,
     Enum,
     Type,
+    Impl,
+    Try,
+    Catch,
+    Finally,
+    Throw,
 
     // Grouping * Operators
     Add,
@@ -48,13 +56,21 @@ pub enum TokenType {
     Comma,
     Semicolon,
     Dot,
+    DotDot,
+    DotDotEq,
+    Ellipsis,
     Colon,
     DoubleColon,
+    Question,
+    QuestionDot,
+    DoubleQuestion,
     ColonEq,
     Arrow,
     Tilde,
+    At,
     BitwiseOr,
     BitwiseAnd,
+    BitwiseXor,
     ShiftLeft,
     ShiftRight,
     And,
@@ -76,15 +92,8 @@ pub enum TokenType {
     EOF,
 }
 
-impl TokenType {
-    pub fn to_string(token_type: TokenType) -> String {
-        let string_repr = format!("{:#?}", token_type);
-        format!("{}{}", &string_repr[..1].to_lowercase(), &string_repr[1..])
-    }
-}
-
 pub struct KeywordMap {
-    data: [(&'static str, TokenType); 18],
+    data: [(&'static str, TokenType); 24],
 }
 
 impl KeywordMap {
@@ -101,6 +110,7 @@ impl KeywordMap {
                 ("break", TokenType::Break),
                 ("continue", TokenType::Continue),
                 ("return", TokenType::Return),
+                ("in", TokenType::In),
                 ("true", TokenType::True),
                 ("false", TokenType::False),
                 ("import", TokenType::Import),
@@ -108,6 +118,11 @@ impl KeywordMap {
                 ("struct", TokenType::Struct),
                 ("enum", TokenType::Enum),
                 ("type", TokenType::Type),
+                ("impl", TokenType::Impl),
+                ("try", TokenType::Try),
+                ("catch", TokenType::Catch),
+                ("finally", TokenType::Finally),
+                ("throw", TokenType::Throw),
                 ("null", TokenType::Null),
             ],
         }
@@ -115,7 +130,7 @@ impl KeywordMap {
     pub fn get(&self, key: &str) -> Option<TokenType> {
         for (k, v) in &self.data {
             if *k == key {
-                return Some(v.clone());
+                return Some(*v);
             }
         }
         None
@@ -127,8 +142,8 @@ pub const KEYWORDS: KeywordMap = KeywordMap::new();
 #[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
-    pub line_num: usize,
     pub lexeme: String,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -137,45 +152,77 @@ pub enum Type {
     Int,
     Short,
     Large,
+    /// An integer of any size
+    BigInt,
     Float,
+    /// An exact decimal number
+    Decimal,
     String,
     Array(Box<Type>),
     Tuple(Vec<Type>),
+    /// A thrown error, with a kind and a message
+    Error,
+    /// `ok(value)` or `err(value)`
+    Result,
+    /// `T?`, a `T` or null. Every other type excludes null.
+    Optional(Box<Type>),
+    /// Any value, including null. It cannot be written, it is the element
+    /// type of a rest parameter declared without one and of a bare `array`.
+    Any,
+    /// A type parameter such as the `T` of `fun first<T>(xs array<T>) > T`
+    Param(String),
+    /// A struct or interface by name, with type arguments if it is generic,
+    /// e.g. `Box<int>`
+    Named {
+        name: String,
+        args: Vec<Type>,
+    },
     Void,
 }
 
 impl Type {
-    pub fn from_string(string: String) -> Type {
-        let type_str = &string;
-        let type_result = match type_str.as_str() {
+    /// Looks up a built-in type by name
+    pub fn from_string(string: &str) -> Option<Type> {
+        match string {
             "bool" => Some(Type::Bool),
             "int" => Some(Type::Int),
             "short" => Some(Type::Short),
             "large" => Some(Type::Large),
+            "bigint" => Some(Type::BigInt),
             "float" => Some(Type::Float),
+            "decimal" => Some(Type::Decimal),
             "string" => Some(Type::String),
-            "array" => Some(Type::Array(Box::new(Type::Bool))),
+            "array" => Some(Type::Array(Box::new(Type::Any))),
             "tuple" => Some(Type::Tuple(vec![Type::Bool])),
+            "error" => Some(Type::Error),
+            "result" => Some(Type::Result),
             "void" => Some(Type::Void),
             _ => None,
-        };
-
-        if type_result.is_none() {
-            let message = format!("{} Unknown type '{}'", ERROR_INDICATOR, type_str);
-            println!("{}", message);
-            // Todo: implement error handling kinda like parser
-            std::process::exit(1);
         }
-
-        type_result.unwrap()
     }
 }
 
+/// A `// ...` comment. The parser never sees comments, `velo fmt` puts
+/// them back by their spans.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// From the `//` up to the end of the line, trailing spaces left out
+    pub text: String,
+    pub span: Span,
+    /// Nothing but whitespace comes before it on its line
+    pub own_line: bool,
+}
+
 pub struct Lexer {
     pub source: Vec<char>,
     pub tokens: Vec<Token>,
+    pub comments: Vec<Comment>,
+    /// Unclosed strings and characters that start no token. Lexing carries
+    /// on past them so that every one is reported.
+    pub errors: Vec<VeloError>,
+    pos: usize,
     line: usize,
-    start_pos: usize,
+    column: usize,
 }
 
 impl Lexer {
@@ -183,200 +230,304 @@ impl Lexer {
         Lexer {
             source: src.chars().collect(),
             tokens: Vec::new(),
+            comments: Vec::new(),
+            errors: Vec::new(),
+            pos: 0,
             line: 1,
-            start_pos: 0,
+            column: 1,
         }
     }
 
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.source.get(self.pos + offset).copied()
+    }
+
+    fn advance(&mut self) -> char {
+        let c = self.source[self.pos];
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        c
+    }
+
+    fn span_from(&self, start: usize, line: usize, column: usize) -> Span {
+        Span::new(start, self.pos, line, column)
+    }
+
+    fn error(&mut self, span: Span, message: &str) {
+        self.errors
+            .push(VeloError::error(span, message, ParseError));
+    }
+
     fn get_string(&mut self) -> Token {
+        let (start, line, column) = (self.pos, self.line, self.column);
         let mut string = String::new();
-        self.source.remove(0);
+        self.advance();
 
-        while let Some(c) = self.source.get(0) {
+        while let Some(c) = self.peek(0) {
+            self.advance();
             match c {
                 '"' => {
-                    self.source.remove(0);
                     return Token {
                         token_type: TokenType::String,
-                        line_num: self.line,
                         lexeme: string,
+                        span: self.span_from(start, line, column),
                     };
                 }
-                _ => {
-                    if *c == '\n' {
-                        self.line += 1;
+                '\\' => match self.peek(0) {
+                    Some(escaped @ ('"' | '\\' | 'n' | 't')) => {
+                        self.advance();
+                        string.push(match escaped {
+                            'n' => '\n',
+                            't' => '\t',
+                            _ => escaped,
+                        });
                     }
-                    string.push(*c);
-                    self.source.remove(0);
-                }
+                    _ => string.push(c),
+                },
+                _ => string.push(c),
             }
         }
 
-        eprintln!("err: unclosed string");
-        std::process::exit(1);
+        // Only the opening quote is underlined, the rest of the file having
+        // become the string
+        self.error(
+            Span::new(start, start + 1, line, column),
+            "Unclosed string, expected a '\"' to end it",
+        );
+        Token {
+            token_type: TokenType::String,
+            lexeme: string,
+            span: self.span_from(start, line, column),
+        }
     }
 
-    fn make_token(&mut self, c: char, tok_type: TokenType) -> Token {
-        let c = c.to_string();
-
-        self.source.remove(0);
-        Token {
-            token_type: tok_type,
-            line_num: self.line,
-            lexeme: c.to_string(),
+    fn comment(&mut self) {
+        let (start, line, column) = (self.pos, self.line, self.column);
+        let own_line = self.source[..start]
+            .iter()
+            .rev()
+            .take_while(|c| **c != '\n')
+            .all(|c| c.is_whitespace());
+        let mut text = String::new();
+        while self.peek(0).is_some_and(|c| c != '\n') {
+            text.push(self.advance());
         }
+        self.comments.push(Comment {
+            text: text.trim_end().to_string(),
+            span: self.span_from(start, line, column),
+            own_line,
+        });
     }
 
-    fn make_long_token(&mut self, s: &str, tok_type: TokenType) -> Token {
-        let s = s.to_string();
+    fn make_token(&mut self, tok_type: TokenType) -> Token {
+        self.make_long_token(1, tok_type)
+    }
 
-        self.source.remove(0);
-        self.source.remove(0);
+    fn make_long_token(&mut self, len: usize, tok_type: TokenType) -> Token {
+        let (start, line, column) = (self.pos, self.line, self.column);
+        let mut lexeme = String::new();
+        for _ in 0..len {
+            lexeme.push(self.advance());
+        }
         Token {
             token_type: tok_type,
-            line_num: self.line,
-            lexeme: s.to_string(),
+            lexeme,
+            span: self.span_from(start, line, column),
         }
     }
 
     pub fn tokenize(&mut self) -> Self {
         let mut tokens = Vec::new();
-        while !self.source.is_empty() {
-            match self.source[0] {
-                '(' => tokens.push(self.make_token(self.source[0], TokenType::LParen)),
-                ')' => tokens.push(self.make_token(self.source[0], TokenType::RParen)),
-                '[' => tokens.push(self.make_token(self.source[0], TokenType::LBracket)),
-                ']' => tokens.push(self.make_token(self.source[0], TokenType::RBracket)),
-                '{' => tokens.push(self.make_token(self.source[0], TokenType::LBrace)),
-                '}' => tokens.push(self.make_token(self.source[0], TokenType::RBrace)),
-
-                '+' => match self.source[1] {
-                    '=' => tokens.push(self.make_long_token("+=", TokenType::PlusEq)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Add)),
-                },
-                '-' => match self.source[1] {
-                    '=' => tokens.push(self.make_long_token("-=", TokenType::MinusEq)),
-                    '>' => tokens.push(self.make_long_token("-=", TokenType::Arrow)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Sub)),
-                },
-                '*' => match self.source[1] {
-                    '=' => tokens.push(self.make_long_token("*=", TokenType::TimesEq)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Mul)),
-                },
-                '/' => match self.source[1] {
-                    '=' => tokens.push(self.make_long_token("/=", TokenType::DivEq)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Div)),
-                },
-
-                '=' => match self.source[1] {
-                    '=' => tokens.push(self.make_long_token("==", TokenType::EqEq)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Eq)),
-                },
-
-                '!' => match self.source[1] {
-                    '=' => tokens.push(self.make_long_token("!=", TokenType::Ne)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Not)),
-                },
-
-                '%' => tokens.push(self.make_token(self.source[0], TokenType::Mod)),
-                '$' => tokens.push(self.make_token(self.source[0], TokenType::Dollar)),
-
-                ',' => tokens.push(self.make_token(self.source[0], TokenType::Comma)),
-                ';' => tokens.push(self.make_token(self.source[0], TokenType::Semicolon)),
-                '.' => tokens.push(self.make_token(self.source[0], TokenType::Dot)),
-                ':' => match self.source[1] {
-                    ':' => tokens.push(self.make_long_token("::", TokenType::DoubleColon)),
-                    '=' => tokens.push(self.make_long_token(":=", TokenType::ColonEq)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Colon)),
-                },
-                '~' => tokens.push(self.make_token(self.source[0], TokenType::Tilde)),
-
-                '|' => match self.source[1] {
-                    '|' => tokens.push(self.make_long_token("||", TokenType::Or)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::BitwiseOr)),
-                },
-
-                '&' => match self.source[1] {
-                    '&' => tokens.push(self.make_long_token("&&", TokenType::And)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::BitwiseAnd)),
-                },
+        while let Some(c) = self.peek(0) {
+            let next = self.peek(1);
+            let token = match (c, next) {
+                ('(', _) => self.make_token(TokenType::LParen),
+                (')', _) => self.make_token(TokenType::RParen),
+                ('[', _) => self.make_token(TokenType::LBracket),
+                (']', _) => self.make_token(TokenType::RBracket),
+                ('{', _) => self.make_token(TokenType::LBrace),
+                ('}', _) => self.make_token(TokenType::RBrace),
+
+                ('+', Some('=')) => self.make_long_token(2, TokenType::PlusEq),
+                ('+', _) => self.make_token(TokenType::Add),
+                ('-', Some('=')) => self.make_long_token(2, TokenType::MinusEq),
+                ('-', Some('>')) => self.make_long_token(2, TokenType::Arrow),
+                ('-', _) => self.make_token(TokenType::Sub),
+                ('*', Some('=')) => self.make_long_token(2, TokenType::TimesEq),
+                ('*', _) => self.make_token(TokenType::Mul),
+                ('/', Some('/')) => {
+                    self.comment();
+                    continue;
+                }
+                ('/', Some('=')) => self.make_long_token(2, TokenType::DivEq),
+                ('/', _) => self.make_token(TokenType::Div),
 
-                '<' => match self.source[1] {
-                    '<' => tokens.push(self.make_long_token("<<", TokenType::ShiftLeft)),
-                    '=' => tokens.push(self.make_long_token("<=", TokenType::LtEq)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Lt)),
-                },
+                ('=', Some('=')) => self.make_long_token(2, TokenType::EqEq),
+                ('=', _) => self.make_token(TokenType::Eq),
+                ('!', Some('=')) => self.make_long_token(2, TokenType::Ne),
+                ('!', _) => self.make_token(TokenType::Not),
 
-                '>' => match self.source[1] {
-                    '>' => tokens.push(self.make_long_token(">>", TokenType::ShiftRight)),
-                    '=' => tokens.push(self.make_long_token(">=", TokenType::GtEq)),
-                    _ => tokens.push(self.make_token(self.source[0], TokenType::Gt)),
-                },
+                ('%', _) => self.make_token(TokenType::Mod),
+                ('$', _) => self.make_token(TokenType::Dollar),
 
-                '"' => tokens.push(self.get_string()),
+                (',', _) => self.make_token(TokenType::Comma),
+                (';', _) => self.make_token(TokenType::Semicolon),
+                ('.', Some('.')) if self.peek(2) == Some('.') => {
+                    self.make_long_token(3, TokenType::Ellipsis)
+                }
+                ('.', Some('.')) if self.peek(2) == Some('=') => {
+                    self.make_long_token(3, TokenType::DotDotEq)
+                }
+                ('.', Some('.')) => self.make_long_token(2, TokenType::DotDot),
+                ('.', _) => self.make_token(TokenType::Dot),
+                (':', Some(':')) => self.make_long_token(2, TokenType::DoubleColon),
+                (':', Some('=')) => self.make_long_token(2, TokenType::ColonEq),
+                (':', _) => self.make_token(TokenType::Colon),
+                ('?', Some('.')) => self.make_long_token(2, TokenType::QuestionDot),
+                ('?', Some('?')) => self.make_long_token(2, TokenType::DoubleQuestion),
+                ('?', _) => self.make_token(TokenType::Question),
+                ('~', _) => self.make_token(TokenType::Tilde),
+                ('@', _) => self.make_token(TokenType::At),
+
+                ('|', Some('|')) => self.make_long_token(2, TokenType::Or),
+                ('|', _) => self.make_token(TokenType::BitwiseOr),
+                ('&', Some('&')) => self.make_long_token(2, TokenType::And),
+                ('&', _) => self.make_token(TokenType::BitwiseAnd),
+                ('^', _) => self.make_token(TokenType::BitwiseXor),
+
+                ('<', Some('<')) => self.make_long_token(2, TokenType::ShiftLeft),
+                ('<', Some('=')) => self.make_long_token(2, TokenType::LtEq),
+                ('<', _) => self.make_token(TokenType::Lt),
+                ('>', Some('>')) => self.make_long_token(2, TokenType::ShiftRight),
+                ('>', Some('=')) => self.make_long_token(2, TokenType::GtEq),
+                ('>', _) => self.make_token(TokenType::Gt),
+
+                ('"', _) => self.get_string(),
                 _ => {
-                    if self.source[0].is_ascii_whitespace() {
-                        match self.source[0] {
-                            '\n' => {
-                                self.line += 1;
-                                self.source.remove(0);
-                            }
-                            _ => {
-                                self.source.remove(0);
-                            }
-                        }
-                    } else if self.source[0].is_ascii_alphabetic() {
+                    if c.is_ascii_whitespace() {
+                        self.advance();
+                        continue;
+                    }
+
+                    let (start, line, column) = (self.pos, self.line, self.column);
+                    if c.is_ascii_alphabetic() || c == '_' {
                         let mut ident = String::new();
-                        while !self.source.is_empty() && self.source[0].is_ascii_alphabetic() {
-                            ident.push(self.source.remove(0));
+                        while self
+                            .peek(0)
+                            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+                        {
+                            ident.push(self.advance());
                         }
-                        match KEYWORDS.get(&ident[..]) {
-                            Some(token_type) => tokens.push(Token {
-                                token_type,
-                                line_num: self.line,
-                                lexeme: ident,
-                            }),
-                            None => tokens.push(Token {
-                                token_type: TokenType::Identifier,
-                                line_num: self.line,
-                                lexeme: ident,
-                            }),
+                        let token_type = KEYWORDS.get(&ident).unwrap_or(TokenType::Identifier);
+                        Token {
+                            token_type,
+                            lexeme: ident,
+                            span: self.span_from(start, line, column),
                         }
-                    } else if self.source[0].is_digit(10) {
+                    } else if c.is_ascii_digit() {
                         let mut num = String::new();
-                        while !self.source.is_empty()
-                            && (self.source[0].is_digit(10) || self.source[0] == '.')
-                        {
-                            num.push(self.source.remove(0));
+                        // `0..10` is a range, not a number followed by `.10`
+                        while let Some(c) = self.peek(0) {
+                            let range = c == '.' && self.peek(1) == Some('.');
+                            if !(c.is_ascii_digit() || c == '.') || range {
+                                break;
+                            }
+                            num.push(self.advance());
+                        }
+                        // `10n` is a `bigint` and `1.5d` a `decimal`
+                        let suffix = self.peek(0).filter(|c| *c == 'n' || *c == 'd');
+                        let word = self
+                            .peek(1)
+                            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_');
+                        if suffix.is_some() && !word {
+                            num.push(self.advance());
                         }
 
-                        tokens.push(Token {
+                        Token {
                             token_type: TokenType::NumericLiteral,
-                            line_num: self.line,
                             lexeme: num,
-                        })
+                            span: self.span_from(start, line, column),
+                        }
                     } else {
-                        eprintln!(
-                            "[err] unrecognized character found in source: {}",
-                            self.source[0]
-                        );
-                        std::process::exit(1);
+                        self.advance();
+                        let span = self.span_from(start, line, column);
+                        self.error(span, &format!("Unrecognized character '{}'", c));
+                        continue;
                     }
                 }
-            }
+            };
+            tokens.push(token);
         }
 
         tokens.push(Token {
             token_type: TokenType::EOF,
-            line_num: self.line,
             lexeme: "EOF".to_string(),
+            span: Span::new(self.pos, self.pos, self.line, self.column),
         });
 
         Self {
             source: vec![],
             tokens,
+            comments: std::mem::take(&mut self.comments),
+            errors: std::mem::take(&mut self.errors),
+            pos: self.pos,
             line: self.line,
-            start_pos: self.start_pos,
+            column: self.column,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Lexer, TokenType};
+
+    #[test]
+    fn unclosed_string_is_an_error() {
+        let lexed = Lexer::new("x := 1;\ny := \"abc;").tokenize();
+        let messages: Vec<&str> = lexed.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["Unclosed string, expected a '\"' to end it"]);
+        let span = lexed.errors[0].span;
+        assert_eq!((span.line, span.column, span.end - span.start), (2, 6, 1));
+    }
+
+    #[test]
+    fn every_unrecognized_character_is_reported() {
+        let lexed = Lexer::new("a # b ` c").tokenize();
+        let found: Vec<(String, usize)> = lexed
+            .errors
+            .iter()
+            .map(|e| (e.message.clone(), e.span.column))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Unrecognized character '#'".to_string(), 3),
+                ("Unrecognized character '`'".to_string(), 7),
+            ]
+        );
+        // The rest of the source is still lexed
+        let types: Vec<TokenType> = lexed.tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            [
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::Identifier,
+                TokenType::EOF
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes_and_suffixes() {
+        let lexed = Lexer::new(r#""a\"b\n" 10n 1.5d 0..3"#).tokenize();
+        assert!(lexed.errors.is_empty());
+        let lexemes: Vec<&str> = lexed.tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(lexemes, ["a\"b\n", "10n", "1.5d", "0", "..", "3", "EOF"]);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod ast;
 pub mod lexer;
 pub mod parse;
+pub mod printer;
+pub mod span;
@@ -1,170 +1,338 @@
-use crate::error::ERROR_INDICATOR;
-use crate::syntax::ast::Expression;
-use crate::syntax::lexer::{KeywordMap, Token, TokenType, KEYWORDS};
-use crate::syntax::parse::{ConditionType, Parser};
+use crate::syntax::ast::{ConditionType, Expression, ExpressionKind};
+use crate::syntax::lexer::{Token, TokenType};
+use crate::syntax::parse::{ParseResult, Parser};
 
 impl Parser {
-    pub fn parse_binary(&mut self) -> Expression {
-        let mut to_eval: Vec<Token> = Vec::new();
-
-        let mut keyword_error = false;
-        let mut keyword_fault = TokenType::Null;
-        let mut current_index = self.cursor + 1;
-
-        to_eval.push(self.tokens[current_index - 1].clone());
-
-        while let Some(next_token) = self.tokens.get(current_index) {
-            if [
-                TokenType::NumericLiteral,
-                TokenType::Identifier,
-                TokenType::Add,
-                TokenType::Sub,
-                TokenType::Mul,
-                TokenType::Div,
-                TokenType::EqEq, // Include DoubleEqual for truthy evaluation
-                TokenType::LParen,
-            ]
-            .contains(&next_token.token_type)
-                || KeywordMap::get(&KEYWORDS, &next_token.lexeme).is_some()
-            {
-                if KEYWORDS.get(&next_token.lexeme).is_some() {
-                    keyword_error = true;
-                    keyword_fault = next_token.token_type;
-                    println!("keyword: {:#?}", KEYWORDS.get(&next_token.lexeme))
-                }
-                if next_token.token_type == TokenType::Identifier {
-                    to_eval.push(next_token.clone());
-                    current_index += 1;
-                } else {
-                    to_eval.push(next_token.clone());
-                    current_index += 1;
-                }
-            } else {
-                break;
-            }
+    pub fn parse_expression(&mut self) -> ParseResult<Expression> {
+        self.parse_range()
+    }
+
+    /// `start..end` and `start..=end`, binding looser than anything else.
+    /// Either bound may be left off, as in `xs[..3]`, except that `..=`
+    /// needs an end.
+    fn parse_range(&mut self) -> ParseResult<Expression> {
+        let first = self.peek().span;
+        let start = match self.peek().token_type {
+            TokenType::DotDot | TokenType::DotDotEq => None,
+            _ => Some(self.parse_coalesce()?),
+        };
+        let inclusive = match self.peek().token_type {
+            TokenType::DotDot => false,
+            TokenType::DotDotEq => true,
+            // Without a leading `..` the start was parsed
+            _ => return Ok(start.expect("range start")),
+        };
+        let op = self.advance();
+
+        let end = if self.at_range_end() {
+            None
+        } else {
+            Some(self.parse_coalesce()?)
+        };
+        if inclusive && end.is_none() {
+            return Err(self.error(op.span, "Expected an end bound after `..=`"));
         }
 
-        let keyword_error_msg = format!(
-            "{} \x1b[1mExpected ';' after expression, found keyword '{}'\x1b[0m",
-            ERROR_INDICATOR,
-            TokenType::to_string(keyword_fault),
-        );
+        let span = first.to(end.as_ref().map_or(op.span, |end| end.span));
+        let range = ExpressionKind::Range {
+            start: start.map(Box::new),
+            end: end.map(Box::new),
+            inclusive,
+            step: None,
+        };
+        Ok(Expression::new(range, span))
+    }
 
-        self.cursor = current_index - 1;
+    /// Whether the next token closes a range that has no end bound
+    fn at_range_end(&self) -> bool {
+        matches!(
+            self.peek().token_type,
+            TokenType::RBracket
+                | TokenType::RParen
+                | TokenType::LBrace
+                | TokenType::RBrace
+                | TokenType::Comma
+                | TokenType::Semicolon
+                | TokenType::EOF
+        )
+    }
 
-        match keyword_error {
-            false => {
-                let res = Self::parse_expression(to_eval); // Modified to capture truthy result
-                res
-            }
-            _ => {
-                self.throw_error(self.tokens[1].line_num, keyword_error_msg);
-                Expression::Float(0.0)
-            }
+    /// `lhs ?? rhs`, which associates to the right so that
+    /// `a ?? b ?? c` takes the first value that is not null
+    fn parse_coalesce(&mut self) -> ParseResult<Expression> {
+        let lhs = self.parse_conditional()?;
+        if !self.check(TokenType::DoubleQuestion) {
+            return Ok(lhs);
+        }
+        self.advance();
+
+        let rhs = self.parse_coalesce()?;
+        let span = lhs.span.to(rhs.span);
+        let coalesce = ExpressionKind::Coalesce {
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        };
+        Ok(Expression::new(coalesce, span))
+    }
+
+    /// `lhs == rhs` and `lhs != rhs`
+    fn parse_conditional(&mut self) -> ParseResult<Expression> {
+        let mut lhs = self.parse_comparison()?;
+
+        loop {
+            let op = match self.peek().token_type {
+                TokenType::EqEq => ConditionType::Equal,
+                TokenType::Ne => ConditionType::NotEqual,
+                _ => break,
+            };
+            self.advance();
+            let rhs = self.parse_comparison()?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expression::new(
+                ExpressionKind::Conditional {
+                    lhs: Box::new(lhs),
+                    op,
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
         }
+
+        Ok(lhs)
     }
 
-    pub fn parse_expression(tokens: Vec<Token>) -> Expression {
-        let mut ops_stack: Vec<TokenType> = Vec::new();
-        let mut expr_stack: Vec<Expression> = Vec::new();
-        let mut i = 0;
-
-        let mut index = 0;
-        while index < tokens.len() {
-            if tokens[index].token_type == TokenType::EqEq {
-                let lhs_tokens = tokens[..index].to_vec();
-                let rhs_tokens = tokens[(index + 1)..].to_vec();
-
-                let lhs_expr = Self::parse_expression(lhs_tokens);
-                let rhs_expr = Self::parse_expression(rhs_tokens);
-
-                return Expression::Conditional {
-                    lhs: Box::new(lhs_expr),
-                    op: ConditionType::Equal,
-                    rhs: Box::new(rhs_expr),
-                };
+    /// `<`, `<=`, `>` and `>=`, binding tighter than `==` so that
+    /// `a < b == c < d` compares the two results
+    fn parse_comparison(&mut self) -> ParseResult<Expression> {
+        let mut lhs = self.parse_binary(1)?;
+
+        loop {
+            let op = match self.peek().token_type {
+                TokenType::Lt => ConditionType::Less,
+                TokenType::LtEq => ConditionType::LessEqual,
+                TokenType::Gt => ConditionType::Greater,
+                TokenType::GtEq => ConditionType::GreaterEqual,
+                _ => break,
+            };
+            self.advance();
+            let rhs = self.parse_binary(1)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expression::new(
+                ExpressionKind::Conditional {
+                    lhs: Box::new(lhs),
+                    op,
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
+        }
+
+        Ok(lhs)
+    }
+
+    /// Arithmetic by precedence climbing, operators of equal precedence
+    /// associate to the left
+    pub fn parse_binary(&mut self, min_precedence: i32) -> ParseResult<Expression> {
+        let mut lhs = self.parse_postfix()?;
+
+        loop {
+            let op = self.peek().token_type;
+            let precedence = Self::precedence(&op);
+            if precedence == 0 || precedence < min_precedence {
+                break;
             }
-            index += 1;
+            self.advance();
+
+            let rhs = self.parse_binary(precedence + 1)?;
+            let span = lhs.span.to(rhs.span);
+            lhs = Expression::new(
+                ExpressionKind::BinaryOp {
+                    lhs: Box::new(lhs),
+                    op,
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
         }
 
-        while i < tokens.len() {
-            match tokens[i].token_type {
-                TokenType::LParen => {
-                    ops_stack.push(TokenType::LParen);
-                    expr_stack.push(Expression::Null);
-                }
-                TokenType::RParen => {
-                    while let Some(&top_op) = ops_stack.last() {
-                        if top_op == TokenType::LParen {
-                            ops_stack.pop().unwrap();
-                            break;
-                        } else {
-                            let rhs_expr = expr_stack.pop().unwrap();
-                            let lhs_expr = expr_stack.pop().unwrap();
-                            let op = ops_stack.pop().unwrap();
-                            let new_expr = Expression::BinaryOp {
-                                lhs: Box::new(lhs_expr),
-                                op,
-                                rhs: Box::new(rhs_expr),
-                            };
-                            expr_stack.push(new_expr);
-                        }
-                    }
-                }
-                TokenType::Add | TokenType::Sub | TokenType::Mul | TokenType::Div => {
-                    while let Some(&top_op) = ops_stack.last() {
-                        if Self::precedence(&top_op) >= Self::precedence(&tokens[i].token_type)
-                            && top_op != TokenType::LParen
-                        {
-                            let rhs_expr = expr_stack.pop().unwrap();
-                            let lhs_expr = expr_stack.pop().unwrap();
-                            let op = ops_stack.pop().unwrap();
-                            let new_expr = Expression::BinaryOp {
-                                lhs: Box::new(lhs_expr),
-                                op,
-                                rhs: Box::new(rhs_expr),
-                            };
-                            expr_stack.push(new_expr);
-                        } else {
-                            break;
-                        }
-                    }
-                    ops_stack.push(tokens[i].token_type.clone());
-                }
-                TokenType::Identifier => {
-                    let num = tokens[i].lexeme.clone();
-                    expr_stack.push(Expression::Identifier(num));
+        Ok(lhs)
+    }
+
+    /// Indexing `xs[i]`, slicing `xs[1..4]`, fields `x.name` and method
+    /// calls `x.name()`, each of which has an optional form starting with
+    /// `?.` that gives null when `x` is null
+    fn parse_postfix(&mut self) -> ParseResult<Expression> {
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            expr = match self.peek().token_type {
+                TokenType::LBracket => self.index(expr, false)?,
+                TokenType::QuestionDot if self.peek_at(1).token_type == TokenType::LBracket => {
+                    self.advance();
+                    self.index(expr, true)?
                 }
-                _ => {
-                    let num = tokens[i].lexeme.clone().parse::<f32>();
-                    if num.is_ok() {
-                        expr_stack.push(Expression::Float(num.unwrap()));
-                    }
+                TokenType::Dot | TokenType::QuestionDot => self.member(expr)?,
+                TokenType::Question => self.propagate(expr)?,
+                _ => break,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// `?` after a result, which returns early from the enclosing function
+    fn propagate(&mut self, value: Expression) -> ParseResult<Expression> {
+        let question = self.advance();
+        if self.function_depth == 0 {
+            return Err(self.error(
+                question.span,
+                "Cannot use `?` outside of a function, there is nothing to return from",
+            ));
+        }
+
+        let span = value.span.to(question.span);
+        Ok(Expression::new(
+            ExpressionKind::Propagate(Box::new(value)),
+            span,
+        ))
+    }
+
+    /// `[index]` after `target`
+    fn index(&mut self, target: Expression, optional: bool) -> ParseResult<Expression> {
+        self.advance();
+        let index = self.parse_expression()?;
+        let close = self.expect(TokenType::RBracket, "']' to close '['")?;
+
+        let span = target.span.to(close.span);
+        let index = ExpressionKind::Index {
+            target: Box::new(target),
+            index: Box::new(index),
+            optional,
+        };
+        Ok(Expression::new(index, span))
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Expression> {
+        let token = self.peek().clone();
+        let kind = match token.token_type {
+            TokenType::True => ExpressionKind::Bool(true),
+            TokenType::False => ExpressionKind::Bool(false),
+            TokenType::Null => ExpressionKind::Null,
+            TokenType::String => ExpressionKind::StringLiteral(token.lexeme.clone()),
+            TokenType::NumericLiteral => self.parse_number(&token, false)?,
+            // A leading `-` is part of the number literal
+            TokenType::Sub if self.peek_at(1).token_type == TokenType::NumericLiteral => {
+                self.advance();
+                let number = self.advance();
+                let value = self.parse_number(&number, true)?;
+                return Ok(Expression::new(value, token.span.to(number.span)));
+            }
+            TokenType::Identifier => {
+                if self.peek_at(1).token_type == TokenType::LParen {
+                    return self.call_expr();
                 }
+                ExpressionKind::Identifier(token.lexeme.clone())
+            }
+            TokenType::If => return self.if_expression(),
+            TokenType::LBracket => return self.array_literal(),
+            TokenType::LParen => {
+                self.advance();
+                let mut inner = self.parse_expression()?;
+                let close = self.expect(TokenType::RParen, "')' to close '('")?;
+                inner.span = token.span.to(close.span);
+                return Ok(inner);
+            }
+            _ => return Err(self.unexpected("an expression")),
+        };
+
+        self.advance();
+        Ok(Expression::new(kind, token.span))
+    }
+
+    /// `[item, ...]`
+    fn array_literal(&mut self) -> ParseResult<Expression> {
+        let open = self.advance();
+
+        let mut items = Vec::new();
+        while !self.check(TokenType::RBracket) {
+            items.push(self.parse_expression()?);
+
+            if !self.check(TokenType::RBracket) {
+                self.expect(TokenType::Comma, "',' or ']' in array")?;
             }
-            i += 1;
         }
+        let close = self.advance();
 
-        while !ops_stack.is_empty() {
-            let rhs_expr = expr_stack.pop().unwrap();
-            let lhs_expr = expr_stack.pop().unwrap();
-            let op = ops_stack.pop().unwrap();
-            let new_expr = Expression::BinaryOp {
-                lhs: Box::new(lhs_expr),
-                op,
-                rhs: Box::new(rhs_expr),
+        Ok(Expression::new(
+            ExpressionKind::Array(items),
+            open.span.to(close.span),
+        ))
+    }
+
+    /// A number with a `.` is a `float`, any other is an `int`, or a
+    /// `large` when it does not fit in an `int`. An `n` after the digits
+    /// makes a `bigint` and a `d` a `decimal`.
+    fn parse_number(&self, token: &Token, negative: bool) -> ParseResult<ExpressionKind> {
+        let text = if negative {
+            format!("-{}", token.lexeme)
+        } else {
+            token.lexeme.clone()
+        };
+        if let Some(digits) = text.strip_suffix('n') {
+            if digits.contains('.') {
+                return Err(self.error(
+                    token.span,
+                    &format!(
+                        "A bigint literal cannot have a fraction, found '{}'",
+                        token.lexeme
+                    ),
+                ));
+            }
+            return Ok(ExpressionKind::BigInt(digits.to_string()));
+        }
+        if let Some(digits) = text.strip_suffix('d') {
+            if digits.matches('.').count() > 1 || digits.ends_with('.') {
+                return Err(self.error(
+                    token.span,
+                    &format!("Invalid number literal '{}'", token.lexeme),
+                ));
+            }
+            return Ok(ExpressionKind::Decimal(digits.to_string()));
+        }
+        if text.contains('.') {
+            return match text.parse::<f64>() {
+                Ok(num) => Ok(ExpressionKind::Float(num)),
+                Err(_) => Err(self.error(
+                    token.span,
+                    &format!("Invalid number literal '{}'", token.lexeme),
+                )),
             };
-            expr_stack.push(new_expr);
         }
 
-        expr_stack.pop().unwrap()
+        match text.parse::<i64>() {
+            Ok(num) => Ok(match i32::try_from(num) {
+                Ok(num) => ExpressionKind::Int(num),
+                Err(_) => ExpressionKind::Large(num),
+            }),
+            Err(_) => Err(self.error(
+                token.span,
+                &format!(
+                    "Number literal '{}' is too large, the largest integer is {}",
+                    token.lexeme,
+                    i64::MAX
+                ),
+            )),
+        }
     }
 
     fn precedence(op: &TokenType) -> i32 {
         match op {
-            TokenType::Add | TokenType::Sub => 1,
-            TokenType::Mul | TokenType::Div => 2,
-            TokenType::LParen => 3,
+            TokenType::BitwiseOr => 1,
+            TokenType::BitwiseXor => 2,
+            TokenType::BitwiseAnd => 3,
+            TokenType::ShiftLeft | TokenType::ShiftRight => 4,
+            TokenType::Add | TokenType::Sub => 5,
+            TokenType::Mul | TokenType::Div | TokenType::Mod => 6,
             _ => 0,
         }
     }
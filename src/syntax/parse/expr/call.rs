@@ -1,125 +1,114 @@
-use super::super::Parser;
-use crate::syntax::ast::{Ast, Expression};
-use crate::syntax::lexer::TokenType;
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Expression, ExpressionKind, NamedArg};
+use crate::syntax::lexer::{Token, TokenType};
 
 impl Parser {
-    pub fn call_expr(&mut self) -> Expression {
-        let name = self.tokens[self.cursor].lexeme.clone();
-        self.cursor += 2;
+    /// `name(arg, ...)`
+    pub fn call_expr(&mut self) -> ParseResult<Expression> {
+        let name = self.advance();
+        self.advance();
+        let (params, named, close) = self.call_args(&name.lexeme)?;
 
-        let mut params = Vec::new();
-
-        while let Some(param_token) = self.tokens.get(self.cursor) {
-            match param_token.token_type {
-                TokenType::String => {
-                    params.push(Expression::StringLiteral(param_token.lexeme.clone()));
-                    self.cursor += 1;
-
-                    if let Some(next_token) = self.tokens.get(self.cursor) {
-                        match next_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
-                    } else {
-                        std::process::exit(1)
-                    }
-                }
-                TokenType::Identifier => {
-                    let literal = self.parse_literal(param_token.clone(), Some(self.cursor));
-                    self.cursor += 1;
+        let call_expr = ExpressionKind::CallExpr {
+            name: name.lexeme,
+            params,
+            named,
+        };
+        Ok(Expression::new(call_expr, name.span.to(close.span)))
+    }
 
-                    params.push(literal);
-                    if let Some(after_token) = self.tokens.get(self.cursor) {
-                        match after_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
-                    }
-                }
-                TokenType::RParen => break,
-                TokenType::EOF => break,
+    /// `target.name(arg, ...)` or `target.name`, or either with `?.`
+    pub fn member(&mut self, target: Expression) -> ParseResult<Expression> {
+        let dot = self.advance();
+        let optional = dot.token_type == TokenType::QuestionDot;
+        let name = self.expect(
+            TokenType::Identifier,
+            &format!("a field or method name after '{}'", dot.lexeme),
+        )?;
 
-                _ => {
-                    println!("`{}` is unimplemented!", param_token.lexeme.clone());
-                    unimplemented!()
-                }
-            }
+        if !self.check(TokenType::LParen) {
+            let span = target.span.to(name.span);
+            let field = ExpressionKind::Field {
+                target: Box::new(target),
+                name: name.lexeme,
+                optional,
+            };
+            return Ok(Expression::new(field, span));
         }
-        let temp = params.clone();
-        let nm = format!("{}", &name);
-        let call_expr = Expression::CallExpr { name, params };
 
-        self.reset_cursor();
-        self.nodes.push(Ast::Expression(call_expr));
-        Expression::CallExpr {
-            name: nm,
-            params: temp,
+        self.advance();
+        let (params, named, close) = self.call_args(&name.lexeme)?;
+        if let Some(arg) = named.first() {
+            return Err(self.error(
+                arg.span,
+                &format!("Method '{}' does not take named arguments", name.lexeme),
+            ));
         }
+        let span = target.span.to(close.span);
+        let method_call = ExpressionKind::MethodCall {
+            target: Box::new(target),
+            name: name.lexeme,
+            params,
+            optional,
+        };
+        Ok(Expression::new(method_call, span))
     }
 
-    pub fn call_expr_as_var(&mut self) -> Expression {
-        let name = self.tokens[self.cursor].lexeme.clone();
-        self.cursor += 2;
+    /// Arguments up to and including the closing ')'. Named arguments,
+    /// `name: value`, must follow the positional ones, which may be spread
+    /// with `...items`.
+    fn call_args(&mut self, name: &str) -> ParseResult<(Vec<Expression>, Vec<NamedArg>, Token)> {
         let mut params = Vec::new();
-
-        while let Some(param_token) = self.tokens.get(self.cursor) {
-            match param_token.token_type {
-                TokenType::String => {
-                    params.push(Expression::StringLiteral(param_token.lexeme.clone()));
-                    self.cursor += 1;
-
-                    if let Some(next_token) = self.tokens.get(self.cursor) {
-                        match next_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
-                    } else {
-                        std::process::exit(1)
-                    }
+        let mut named: Vec<NamedArg> = Vec::new();
+        while !self.check(TokenType::RParen) {
+            if self.check(TokenType::Identifier) && self.peek_at(1).token_type == TokenType::Colon {
+                let arg_name = self.advance();
+                self.advance();
+                let value = self.parse_expression()?;
+                let span = arg_name.span.to(value.span);
+                if named.iter().any(|arg| arg.name == arg_name.lexeme) {
+                    return Err(self.error(
+                        span,
+                        &format!(
+                            "Argument '{}' is given twice in call to '{}'",
+                            arg_name.lexeme, name
+                        ),
+                    ));
                 }
-                TokenType::Identifier => {
-                    let literal = self.parse_literal(param_token.clone(), Some(self.cursor));
-                    self.cursor += 1;
+                named.push(NamedArg {
+                    name: arg_name.lexeme,
+                    value,
+                    span,
+                });
+            } else if let Some(arg) = named.last() {
+                return Err(self.error(
+                    self.peek().span,
+                    &format!(
+                        "Positional arguments must come before named ones, found one after '{}'",
+                        arg.name
+                    ),
+                ));
+            } else if self.check(TokenType::Ellipsis) {
+                let ellipsis = self.advance();
+                let items = self.parse_expression()?;
+                let span = ellipsis.span.to(items.span);
+                params.push(Expression::new(
+                    ExpressionKind::Spread(Box::new(items)),
+                    span,
+                ));
+            } else {
+                params.push(self.parse_expression()?);
+            }
 
-                    params.push(literal);
-                    if let Some(after_token) = self.tokens.get(self.cursor) {
-                        match after_token.token_type {
-                            TokenType::Comma => self.cursor += 1,
-                            TokenType::RParen => {
-                                self.cursor += 1;
-                                break;
-                            }
-                            _ => unimplemented!(),
-                        }
-                    }
-                }
-                TokenType::RParen => break,
-                _ => {
-                    println!("`{}` is unimplemented!", param_token.lexeme.clone());
-                    unimplemented!()
-                }
+            if !self.check(TokenType::RParen) {
+                self.expect(
+                    TokenType::Comma,
+                    &format!("',' or ')' in call to '{}'", name),
+                )?;
             }
         }
-        let call_expr = Expression::CallExpr { name, params };
-
-        self.tokens.drain(0..self.cursor); // so uhh... forgot to add this line...
-                                           // took 2 hours to figure out why it wasnt working
-                                           // having fun :)
-
-        self.cursor = 0;
+        let close = self.advance();
 
-        call_expr
+        Ok((params, named, close))
     }
 }
@@ -1,12 +1,23 @@
 mod expr;
 mod stmt;
+mod types;
 
-use super::ast::Expression;
 use super::ast::*;
-use super::lexer::{KeywordMap, Token, TokenType, KEYWORDS};
-use crate::error::{ErrorType::ParseError, VeloError, ERROR_INDICATOR};
+use super::lexer::{Lexer, Token, TokenType};
+use super::span::Span;
+use crate::error::{ErrorType::ParseError, VeloError};
 
-use std::process;
+pub type ParseResult<T> = Result<T, VeloError>;
+
+/// Lexes and parses source text, returning every error found. Tokens are
+/// only parsed when the lexer found nothing wrong.
+pub fn parse_source(source: &str) -> Result<Vec<Ast>, Vec<VeloError>> {
+    let lexed = Lexer::new(source).tokenize();
+    if !lexed.errors.is_empty() {
+        return Err(lexed.errors);
+    }
+    Parser::new(lexed.tokens).parse()
+}
 
 #[derive(Debug)]
 pub struct Parser {
@@ -14,6 +25,10 @@ pub struct Parser {
     pub cursor: usize,
     pub nodes: Vec<Ast>,
     pub errors: Vec<VeloError>,
+    function_depth: usize,
+    value_if_depth: usize,
+    /// Type parameters of the enclosing generic declarations
+    type_params: Vec<String>,
 }
 
 impl Parser {
@@ -23,128 +38,188 @@ impl Parser {
             cursor: 0,
             nodes: Vec::new(),
             errors: Vec::new(),
+            function_depth: 0,
+            value_if_depth: 0,
+            type_params: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Ast>, VeloError> {
-        while !self.tokens.is_empty() {
-            match self.tokens[0].token_type {
-                TokenType::Immut => {
-                    self.variable_assignment(false, None, true, false);
-                }
-                TokenType::If => self.if_statement(),
-                TokenType::Function => self.function_declaration(),
-                TokenType::Identifier => {
-                    match self.tokens[1].token_type {
-                        TokenType::ColonEq => {
-                            self.variable_assignment(false, None, false, false);
-                        }
-                        TokenType::LParen => {
-                            self.call_expr();
-                        }
-                        TokenType::Eq => unimplemented!(), // for not reassignment
-                        _ => unimplemented!(),
-                    }
-                }
-                TokenType::Import => {
-                    self.import_path();
-                }
-                TokenType::Semicolon => {
-                    self.tokens.remove(0);
-                }
-                TokenType::EOF => {
-                    self.nodes.push(Ast::Expression(Expression::Null));
-                    self.tokens.remove(0);
-                }
-                _ => {
-                    println!(
-                        "incomplete\n{:#?}\n{:#?}",
-                        self.tokens[0].token_type,
-                        self.tokens[0].lexeme.clone()
-                    );
-                    process::exit(1)
+    /// Parses the whole token stream. Errors do not stop parsing, the parser
+    /// skips to the next statement so that every error can be reported.
+    pub fn parse(&mut self) -> Result<Vec<Ast>, Vec<VeloError>> {
+        while !self.at_end() {
+            if self.check(TokenType::Semicolon) {
+                self.advance();
+                continue;
+            }
+
+            match self.parse_statement() {
+                Ok(node) => self.nodes.push(node),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
                 }
-            };
+            }
         }
 
-        let mut ast_nodes = Vec::new();
-
-        for node in &self.nodes {
-            ast_nodes.push(node.clone())
+        if self.errors.is_empty() {
+            Ok(self.nodes.clone())
+        } else {
+            Err(self.errors.clone())
         }
-        if self.errors.len() > 0 {
-            for error in self.errors.iter() {
-                println!("{}", error.message);
-                println!("  [filename goes here]:{}\n\n", error.line);
-                println!("TODO: Potential Fixes");
-
-                match error.error_type {
-                    ParseError => println!("This error is found to be of type 'ParseError'"),
-                    _ => unreachable!(),
-                }
+    }
+
+    fn parse_statement(&mut self) -> ParseResult<Ast> {
+        match self.peek().token_type {
+            TokenType::Immut => self.variable_assignment(),
+            TokenType::If => self.if_statement(),
+            TokenType::For => self.for_statement(),
+            TokenType::Function => self.function_declaration(),
+            TokenType::Struct => self.struct_declaration(),
+            TokenType::Type => self.interface_declaration(),
+            TokenType::Impl => self.impl_block(),
+            TokenType::At => self.annotated_declaration(),
+            TokenType::Try => self.try_statement(),
+            TokenType::Throw => self.throw_statement(),
+            TokenType::Return => self.return_statement(),
+            TokenType::Import => self.import_path(),
+            TokenType::Identifier if self.peek_at(1).token_type == TokenType::ColonEq => {
+                self.variable_assignment()
             }
-            process::exit(1);
+            _ => self.expression_statement(),
         }
-
-        Ok(ast_nodes)
     }
 
-    fn parse_literal(&mut self, token: Token, cursor: Option<usize>) -> Expression {
-        match cursor.is_some() {
-            true => self.cursor = cursor.unwrap(),
-            _ => {}
+    /// Any expression followed by `;`. The semicolon may be left off the
+    /// last expression of a block or of the input, which lets the REPL
+    /// take `1 + 2` as is.
+    fn expression_statement(&mut self) -> ParseResult<Ast> {
+        let expr = self.parse_expression()?;
+        if self.check(TokenType::Semicolon) {
+            self.advance();
+        } else if !self.at_end() && !self.check(TokenType::RBrace) {
+            self.expect_semicolon("expression")?;
         }
-        match token.token_type {
-            TokenType::True => Expression::Bool(true),
-            TokenType::False => Expression::Bool(false),
-            TokenType::String => Expression::StringLiteral(token.lexeme.clone()),
-            TokenType::NumericLiteral | TokenType::Identifier | TokenType::LParen => {
-                if self.tokens.get(self.cursor + 1).is_some() {
-                    self.parse_binary()
-                } else {
-                    let message = format!(
-                        "{} \x1b[1mUnexpected EOF when parsing file",
-                        ERROR_INDICATOR
-                    );
-                    self.throw_error(self.tokens[0].line_num, message);
-
-                    Expression::Null
-                }
+
+        Ok(Ast::Expression(expr))
+    }
+
+    /// Parses `{ statements }` and returns the statements and the span of
+    /// the whole block
+    fn parse_block(&mut self, context: &str) -> ParseResult<(Vec<Ast>, Span)> {
+        let open = self.expect(TokenType::LBrace, &format!("'{{' to start {}", context))?;
+        let mut body = Vec::new();
+
+        while !self.check(TokenType::RBrace) {
+            if self.at_end() {
+                return Err(self.error(
+                    open.span,
+                    &format!("Unclosed '{{', expected '}}' to end {}", context),
+                ));
             }
-            _ => {
-                let message = format!(
-                    "{} \x1b[1mCannot assign items of type {:#?} to variables\x1b[0m",
-                    ERROR_INDICATOR, token.token_type
-                );
-                self.throw_error(self.tokens[0].line_num, message);
-                self.cursor = 0;
-
-                Expression::Null
+            if self.check(TokenType::Semicolon) {
+                self.advance();
+                continue;
             }
+            body.push(self.parse_statement()?);
         }
+
+        let close = self.advance();
+        Ok((body, open.span.to(close.span)))
     }
 
-    fn import_path(&mut self) {
-        let mut path = String::new();
-        self.cursor += 1;
-        if let Some(token) = self.tokens.get(self.cursor) {
-            if token.token_type == TokenType::String {
-                self.cursor += 1;
-                path = token.lexeme.clone();
-            }
+    fn import_path(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let path = self.expect(TokenType::String, "a path string after `import`")?;
+        let span = keyword.span.to(path.span);
+        if self.check(TokenType::Semicolon) {
+            self.advance();
         }
-        self.tokens.drain(0..self.cursor);
-        self.cursor = 0;
-        self.nodes.push(Ast::Statement(Statement::Import(path)));
+
+        Ok(Ast::Statement(Statement::new(
+            StatementKind::Import(path.lexeme),
+            span,
+        )))
     }
 
-    fn reset_cursor(&mut self) {
-        self.tokens.drain(0..=self.cursor);
-        self.cursor = 0;
+    fn at_end(&self) -> bool {
+        self.peek().token_type == TokenType::EOF
+    }
+
+    fn peek(&self) -> &Token {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        let index = (self.cursor + offset).min(self.tokens.len() - 1);
+        &self.tokens[index]
+    }
+
+    fn check(&self, token_type: TokenType) -> bool {
+        self.peek().token_type == token_type
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if !self.at_end() {
+            self.cursor += 1;
+        }
+        token
+    }
+
+    fn expect(&mut self, token_type: TokenType, expected: &str) -> ParseResult<Token> {
+        if self.check(token_type) {
+            Ok(self.advance())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn expect_semicolon(&mut self, after: &str) -> ParseResult<Token> {
+        if self.check(TokenType::Semicolon) {
+            Ok(self.advance())
+        } else {
+            let found = self.describe(self.peek());
+            Err(self.error(
+                self.peek().span,
+                &format!("Expected ';' after {}, found {}", after, found),
+            ))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> VeloError {
+        let token = self.peek();
+        let message = format!("Expected {}, found {}", expected, self.describe(token));
+        self.error(token.span, &message)
+    }
+
+    fn describe(&self, token: &Token) -> String {
+        match token.token_type {
+            TokenType::EOF => "end of file".to_string(),
+            TokenType::String => format!("string \"{}\"", token.lexeme),
+            _ => format!("'{}'", token.lexeme),
+        }
+    }
+
+    /// Skips tokens until the start of what is probably the next statement
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        while !self.at_end() {
+            match self.advance().token_type {
+                TokenType::LBrace => depth += 1,
+                TokenType::RBrace => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                TokenType::Semicolon if depth == 0 => return,
+                _ => {}
+            }
+        }
     }
 
-    pub fn throw_error(&mut self, line: usize, message: String) {
-        self.errors
-            .push(VeloError::error(line, &message, ParseError));
+    pub fn error(&self, span: Span, message: &str) -> VeloError {
+        VeloError::error(span, message, ParseError)
     }
 }
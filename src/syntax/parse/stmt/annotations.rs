@@ -0,0 +1,160 @@
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Annotation, Ast, Expression, ExpressionKind, Statement, StatementKind};
+use crate::syntax::lexer::TokenType;
+
+/// The annotations Velo itself understands
+const BUILT_IN: [&str; 3] = ["test", "deprecated", "inline"];
+
+impl Parser {
+    /// `@name(args) ...` and the function, struct or variable declaration
+    /// they are on
+    pub fn annotated_declaration(&mut self) -> ParseResult<Ast> {
+        let annotations = self.parse_annotations()?;
+        let node = match self.peek().token_type {
+            TokenType::Function => self.function_declaration()?,
+            TokenType::Struct => self.struct_declaration()?,
+            TokenType::Immut => self.variable_assignment()?,
+            TokenType::Identifier if self.peek_at(1).token_type == TokenType::ColonEq => {
+                self.variable_assignment()?
+            }
+            _ => {
+                return Err(
+                    self.unexpected("`fun`, `struct` or a variable declaration after annotations")
+                )
+            }
+        };
+
+        let mut stmt = match node {
+            Ast::Statement(stmt) => stmt,
+            Ast::Expression(_) => unreachable!(),
+        };
+        self.check_annotations(&annotations, &stmt, false)?;
+        stmt.annotations = annotations;
+        Ok(Ast::Statement(stmt))
+    }
+
+    /// Any number of `@name` or `@name(arg, ...)`
+    pub fn parse_annotations(&mut self) -> ParseResult<Vec<Annotation>> {
+        let mut annotations = Vec::new();
+        while self.check(TokenType::At) {
+            let at = self.advance();
+            let name = self.expect(TokenType::Identifier, "an annotation name after '@'")?;
+            let mut span = at.span.to(name.span);
+
+            let mut args = Vec::new();
+            if self.check(TokenType::LParen) {
+                self.advance();
+                while !self.check(TokenType::RParen) {
+                    let arg = self.parse_expression()?;
+                    if !is_literal(&arg) {
+                        return Err(self.error(
+                            arg.span,
+                            &format!(
+                                "Arguments of annotation '@{}' must be literal values",
+                                name.lexeme
+                            ),
+                        ));
+                    }
+                    args.push(arg);
+
+                    if !self.check(TokenType::RParen) {
+                        self.expect(
+                            TokenType::Comma,
+                            &format!("',' or ')' in arguments of annotation '@{}'", name.lexeme),
+                        )?;
+                    }
+                }
+                span = span.to(self.advance().span);
+            }
+
+            annotations.push(Annotation {
+                name: name.lexeme,
+                args,
+                span,
+            });
+        }
+
+        Ok(annotations)
+    }
+
+    /// Built-in annotations go only where they mean something, and once
+    pub fn check_annotations(
+        &self,
+        annotations: &[Annotation],
+        stmt: &Statement,
+        method: bool,
+    ) -> ParseResult<()> {
+        for (i, annotation) in annotations.iter().enumerate() {
+            let name = annotation.name.as_str();
+            if !BUILT_IN.contains(&name) {
+                continue;
+            }
+            if annotations[..i].iter().any(|a| a.name == name) {
+                return Err(self.error(
+                    annotation.span,
+                    &format!("Annotation '@{}' is given twice", name),
+                ));
+            }
+
+            let problem = match (name, &stmt.kind) {
+                ("test", StatementKind::Function { .. }) if method => {
+                    Some("cannot be put on a method")
+                }
+                (
+                    "test",
+                    StatementKind::Function {
+                        params,
+                        type_params,
+                        ..
+                    },
+                ) => {
+                    let required = params.iter().any(|p| p.default.is_none() && !p.rest);
+                    if required || !type_params.is_empty() {
+                        Some("can only be put on a function that takes no arguments")
+                    } else if !annotation.args.is_empty() {
+                        Some("does not take arguments")
+                    } else {
+                        None
+                    }
+                }
+                ("deprecated", StatementKind::Function { .. } | StatementKind::Struct { .. }) => {
+                    match annotation.args.as_slice() {
+                        []
+                        | [Expression {
+                            kind: ExpressionKind::StringLiteral(_),
+                            ..
+                        }] => None,
+                        _ => Some("takes nothing or a message string"),
+                    }
+                }
+                ("inline", StatementKind::Function { .. }) if !annotation.args.is_empty() => {
+                    Some("does not take arguments")
+                }
+                ("inline", StatementKind::Function { .. }) => None,
+                ("test" | "inline", _) => Some("can only be put on a function"),
+                _ => Some("can only be put on a function or a struct"),
+            };
+
+            if let Some(problem) = problem {
+                return Err(self.error(annotation.span, &format!("'@{}' {}", name, problem)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_literal(expr: &Expression) -> bool {
+    matches!(
+        expr.kind,
+        ExpressionKind::Short(_)
+            | ExpressionKind::Int(_)
+            | ExpressionKind::Large(_)
+            | ExpressionKind::BigInt(_)
+            | ExpressionKind::Float(_)
+            | ExpressionKind::Decimal(_)
+            | ExpressionKind::Bool(_)
+            | ExpressionKind::StringLiteral(_)
+            | ExpressionKind::Null
+    )
+}
@@ -0,0 +1,27 @@
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Ast, Statement, StatementKind};
+use crate::syntax::lexer::TokenType;
+
+impl Parser {
+    /// `for name in iterable { body }`
+    pub fn for_statement(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let name = self.expect(TokenType::Identifier, "a loop variable name after `for`")?;
+        self.expect(
+            TokenType::In,
+            &format!("`in` after loop variable '{}'", name.lexeme),
+        )?;
+        let iterable = self.parse_expression()?;
+        let (body, body_span) = self.parse_block("for loop body")?;
+
+        let for_loop = StatementKind::For {
+            name: name.lexeme,
+            iterable,
+            body,
+        };
+        Ok(Ast::Statement(Statement::new(
+            for_loop,
+            keyword.span.to(body_span),
+        )))
+    }
+}
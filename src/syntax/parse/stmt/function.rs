@@ -1,254 +1,251 @@
-#![allow(unused)]
-use super::super::Parser;
-use crate::error::ERROR_INDICATOR;
-use crate::syntax::ast::{Ast, Expression, FunctionBody, Statement};
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Ast, Expression, FunctionBody, Param, Statement, StatementKind};
 use crate::syntax::lexer::{Token, TokenType, Type};
+use crate::syntax::span::Span;
 
 impl Parser {
-    pub fn function_declaration(&mut self) {
-        let mut name = self.parse_function_name();
-        let mut params = self.parse_function_params(&name);
-        let mut ret_type = self.parse_function_ret_type(&name);
-
-        let mut stmts = Vec::new();
-        let mut exprs = Vec::new();
-        let mut block = None;
-        // Return type
-
-        // make variable_assignment not drain tokens when parsing for function body
-        // MOST CLEAN CODE OF 2024 (official)
-        if let Some(token) = self.tokens.get(self.cursor - 1) {
-            println!("{}", token.lexeme.clone());
-            if token.token_type == TokenType::LBrace || token.token_type == TokenType::Identifier {
-                let mut brace_count = 1;
-                let mut body_cursor = self.cursor + 1;
-
-                match token.token_type {
-                    TokenType::LBrace => body_cursor = self.cursor,
-                    _ => body_cursor = self.cursor + 1,
-                }
-
-                while let Some(body_token) = self.tokens.get(body_cursor) {
-                    println!("body token {}", body_token.lexeme);
-                    match body_token.token_type {
-                        TokenType::LBrace => brace_count += 1,
-                        TokenType::RBrace => {
-                            brace_count -= 1;
-                            if brace_count == 0 {
-                                self.cursor = body_cursor + 1;
-                                break;
-                            }
-                        }
-                        TokenType::Identifier => match self.tokens[body_cursor + 1].token_type {
-                            TokenType::ColonEq => {
-                                body_cursor += 1;
-                                let var =
-                                    self.variable_assignment(true, Some(body_cursor), false, true);
-                                stmts.push(var.unwrap())
-                            }
-                            _ => unimplemented!(),
-                        },
-
-                        _ => {}
-                    }
-
-                    body_cursor += 1;
-                }
-            } else {
-                let message = format!(
-                    "{} \x1b[1mExpected '{{' to start function body, found '{}'\x1b[0m",
-                    ERROR_INDICATOR,
-                    token.lexeme.clone()
-                );
-                self.throw_error(token.line_num, message);
-
-                self.tokens.clear();
-                return;
-            }
-        } else {
-            let message = format!(
-                "{} \x1b[1mUnexpected end of tokens while parsing function body\x1b[0m",
-                ERROR_INDICATOR,
-            );
-            self.throw_error(0, message);
-
-            return;
-        }
-
-        let body = FunctionBody::new(stmts, exprs, block);
+    /// `fun name<T, ...>(param type, ...) > ret_type { body }`
+    pub fn function_declaration(&mut self) -> ParseResult<Ast> {
+        Ok(Ast::Statement(self.function(false)?))
+    }
 
-        self.reset_cursor();
-        // Avengers! Assemble (please help)
-        let function_assignment = Statement::Function {
+    /// A function, or with `method` set a method of an `impl` block, which
+    /// takes `self` before its other parameters
+    pub fn function(&mut self, method: bool) -> ParseResult<Statement> {
+        let keyword = self.advance();
+        let name = self.parse_function_name()?;
+        let type_params = self.parse_type_params(&format!("function '{}'", name))?;
+
+        // The type parameters are in scope for the rest of the declaration
+        let scope = self.type_params.len();
+        self.type_params.extend(type_params.iter().cloned());
+        let function = self.function_rest(&name, method);
+        self.type_params.truncate(scope);
+        let (params, ret_type, body, body_span) = function?;
+
+        let function_assignment = StatementKind::Function {
             name,
+            type_params,
             params,
             ret_type,
             body,
         };
-        self.nodes.push(Ast::Statement(function_assignment));
+        Ok(Statement::new(
+            function_assignment,
+            keyword.span.to(body_span),
+        ))
+    }
+
+    /// Parameters, return type and body
+    fn function_rest(
+        &mut self,
+        name: &str,
+        method: bool,
+    ) -> ParseResult<(Vec<Param>, Type, FunctionBody, Span)> {
+        let params = self.parse_function_params(name, method)?;
+        let ret_type = self.parse_function_ret_type(name)?;
+
+        // A function declared inside an `if` expression may still return
+        let value_if_depth = std::mem::take(&mut self.value_if_depth);
+        self.function_depth += 1;
+        let block = self.parse_block(&format!("body of function '{}'", name));
+        self.function_depth -= 1;
+        self.value_if_depth = value_if_depth;
+        let (stmts, body_span) = block?;
+
+        Ok((params, ret_type, FunctionBody::new(stmts), body_span))
     }
 
-    fn parse_function_name(&mut self) -> String {
-        let mut name = String::new();
+    /// `return value;` or `return;`
+    pub fn return_statement(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        if self.function_depth == 0 {
+            return Err(self.error(keyword.span, "Cannot use `return` outside of a function"));
+        }
+        if self.value_if_depth > 0 {
+            return Err(self.error(
+                keyword.span,
+                "Cannot use `return` inside an `if` that is used as a value",
+            ));
+        }
 
-        match self.tokens.get(1) {
-            Some(next_token) if next_token.token_type == TokenType::Identifier => {
-                name = next_token.lexeme.clone();
-                self.cursor += 1;
-            }
-            Some(next_token) => {
-                let message = format!(
-                    "{} \x1b[1mCannot declare function with name of type {}",
-                    ERROR_INDICATOR,
-                    TokenType::to_string(next_token.token_type)
-                );
-                self.throw_error(next_token.line_num, message);
-            }
-            None => {
-                let message = format!(
-                    "{} \x1b[1mUnexpected end of input while parsing function declaration\x1b[0m",
-                    ERROR_INDICATOR,
-                );
-                self.throw_error(self.tokens[0].line_num, message);
-            }
+        let value = match self.peek().token_type {
+            TokenType::Semicolon | TokenType::RBrace => None,
+            _ => Some(self.parse_expression()?),
+        };
+        let end = match &value {
+            Some(value) => value.span,
+            None => keyword.span,
+        };
+        if self.check(TokenType::Semicolon) {
+            self.advance();
+        } else if !self.check(TokenType::RBrace) {
+            self.expect_semicolon("return value")?;
         }
 
-        name
+        Ok(Ast::Statement(Statement::new(
+            StatementKind::Return(value),
+            keyword.span.to(end),
+        )))
     }
 
-    fn parse_function_params(&mut self, name: &String) -> Vec<(String, Type)> {
-        let mut params = Vec::new();
-
-        if let Some(token) = self.tokens.get(self.cursor + 1) {
-            if token.token_type == TokenType::LParen {
-                let mut param_cursor = self.cursor + 2;
-                while let Some(param_token) = self.tokens.get(param_cursor) {
-                    if param_token.token_type == TokenType::RParen {
-                        self.cursor = param_cursor + 1;
-                        break;
-                    } else if param_token.token_type == TokenType::Identifier {
-                        // Parsing parameter name
-                        let param_name = param_token.lexeme.clone();
-
-                        if let Some(next_token) = self.tokens.get(param_cursor + 1) {
-                            // Checking for parameter type declaration
-                            if next_token.token_type == TokenType::Identifier {
-                                if let Some(type_token) = self.tokens.get(param_cursor + 1) {
-                                    // Parsing parameter type
-                                    let param_type = Type::from_string(type_token.lexeme.clone());
-                                    params.push((param_name, param_type));
-
-                                    param_cursor += 2; // Move cursor past parameter type
-                                    if let Some(next_next_token) = self.tokens.get(param_cursor) {
-                                        match next_next_token.token_type {
-                                            TokenType::Comma => param_cursor += 1,
-                                            TokenType::RParen => {
-                                                self.cursor = param_cursor + 1;
-                                                break;
-                                            }
-                                            _ => {
-                                                // Handle unexpected token
-                                                let message = format!(
-                                                "{} \x1b[1mUnexpected token '{:#?}' while parsing parameters for function '{}'\x1b[0m",
-                                                ERROR_INDICATOR, next_next_token.token_type, name
-                                            );
-                                                self.throw_error(next_next_token.line_num, message);
-                                                self.tokens.drain(0..param_cursor);
-                                                break;
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    // Handle missing parameter type
-                                    let message = format!(
-                                        "{} \x1b[1mExpected parameter type after '{}'\x1b[0m",
-                                        ERROR_INDICATOR, param_name
-                                    );
-                                    self.throw_error(next_token.line_num, message);
-                                    self.tokens.clear();
-                                    self.cursor += 1;
-                                    break;
-                                }
-                            } else {
-                                let message = format!(
-                                    "{} \x1b[1mExpected type to follow parameter, but found '{}' for function '{}'",
-                                    ERROR_INDICATOR,
-                                    next_token.lexeme.clone(),
-                                    name,
-                                );
-                                self.throw_error(next_token.line_num, message);
-                                self.cursor += 1;
-                                break;
-                            }
-                        }
-                    } else {
-                        // Handle unexpected token for parameter name
-                        let message = format!(
-                        "{} \x1b[1mUnexpected token '{:#?}' while parsing parameters for function '{}'\x1b[0m",
-                        ERROR_INDICATOR, TokenType::to_string(param_token.token_type), name
-                    );
-                        self.throw_error(param_token.line_num, message);
-                        self.tokens.clear();
-                        break;
-                    }
-                }
-            } else {
-                // Handle missing '(' after function name
-                let message = format!(
-                    "{} \x1b[1mExpected '(' after function name, found '{:#?}' for function '{}'\x1b[0m",
-                    ERROR_INDICATOR, 
-                    TokenType::to_string(token.token_type),
-                    name
-                );
-                self.throw_error(token.line_num, message);
-                self.tokens.clear();
-            }
+    pub fn parse_function_name(&mut self) -> ParseResult<String> {
+        if self.check(TokenType::Identifier) {
+            Ok(self.advance().lexeme)
         } else {
-            // Handle unexpected end of tokens while parsing function parameters
-            let message = format!(
-                "{} \x1b[1mUnexpected end of tokens while parsing parameters for function '{}'\x1b[0m",
-                ERROR_INDICATOR, name
-            );
-            self.throw_error(0, message);
+            let found = self.describe(self.peek());
+            Err(self.error(
+                self.peek().span,
+                &format!("Cannot declare function with name {}", found),
+            ))
         }
-        params
     }
 
-    fn parse_function_ret_type(&mut self, name: &String) -> Type {
-        let mut ret_type = Type::Void;
+    /// Parameters with a default value must come after those without one,
+    /// and a rest parameter must come last. A method's parameters start
+    /// with `self`, which is left out of the result.
+    pub fn parse_function_params(&mut self, name: &str, method: bool) -> ParseResult<Vec<Param>> {
+        self.expect(
+            TokenType::LParen,
+            &format!("'(' after function name '{}'", name),
+        )?;
+        if method {
+            let receiver = self.peek().clone();
+            if receiver.token_type != TokenType::Identifier || receiver.lexeme != "self" {
+                return Err(self.error(
+                    receiver.span,
+                    &format!(
+                        "Expected 'self' as the first parameter of method '{}', found {}",
+                        name,
+                        self.describe(&receiver)
+                    ),
+                ));
+            }
+            self.advance();
+            if !self.check(TokenType::RParen) {
+                self.expect(
+                    TokenType::Comma,
+                    &format!("',' or ')' in parameters of method '{}'", name),
+                )?;
+            }
+        }
 
-        if let Some(token) = self.tokens.get(self.cursor) {
-            if token.token_type == TokenType::Gt {
-                if let Some(next_token) = self.tokens.get(self.cursor + 1) {
-                    ret_type = Type::from_string(next_token.lexeme.clone());
-                    self.cursor += 2;
+        let mut params: Vec<Param> = Vec::new();
+        while !self.check(TokenType::RParen) {
+            if let Some(previous) = params.last().filter(|p| p.rest) {
+                return Err(self.error(
+                    self.peek().span,
+                    &format!(
+                        "Rest parameter '{}' must be the last parameter of function '{}'",
+                        previous.name, name
+                    ),
+                ));
+            }
+
+            let rest = self.check(TokenType::Ellipsis);
+            if rest {
+                self.advance();
+            }
+            let param = self.expect(
+                TokenType::Identifier,
+                &format!("a parameter name for function '{}'", name),
+            )?;
+            if param.lexeme == "self" {
+                return Err(self.error(
+                    param.span,
+                    "'self' can only be the first parameter of a method",
+                ));
+            }
+            if params.iter().any(|p| p.name == param.lexeme) {
+                return Err(self.error(
+                    param.span,
+                    &format!(
+                        "Parameter '{}' is declared twice for function '{}'",
+                        param.lexeme, name
+                    ),
+                ));
+            }
+
+            if rest {
+                // The element type is optional, `...parts` takes anything
+                let element = if self.check(TokenType::Identifier) {
+                    self.parse_type("a type for the rest parameter")?
                 } else {
-                    let message = format!(
-                        "{} \x1b[1mExpected return type after '>' for function '{}'\x1b[0m",
-                        ERROR_INDICATOR, name
-                    );
-                    self.throw_error(token.line_num, message);
-                    self.tokens.clear();
+                    Type::Any
+                };
+                if self.check(TokenType::Eq) {
+                    return Err(self.error(
+                        self.peek().span,
+                        &format!(
+                            "Rest parameter '{}' cannot have a default value",
+                            param.lexeme
+                        ),
+                    ));
                 }
+                params.push(Param {
+                    name: param.lexeme,
+                    param_type: Type::Array(Box::new(element)),
+                    default: None,
+                    rest,
+                });
             } else {
-                match token.token_type {
-                    TokenType::LBrace => {
-                        self.cursor += 1;
-                    }
-                    _ => {
-                        let message = format!("{} \x1b[1mExpected either '>' or '{}' when parsing function '{}', but found {}", ERROR_INDICATOR, "{",  name, token.lexeme.clone());
-                        self.throw_error(token.line_num, message);
-                        self.tokens.clear();
-                    }
-                }
+                let param_type =
+                    self.parse_type(&format!("a type to follow parameter '{}'", param.lexeme))?;
+                let default = self.parse_param_default(&param, &params)?;
+                params.push(Param {
+                    name: param.lexeme,
+                    param_type,
+                    default,
+                    rest,
+                });
             }
+
+            if !self.check(TokenType::RParen) {
+                self.expect(
+                    TokenType::Comma,
+                    &format!("',' or ')' in parameters of function '{}'", name),
+                )?;
+            }
+        }
+        self.advance();
+
+        Ok(params)
+    }
+
+    /// `= value` after the type of parameter `param`
+    fn parse_param_default(
+        &mut self,
+        param: &Token,
+        params: &[Param],
+    ) -> ParseResult<Option<Expression>> {
+        if self.check(TokenType::Eq) {
+            self.advance();
+            return Ok(Some(self.parse_expression()?));
+        }
+
+        match params.last().filter(|p| p.default.is_some()) {
+            Some(previous) => Err(self.error(
+                param.span,
+                &format!(
+                    "Parameter '{}' needs a default value, it follows '{}' which has one",
+                    param.lexeme, previous.name
+                ),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn parse_function_ret_type(&mut self, name: &str) -> ParseResult<Type> {
+        if self.check(TokenType::Gt) {
+            self.advance();
+            self.parse_type(&format!("a return type after '>' for function '{}'", name))
+        } else if self.check(TokenType::LBrace) {
+            Ok(Type::Void)
         } else {
-            let message = format!(
-                "{} \x1b[1mUnexpected end of tokens while parsing return type for function '{}'\x1b[0m",
-                ERROR_INDICATOR, name
-            );
-            self.throw_error(0, message);
+            Err(self.unexpected(&format!(
+                "either '>' or '{{' when parsing function '{}'",
+                name
+            )))
         }
-        ret_type
     }
 }
@@ -1,44 +1,115 @@
-use super::super::Parser;
-use crate::syntax::ast::{Ast, Statement};
-use crate::syntax::lexer::{Token, TokenType};
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Ast, Expression, ExpressionKind, Statement, StatementKind};
+use crate::syntax::lexer::TokenType;
+use crate::syntax::span::Span;
 
 impl Parser {
-    pub fn if_statement(&mut self) {
-        let (condition_tokens, body_tokens) = self.collect_if_tokens();
-        self.parse_if_statement(condition_tokens, body_tokens);
-        self.reset_cursor();
+    /// `if condition { body } elif condition { body } else { body }`
+    pub fn if_statement(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let condition = self.parse_expression()?;
+        let (body, body_span) = self.parse_block("if statement body")?;
+
+        let (else_body, end) = match self.peek().token_type {
+            TokenType::ElseIf => {
+                let elif = self.if_statement()?;
+                let end = elif.span();
+                (Some(vec![elif]), end)
+            }
+            TokenType::Else => {
+                self.advance();
+                let (else_body, else_span) = self.parse_block("else body")?;
+                (Some(else_body), else_span)
+            }
+            _ => (None, body_span),
+        };
+
+        let statement = StatementKind::IfStatement {
+            condition,
+            body,
+            else_body,
+        };
+        Ok(Ast::Statement(Statement::new(
+            statement,
+            keyword.span.to(end),
+        )))
     }
 
-    fn collect_if_tokens(&mut self) -> (Vec<Token>, Vec<Token>) {
-        let mut condition_tokens = Vec::new();
-        self.cursor += 1;
+    /// An `if` in value position, e.g. `label := if n == 1 { "item" } else { "items" };`
+    pub fn if_expression(&mut self) -> ParseResult<Expression> {
+        let keyword = self.advance();
+        let condition = self.parse_expression()?;
 
-        while self.tokens[self.cursor].token_type != TokenType::LBrace {
-            condition_tokens.push(self.tokens[self.cursor].clone());
-            self.cursor += 1;
-        }
+        self.value_if_depth += 1;
+        let branches = self.if_expression_branches();
+        self.value_if_depth -= 1;
+        let (body, body_span, else_body, else_span) = branches?;
 
-        // {
-        self.cursor += 1;
+        let body = self.value_branch(body, body_span)?;
+        let else_body = self.value_branch(else_body, else_span)?;
 
-        let mut body_tokens = Vec::new();
-        while self.tokens[self.cursor].token_type != TokenType::RBrace {
-            body_tokens.push(self.tokens[self.cursor].clone());
-            self.cursor += 1;
-        }
+        let kind = ExpressionKind::If {
+            condition: Box::new(condition),
+            body,
+            else_body,
+        };
+        Ok(Expression::new(kind, keyword.span.to(else_span)))
+    }
+
+    fn if_expression_branches(&mut self) -> ParseResult<(Vec<Ast>, Span, Vec<Ast>, Span)> {
+        let (body, body_span) = self.parse_block("if body")?;
 
-        self.cursor += 1;
+        let (else_body, else_span) = match self.peek().token_type {
+            TokenType::ElseIf => {
+                let elif = self.if_expression()?;
+                let span = elif.span;
+                (vec![Ast::Expression(elif)], span)
+            }
+            TokenType::Else => {
+                self.advance();
+                self.parse_block("else body")?
+            }
+            _ => {
+                return Err(self.error(body_span, "An `if` used as a value needs an `else` branch"))
+            }
+        };
 
-        (condition_tokens, body_tokens)
+        Ok((body, body_span, else_body, else_span))
     }
 
-    fn parse_if_statement(&mut self, condition_tokens: Vec<Token>, body_tokens: Vec<Token>) {
-        let condition = Self::parse_expression(condition_tokens);
-        let body = Parser::new(body_tokens)
-            .parse()
-            .expect("Error parsing if statement");
+    /// Checks that a branch ends in a value. A trailing `if` statement
+    /// becomes the value when all of its own branches have one, so `if`s
+    /// nest without parentheses.
+    fn value_branch(&self, mut block: Vec<Ast>, span: Span) -> ParseResult<Vec<Ast>> {
+        match block.pop() {
+            Some(Ast::Expression(expr)) => block.push(Ast::Expression(expr)),
+            Some(Ast::Statement(Statement {
+                kind:
+                    StatementKind::IfStatement {
+                        condition,
+                        body,
+                        else_body: Some(else_body),
+                    },
+                span: if_span,
+                ..
+            })) => {
+                let body = self.value_branch(body, if_span)?;
+                let else_body = self.value_branch(else_body, if_span)?;
+                let kind = ExpressionKind::If {
+                    condition: Box::new(condition),
+                    body,
+                    else_body,
+                };
+                block.push(Ast::Expression(Expression::new(kind, if_span)));
+            }
+            _ => {
+                return Err(self.error(
+                    span,
+                    "This branch has no value, an `if` used as a value must end each branch with an expression",
+                ))
+            }
+        }
 
-        let statement = Statement::IfStatement { condition, body };
-        self.nodes.push(Ast::Statement(statement));
+        Ok(block)
     }
 }
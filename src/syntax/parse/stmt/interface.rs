@@ -0,0 +1,158 @@
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Ast, MethodSignature, Statement, StatementKind};
+use crate::syntax::lexer::{TokenType, Type};
+
+impl Parser {
+    /// `type Name interface { fun method(self, param type, ...) > ret_type ... }`
+    pub fn interface_declaration(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let name = self.expect(TokenType::Identifier, "a name after `type`")?;
+        let kind = self.peek().clone();
+        if kind.token_type != TokenType::Identifier || kind.lexeme != "interface" {
+            return Err(self.error(
+                kind.span,
+                &format!(
+                    "Expected 'interface' after `type {}`, found {}, only interface types can be declared",
+                    name.lexeme,
+                    self.describe(&kind)
+                ),
+            ));
+        }
+        self.advance();
+        self.expect(
+            TokenType::LBrace,
+            &format!("'{{' to start the methods of interface '{}'", name.lexeme),
+        )?;
+
+        let mut methods: Vec<MethodSignature> = Vec::new();
+        while !self.check(TokenType::RBrace) {
+            if self.check(TokenType::Semicolon) {
+                self.advance();
+                continue;
+            }
+            let method = self.method_signature(&name.lexeme)?;
+            if methods.iter().any(|m| m.name == method.name) {
+                return Err(self.error(
+                    method.span,
+                    &format!(
+                        "Method '{}' is declared twice for interface '{}'",
+                        method.name, name.lexeme
+                    ),
+                ));
+            }
+            methods.push(method);
+        }
+        let close = self.advance();
+
+        let interface = StatementKind::Interface {
+            name: name.lexeme,
+            methods,
+        };
+        Ok(Ast::Statement(Statement::new(
+            interface,
+            keyword.span.to(close.span),
+        )))
+    }
+
+    /// `fun method(self, param type, ...) > ret_type`, without a body
+    fn method_signature(&mut self, interface: &str) -> ParseResult<MethodSignature> {
+        let keyword = self.expect(
+            TokenType::Function,
+            &format!("'fun' or '}}' in methods of interface '{}'", interface),
+        )?;
+        let name = self.parse_function_name()?;
+        let params = self.parse_function_params(&name, true)?;
+        if let Some(param) = params.iter().find(|p| p.default.is_some()) {
+            return Err(self.error(
+                param.default.as_ref().unwrap().span,
+                &format!(
+                    "Parameter '{}' of interface method '{}' cannot have a default value",
+                    param.name, name
+                ),
+            ));
+        }
+
+        let mut span = keyword.span.to(self.tokens[self.cursor - 1].span);
+        let ret_type = if self.check(TokenType::Gt) {
+            self.advance();
+            let ret_type =
+                self.parse_type(&format!("a return type after '>' for method '{}'", name))?;
+            span = span.to(self.tokens[self.cursor - 1].span);
+            ret_type
+        } else {
+            Type::Void
+        };
+
+        Ok(MethodSignature {
+            name,
+            params,
+            ret_type,
+            span,
+        })
+    }
+
+    /// `impl Interface for Struct { fun method(self, ...) { body } ... }`
+    pub fn impl_block(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let interface = self.expect(TokenType::Identifier, "an interface name after `impl`")?;
+        self.expect(
+            TokenType::For,
+            &format!("'for' after `impl {}`", interface.lexeme),
+        )?;
+        let target = self.expect(
+            TokenType::Identifier,
+            &format!("a struct name after `impl {} for`", interface.lexeme),
+        )?;
+        self.expect(
+            TokenType::LBrace,
+            &format!(
+                "'{{' to start `impl {} for {}`",
+                interface.lexeme, target.lexeme
+            ),
+        )?;
+
+        let mut methods: Vec<Statement> = Vec::new();
+        while !self.check(TokenType::RBrace) {
+            if self.check(TokenType::Semicolon) {
+                self.advance();
+                continue;
+            }
+            let annotations = self.parse_annotations()?;
+            if !self.check(TokenType::Function) {
+                return Err(self.unexpected(&format!(
+                    "'fun' or '}}' in `impl {} for {}`",
+                    interface.lexeme, target.lexeme
+                )));
+            }
+            let mut method = self.function(true)?;
+            self.check_annotations(&annotations, &method, true)?;
+            method.annotations = annotations;
+            if let StatementKind::Function { name, .. } = &method.kind {
+                let twice = methods.iter().any(
+                    |m| matches!(&m.kind, StatementKind::Function { name: n, .. } if n == name),
+                );
+                if twice {
+                    return Err(self.error(
+                        method.span,
+                        &format!(
+                            "Method '{}' is declared twice in `impl {} for {}`",
+                            name, interface.lexeme, target.lexeme
+                        ),
+                    ));
+                }
+            }
+            methods.push(method);
+        }
+        let close = self.advance();
+
+        let impl_block = StatementKind::Impl {
+            interface: interface.lexeme,
+            target: target.lexeme,
+            methods,
+        };
+        Ok(Ast::Statement(Statement::new(
+            impl_block,
+            keyword.span.to(close.span),
+        )))
+    }
+}
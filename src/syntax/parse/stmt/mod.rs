@@ -1,3 +1,8 @@
+pub mod annotations;
+pub mod for_loop;
 pub mod function;
 pub mod if_statement;
+pub mod interface;
+pub mod structs;
+pub mod try_catch;
 pub mod variable;
@@ -0,0 +1,67 @@
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Ast, Statement, StatementKind};
+use crate::syntax::lexer::{TokenType, Type};
+
+impl Parser {
+    /// `struct Name<T, ...> { field type, ... }`
+    pub fn struct_declaration(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let name = self.expect(TokenType::Identifier, "a name after `struct`")?;
+        let type_params = self.parse_type_params(&format!("struct '{}'", name.lexeme))?;
+
+        let scope = self.type_params.len();
+        self.type_params.extend(type_params.iter().cloned());
+        let fields = self.parse_struct_fields(&name.lexeme);
+        self.type_params.truncate(scope);
+        let fields = fields?;
+        let close = self.advance();
+
+        let struct_declaration = StatementKind::Struct {
+            name: name.lexeme,
+            type_params,
+            fields,
+        };
+        Ok(Ast::Statement(Statement::new(
+            struct_declaration,
+            keyword.span.to(close.span),
+        )))
+    }
+
+    /// Fields up to, but not including, the closing '}'
+    fn parse_struct_fields(&mut self, name: &str) -> ParseResult<Vec<(String, Type)>> {
+        self.expect(
+            TokenType::LBrace,
+            &format!("'{{' to start the fields of struct '{}'", name),
+        )?;
+
+        let mut fields: Vec<(String, Type)> = Vec::new();
+        while !self.check(TokenType::RBrace) {
+            let field = self.expect(
+                TokenType::Identifier,
+                &format!("a field name for struct '{}'", name),
+            )?;
+            if fields.iter().any(|(f, _)| *f == field.lexeme) {
+                return Err(self.error(
+                    field.span,
+                    &format!(
+                        "Field '{}' is declared twice for struct '{}'",
+                        field.lexeme, name
+                    ),
+                ));
+            }
+
+            let field_type =
+                self.parse_type(&format!("a type to follow field '{}'", field.lexeme))?;
+            fields.push((field.lexeme, field_type));
+
+            if !self.check(TokenType::RBrace) {
+                self.expect(
+                    TokenType::Comma,
+                    &format!("',' or '}}' in fields of struct '{}'", name),
+                )?;
+            }
+        }
+
+        Ok(fields)
+    }
+}
@@ -0,0 +1,66 @@
+use super::super::{ParseResult, Parser};
+use crate::syntax::ast::{Ast, CatchClause, Statement, StatementKind};
+use crate::syntax::lexer::TokenType;
+
+impl Parser {
+    /// `try { body } catch name { ... } finally { ... }`, where either the
+    /// `catch` or the `finally` may be left off, and the name too
+    pub fn try_statement(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let (body, mut span) = self.parse_block("try block")?;
+
+        let catch = if self.check(TokenType::Catch) {
+            self.advance();
+            let name = if self.check(TokenType::Identifier) {
+                Some(self.advance().lexeme)
+            } else {
+                None
+            };
+            let (body, catch_span) = self.parse_block("catch block")?;
+            span = catch_span;
+            Some(CatchClause { name, body })
+        } else {
+            None
+        };
+
+        let finally = if self.check(TokenType::Finally) {
+            self.advance();
+            let (body, finally_span) = self.parse_block("finally block")?;
+            span = finally_span;
+            Some(body)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.unexpected("`catch` or `finally` after try block"));
+        }
+
+        let try_statement = StatementKind::Try {
+            body,
+            catch,
+            finally,
+        };
+        Ok(Ast::Statement(Statement::new(
+            try_statement,
+            keyword.span.to(span),
+        )))
+    }
+
+    /// `throw value;`
+    pub fn throw_statement(&mut self) -> ParseResult<Ast> {
+        let keyword = self.advance();
+        let value = self.parse_expression()?;
+        if self.check(TokenType::Semicolon) {
+            self.advance();
+        } else if !self.check(TokenType::RBrace) {
+            self.expect_semicolon("thrown value")?;
+        }
+
+        let span = keyword.span.to(value.span);
+        Ok(Ast::Statement(Statement::new(
+            StatementKind::Throw(value),
+            span,
+        )))
+    }
+}
@@ -0,0 +1,150 @@
+use super::{ParseResult, Parser};
+use crate::syntax::lexer::{Token, TokenType, Type};
+use crate::syntax::span::Span;
+
+impl Parser {
+    /// A type, followed by `?` if it may also be null. A name that is not
+    /// built in or a type parameter in scope refers to a struct, which is
+    /// looked up when the program runs.
+    pub fn parse_type(&mut self, expected: &str) -> ParseResult<Type> {
+        let token = self.expect(TokenType::Identifier, expected)?;
+        let args = if self.check(TokenType::Lt) {
+            self.parse_type_args(&token.lexeme)?
+        } else {
+            Vec::new()
+        };
+
+        let t = if self.type_params.contains(&token.lexeme) {
+            if !args.is_empty() {
+                return Err(self.error(
+                    token.span,
+                    &format!(
+                        "Type parameter '{}' does not take type arguments",
+                        token.lexeme
+                    ),
+                ));
+            }
+            Type::Param(token.lexeme.clone())
+        } else {
+            match Type::from_string(&token.lexeme) {
+                Some(Type::Array(_)) if args.len() == 1 => {
+                    Type::Array(Box::new(args.into_iter().next().unwrap()))
+                }
+                Some(Type::Array(_)) if args.len() > 1 => {
+                    return Err(self.error(
+                        token.span,
+                        &format!(
+                            "Type 'array' takes 1 type argument but {} were given",
+                            args.len()
+                        ),
+                    ))
+                }
+                Some(t) if args.is_empty() || matches!(t, Type::Array(_)) => t,
+                Some(_) => {
+                    return Err(self.error(
+                        token.span,
+                        &format!("Type '{}' does not take type arguments", token.lexeme),
+                    ))
+                }
+                None => Type::Named {
+                    name: token.lexeme.clone(),
+                    args,
+                },
+            }
+        };
+
+        if self.check(TokenType::Question) {
+            let question = self.advance();
+            if t == Type::Void {
+                return Err(self.error(
+                    token.span.to(question.span),
+                    "`void` cannot be optional, it never has a value",
+                ));
+            }
+            return Ok(Type::Optional(Box::new(t)));
+        }
+
+        Ok(t)
+    }
+
+    /// `<type, ...>` after the name of a generic type
+    fn parse_type_args(&mut self, name: &str) -> ParseResult<Vec<Type>> {
+        self.advance();
+        let mut args = Vec::new();
+        loop {
+            args.push(self.parse_type(&format!("a type argument for '{}'", name))?);
+            if self.check(TokenType::Comma) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.expect_closing_angle(&format!("'>' to close the type arguments of '{}'", name))?;
+
+        Ok(args)
+    }
+
+    /// `<T, ...>` declaring the type parameters of a function or struct,
+    /// nothing if there is no `<`
+    pub fn parse_type_params(&mut self, owner: &str) -> ParseResult<Vec<String>> {
+        if !self.check(TokenType::Lt) {
+            return Ok(Vec::new());
+        }
+        self.advance();
+
+        let mut params: Vec<String> = Vec::new();
+        loop {
+            let param = self.expect(
+                TokenType::Identifier,
+                &format!("a type parameter name for {}", owner),
+            )?;
+            if Type::from_string(&param.lexeme).is_some() {
+                return Err(self.error(
+                    param.span,
+                    &format!(
+                        "Type parameter '{}' has the name of a built-in type",
+                        param.lexeme
+                    ),
+                ));
+            }
+            if params.contains(&param.lexeme) {
+                return Err(self.error(
+                    param.span,
+                    &format!(
+                        "Type parameter '{}' is declared twice for {}",
+                        param.lexeme, owner
+                    ),
+                ));
+            }
+            params.push(param.lexeme);
+
+            if self.check(TokenType::Comma) {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        self.expect_closing_angle(&format!("'>' to close the type parameters of {}", owner))?;
+
+        Ok(params)
+    }
+
+    /// Expects a `>`, splitting a `>>` in two so that nested type
+    /// arguments such as `array<array<int>>` can close together
+    fn expect_closing_angle(&mut self, expected: &str) -> ParseResult<Token> {
+        if self.check(TokenType::ShiftRight) {
+            let token = &mut self.tokens[self.cursor];
+            let span = token.span;
+            token.token_type = TokenType::Gt;
+            token.lexeme = ">".to_string();
+            token.span = Span::new(span.start + 1, span.end, span.line, span.column + 1);
+            return Ok(Token {
+                token_type: TokenType::Gt,
+                lexeme: ">".to_string(),
+                span: Span::new(span.start, span.start + 1, span.line, span.column),
+            });
+        }
+
+        self.expect(TokenType::Gt, expected)
+    }
+}
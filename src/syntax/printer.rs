@@ -0,0 +1,970 @@
+//! Turns syntax trees back into canonically formatted Velo source.
+//!
+//! Parsing the output of `print_program` gives back the same tree, apart
+//! from spans. The exception is `Short` literals, which only come from the
+//! optimizer and which the parser reads back as `Int`.
+//!
+//! `format_source` also keeps the comments, placing each one by its span:
+//! before the statement, member or field that follows it, or at the end of
+//! its block. A comment that followed code on its line still does.
+
+use super::ast::{
+    Annotation, Ast, ConditionType, Expression, ExpressionKind, MethodSignature, Param, Statement,
+    StatementKind,
+};
+use super::lexer::{Comment, Lexer, Token, TokenType, Type};
+use super::parse::Parser;
+use super::span::Span;
+use crate::error::VeloError;
+use crate::utils::format_float;
+
+const INDENT: &str = "  ";
+
+pub fn print_program(nodes: &[Ast]) -> String {
+    let mut printer = Printer::new();
+    printer.block(nodes);
+    printer.out
+}
+
+/// Formats source text with its comments, as `velo fmt` does
+pub fn format_source(source: &str) -> Result<String, Vec<VeloError>> {
+    let mut lexed = Lexer::new(source).tokenize();
+    if !lexed.errors.is_empty() {
+        return Err(lexed.errors);
+    }
+    let nodes = Parser::new(lexed.tokens.clone()).parse()?;
+
+    let mut printer = Printer::new();
+    lexed.comments.reverse();
+    printer.comments = lexed.comments;
+    printer.tokens = lexed.tokens;
+    printer.block(&nodes);
+    printer.comments_before(usize::MAX);
+    Ok(printer.out)
+}
+
+/// `fun name(self, param type, ...) > ret_type`, as written in an interface
+pub fn method_signature(method: &MethodSignature) -> String {
+    let mut printer = Printer::new();
+    printer.out.push_str("fun ");
+    printer.out.push_str(&method.name);
+    printer.params(&method.params, true);
+    printer.ret_type(&method.ret_type);
+    printer.out
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+    /// Comments not yet written, the next one last
+    comments: Vec<Comment>,
+    /// The source's tokens, to find the `}`s and struct fields that have
+    /// no spans of their own
+    tokens: Vec<Token>,
+    /// Offset in the source up to which everything has been written
+    position: usize,
+}
+
+impl Printer {
+    fn new() -> Self {
+        Printer {
+            out: String::new(),
+            indent: 0,
+            comments: Vec::new(),
+            tokens: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Writes the comments that come before `offset`, each on a line of
+    /// its own unless it followed code
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self.comments.pop_if(|c| c.span.start < offset) {
+            self.comment(&comment);
+        }
+    }
+
+    /// Like `comments_before`, but stops at the first comment on a line of
+    /// its own
+    fn trailing_comments_before(&mut self, offset: usize) {
+        while let Some(comment) = self
+            .comments
+            .pop_if(|c| !c.own_line && c.span.start < offset)
+        {
+            self.comment(&comment);
+        }
+    }
+
+    fn comment(&mut self, comment: &Comment) {
+        if !comment.own_line && self.out.ends_with('\n') {
+            self.out.pop();
+            self.out.push(' ');
+        } else {
+            self.line_start();
+        }
+        self.out.push_str(&comment.text);
+        self.out.push('\n');
+    }
+
+    fn has_comment_before(&self, offset: Option<usize>) -> bool {
+        match (self.comments.last(), offset) {
+            (Some(comment), Some(offset)) => comment.span.start < offset,
+            _ => false,
+        }
+    }
+
+    /// Starts a statement, member or field at `span`
+    fn member(&mut self, span: Span) {
+        self.comments_before(span.start);
+        self.position = self.position.max(span.start);
+    }
+
+    /// Offset of the `}` that ends the block being written
+    fn next_close(&self) -> Option<usize> {
+        self.tokens
+            .iter()
+            .find(|t| t.token_type == TokenType::RBrace && t.span.start >= self.position)
+            .map(|t| t.span.start)
+    }
+
+    /// Writes the comments left in the block, then its `}`
+    fn close(&mut self) {
+        if let Some(close) = self.next_close() {
+            self.comments_before(close);
+            self.position = close + 1;
+        }
+        self.indent -= 1;
+        self.line_start();
+        self.out.push('}');
+    }
+
+    /// Offsets of the fields of the struct whose `{` comes next
+    fn field_starts(&self) -> Vec<usize> {
+        let tokens = self
+            .tokens
+            .iter()
+            .skip_while(|t| t.span.start < self.position || t.token_type != TokenType::LBrace)
+            .skip(1);
+        let mut starts = Vec::new();
+        let mut depth = 0;
+        let mut field_start = true;
+        for token in tokens {
+            match token.token_type {
+                TokenType::RBrace => break,
+                TokenType::Identifier if field_start => starts.push(token.span.start),
+                TokenType::Lt => depth += 1,
+                TokenType::Gt => depth -= 1,
+                TokenType::ShiftRight => depth -= 2,
+                _ => {}
+            }
+            field_start = token.token_type == TokenType::Comma && depth == 0;
+        }
+        starts
+    }
+
+    fn line_start(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Statements go one per line, with declarations set apart by blank
+    /// lines
+    fn block(&mut self, nodes: &[Ast]) {
+        for (i, node) in nodes.iter().enumerate() {
+            if i > 0 && (is_declaration(node) || is_declaration(&nodes[i - 1])) {
+                // A comment after the previous statement stays on its line,
+                // the rest go with the declaration
+                self.trailing_comments_before(node.span().start);
+                self.out.push('\n');
+            }
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Ast) {
+        let span = node.span();
+        self.member(span);
+        match node {
+            Ast::Expression(expr) => {
+                self.line_start();
+                // At the start of a statement `if` would be read back as an
+                // if statement
+                let parens = starts_with_if(expr);
+                self.expression(expr, if parens { u8::MAX } else { 0 });
+                self.out.push_str(";\n");
+            }
+            Ast::Statement(stmt) => self.statement(stmt),
+        }
+        self.position = self.position.max(span.end);
+    }
+
+    /// Writes `{ ... }` without a trailing newline
+    fn body(&mut self, nodes: &[Ast]) {
+        if nodes.is_empty() && !self.has_comment_before(self.next_close()) {
+            self.out.push_str("{}");
+            self.skip_close();
+            return;
+        }
+
+        self.out.push_str("{\n");
+        self.indent += 1;
+        self.block(nodes);
+        self.close();
+    }
+
+    /// Moves past the `}` of a block written on one line
+    fn skip_close(&mut self) {
+        if let Some(close) = self.next_close() {
+            self.position = close + 1;
+        }
+    }
+
+    fn statement(&mut self, stmt: &Statement) {
+        self.annotations(&stmt.annotations);
+        self.line_start();
+        match &stmt.kind {
+            StatementKind::VariableAssignment {
+                constant,
+                name,
+                value,
+            } => {
+                if *constant {
+                    self.out.push_str("immut ");
+                }
+                self.out.push_str(name);
+                self.out.push_str(" := ");
+                self.expression(value, 0);
+                self.out.push_str(";\n");
+            }
+            StatementKind::IfStatement {
+                condition,
+                body,
+                else_body,
+            } => {
+                self.if_statement(condition, body, else_body);
+                self.out.push('\n');
+            }
+            StatementKind::For {
+                name,
+                iterable,
+                body,
+            } => {
+                self.out.push_str("for ");
+                self.out.push_str(name);
+                self.out.push_str(" in ");
+                self.expression(iterable, 0);
+                self.out.push(' ');
+                self.body(body);
+                self.out.push('\n');
+            }
+            StatementKind::Function { .. } => {
+                self.function(stmt, false);
+                self.out.push('\n');
+            }
+            StatementKind::Struct {
+                name,
+                type_params,
+                fields,
+            } => {
+                self.out.push_str("struct ");
+                self.out.push_str(name);
+                self.type_params(type_params);
+                if fields.is_empty() && !self.has_comment_before(self.next_close()) {
+                    self.out.push_str(" {}\n");
+                    self.skip_close();
+                    return;
+                }
+                self.out.push_str(" {\n");
+                self.indent += 1;
+                let starts = self.field_starts();
+                for (i, (field, field_type)) in fields.iter().enumerate() {
+                    if let Some(start) = starts.get(i) {
+                        self.comments_before(*start);
+                    }
+                    self.line_start();
+                    self.out.push_str(field);
+                    self.out.push(' ');
+                    self.out.push_str(&type_name(field_type));
+                    self.out.push_str(",\n");
+                }
+                self.close();
+                self.out.push('\n');
+            }
+            StatementKind::Interface { name, methods } => {
+                self.out.push_str("type ");
+                self.out.push_str(name);
+                if methods.is_empty() && !self.has_comment_before(self.next_close()) {
+                    self.out.push_str(" interface {}\n");
+                    self.skip_close();
+                    return;
+                }
+                self.out.push_str(" interface {\n");
+                self.indent += 1;
+                for method in methods {
+                    self.member(method.span);
+                    self.line_start();
+                    self.out.push_str("fun ");
+                    self.out.push_str(&method.name);
+                    self.params(&method.params, true);
+                    self.ret_type(&method.ret_type);
+                    self.out.push('\n');
+                    self.position = self.position.max(method.span.end);
+                }
+                self.close();
+                self.out.push('\n');
+            }
+            StatementKind::Impl {
+                interface,
+                target,
+                methods,
+            } => {
+                self.out.push_str("impl ");
+                self.out.push_str(interface);
+                self.out.push_str(" for ");
+                self.out.push_str(target);
+                if methods.is_empty() && !self.has_comment_before(self.next_close()) {
+                    self.out.push_str(" {}\n");
+                    self.skip_close();
+                    return;
+                }
+                self.out.push_str(" {\n");
+                self.indent += 1;
+                for (i, method) in methods.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push('\n');
+                    }
+                    self.member(method.span);
+                    self.annotations(&method.annotations);
+                    self.line_start();
+                    self.function(method, true);
+                    self.out.push('\n');
+                    self.position = self.position.max(method.span.end);
+                }
+                self.close();
+                self.out.push('\n');
+            }
+            StatementKind::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.out.push_str("try ");
+                self.body(body);
+                if let Some(catch) = catch {
+                    self.out.push_str(" catch ");
+                    if let Some(name) = &catch.name {
+                        self.out.push_str(name);
+                        self.out.push(' ');
+                    }
+                    self.body(&catch.body);
+                }
+                if let Some(finally) = finally {
+                    self.out.push_str(" finally ");
+                    self.body(finally);
+                }
+                self.out.push('\n');
+            }
+            StatementKind::Throw(value) => {
+                self.out.push_str("throw ");
+                self.expression(value, 0);
+                self.out.push_str(";\n");
+            }
+            StatementKind::Import(path) => {
+                self.out.push_str("import ");
+                self.string(path);
+                self.out.push_str(";\n");
+            }
+            StatementKind::Return(value) => {
+                self.out.push_str("return");
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.expression(value, 0);
+                }
+                self.out.push_str(";\n");
+            }
+            StatementKind::ExprStmt(expr) => {
+                self.expression(expr, 0);
+                self.out.push_str(";\n");
+            }
+        }
+    }
+
+    fn type_params(&mut self, type_params: &[String]) {
+        if !type_params.is_empty() {
+            self.out.push('<');
+            self.out.push_str(&type_params.join(", "));
+            self.out.push('>');
+        }
+    }
+
+    fn if_statement(&mut self, condition: &Expression, body: &[Ast], else_body: &Option<Vec<Ast>>) {
+        self.out.push_str("if ");
+        self.expression(condition, 0);
+        self.out.push(' ');
+        self.body(body);
+
+        match else_body.as_deref() {
+            Some(
+                [Ast::Statement(Statement {
+                    kind:
+                        StatementKind::IfStatement {
+                            condition,
+                            body,
+                            else_body,
+                        },
+                    ..
+                })],
+            ) => {
+                self.out.push_str(" el");
+                self.if_statement(condition, body, else_body);
+            }
+            Some(else_body) => {
+                self.out.push_str(" else ");
+                self.body(else_body);
+            }
+            None => {}
+        }
+    }
+
+    /// Short branches stay on one line: `if a { 1 } else { 2 }`
+    fn if_expression(&mut self, condition: &Expression, body: &[Ast], else_body: &[Ast]) {
+        self.out.push_str("if ");
+        self.expression(condition, 0);
+        self.out.push(' ');
+        self.value_block(body);
+
+        match else_body {
+            [Ast::Expression(Expression {
+                kind:
+                    ExpressionKind::If {
+                        condition,
+                        body,
+                        else_body,
+                    },
+                ..
+            })] => {
+                self.out.push_str(" el");
+                self.if_expression(condition, body, else_body);
+            }
+            _ => {
+                self.out.push_str(" else ");
+                self.value_block(else_body);
+            }
+        }
+    }
+
+    fn value_block(&mut self, nodes: &[Ast]) {
+        match nodes {
+            [Ast::Expression(expr)] if !self.has_comment_before(self.next_close()) => {
+                self.out.push_str("{ ");
+                self.expression(expr, 0);
+                self.out.push_str(" }");
+                self.skip_close();
+            }
+            _ => {
+                // The trailing value is written without a semicolon
+                self.out.push_str("{\n");
+                self.indent += 1;
+                let (last, rest) = nodes.split_last().expect("if branches are never empty");
+                self.block(rest);
+                self.member(last.span());
+                self.line_start();
+                if let Ast::Expression(expr) = last {
+                    self.expression(expr, 0);
+                }
+                self.out.push('\n');
+                self.position = self.position.max(last.span().end);
+                self.close();
+            }
+        }
+    }
+
+    /// Writes `expr`, parenthesized when it binds looser than `min_precedence`
+    fn expression(&mut self, expr: &Expression, min_precedence: u8) {
+        let precedence = precedence(expr);
+        let parens = precedence < min_precedence;
+        if parens {
+            self.out.push('(');
+        }
+
+        match &expr.kind {
+            ExpressionKind::Short(val) => self.out.push_str(&val.to_string()),
+            ExpressionKind::Int(val) => self.out.push_str(&val.to_string()),
+            ExpressionKind::Large(val) => self.out.push_str(&val.to_string()),
+            ExpressionKind::BigInt(digits) => self.out.push_str(&format!("{}n", digits)),
+            ExpressionKind::Float(val) => self.out.push_str(&format_float(*val)),
+            ExpressionKind::Decimal(digits) => self.out.push_str(&format!("{}d", digits)),
+            ExpressionKind::Bool(val) => self.out.push_str(&val.to_string()),
+            ExpressionKind::StringLiteral(str) => self.string(str),
+            ExpressionKind::Identifier(name) => self.out.push_str(name),
+            ExpressionKind::Null => self.out.push_str("null"),
+            ExpressionKind::Array(items) => {
+                self.out.push('[');
+                self.list(items);
+                self.out.push(']');
+            }
+            ExpressionKind::CallExpr {
+                name,
+                params,
+                named,
+            } => {
+                self.out.push_str(name);
+                self.out.push('(');
+                self.list(params);
+                for (i, arg) in named.iter().enumerate() {
+                    if i > 0 || !params.is_empty() {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push_str(&arg.name);
+                    self.out.push_str(": ");
+                    self.expression(&arg.value, 0);
+                }
+                self.out.push(')');
+            }
+            ExpressionKind::MethodCall {
+                target,
+                name,
+                params,
+                optional,
+            } => {
+                self.expression(target, precedence);
+                self.out.push_str(if *optional { "?." } else { "." });
+                self.out.push_str(name);
+                self.out.push('(');
+                self.list(params);
+                self.out.push(')');
+            }
+            ExpressionKind::Field {
+                target,
+                name,
+                optional,
+            } => {
+                self.expression(target, precedence);
+                self.out.push_str(if *optional { "?." } else { "." });
+                self.out.push_str(name);
+            }
+            ExpressionKind::Index {
+                target,
+                index,
+                optional,
+            } => {
+                self.expression(target, precedence);
+                self.out.push_str(if *optional { "?.[" } else { "[" });
+                self.expression(index, 0);
+                self.out.push(']');
+            }
+            ExpressionKind::Spread(items) => {
+                self.out.push_str("...");
+                self.expression(items, 0);
+            }
+            ExpressionKind::Propagate(value) => {
+                self.expression(value, precedence);
+                self.out.push('?');
+            }
+            ExpressionKind::Coalesce { lhs, rhs } => {
+                // `??` associates to the right
+                self.expression(lhs, precedence + 1);
+                self.out.push_str(" ?? ");
+                self.expression(rhs, precedence);
+            }
+            ExpressionKind::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => {
+                if step.is_some() {
+                    self.out.push('(');
+                }
+                if let Some(start) = start {
+                    self.expression(start, 1);
+                }
+                self.out.push_str(if *inclusive { "..=" } else { ".." });
+                if let Some(end) = end {
+                    self.expression(end, 1);
+                }
+                if let Some(step) = step {
+                    self.out.push_str(").step(");
+                    self.expression(step, 0);
+                    self.out.push(')');
+                }
+            }
+            ExpressionKind::BinaryOp { lhs, op, rhs } => {
+                // Operators associate to the left, so only the right hand
+                // side needs parentheses at equal precedence
+                self.expression(lhs, precedence);
+                self.out.push(' ');
+                self.out.push_str(operator(op));
+                self.out.push(' ');
+                self.expression(rhs, precedence + 1);
+            }
+            ExpressionKind::If {
+                condition,
+                body,
+                else_body,
+            } => self.if_expression(condition, body, else_body),
+            ExpressionKind::Conditional { lhs, op, rhs } => {
+                self.expression(lhs, precedence);
+                self.out.push(' ');
+                self.out.push_str(comparison(op));
+                self.out.push(' ');
+                self.expression(rhs, precedence + 1);
+            }
+        }
+
+        if parens {
+            self.out.push(')');
+        }
+    }
+
+    /// One line for each annotation
+    fn annotations(&mut self, annotations: &[Annotation]) {
+        for annotation in annotations {
+            self.line_start();
+            self.out.push('@');
+            self.out.push_str(&annotation.name);
+            if !annotation.args.is_empty() {
+                self.out.push('(');
+                self.list(&annotation.args);
+                self.out.push(')');
+            }
+            self.out.push('\n');
+        }
+    }
+
+    /// Writes a function, or a method of an `impl` block, without a
+    /// trailing newline
+    fn function(&mut self, function: &Statement, method: bool) {
+        let (name, type_params, params, body, ret_type) = match &function.kind {
+            StatementKind::Function {
+                name,
+                type_params,
+                params,
+                body,
+                ret_type,
+            } => (name, type_params, params, body, ret_type),
+            _ => unreachable!(),
+        };
+        self.out.push_str("fun ");
+        self.out.push_str(name);
+        self.type_params(type_params);
+        self.params(params, method);
+        self.ret_type(ret_type);
+        self.out.push(' ');
+        self.body(body.stmts());
+    }
+
+    /// `(param type, ...)`, starting with `self` for a method
+    fn params(&mut self, params: &[Param], method: bool) {
+        self.out.push('(');
+        if method {
+            self.out.push_str("self");
+        }
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 || method {
+                self.out.push_str(", ");
+            }
+            if param.rest {
+                self.out.push_str("...");
+                self.out.push_str(&param.name);
+                match &param.param_type {
+                    Type::Array(element) if **element != Type::Any => {
+                        self.out.push(' ');
+                        self.out.push_str(&type_name(element));
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            self.out.push_str(&param.name);
+            self.out.push(' ');
+            self.out.push_str(&type_name(&param.param_type));
+            if let Some(default) = &param.default {
+                self.out.push_str(" = ");
+                self.expression(default, 0);
+            }
+        }
+        self.out.push(')');
+    }
+
+    fn ret_type(&mut self, ret_type: &Type) {
+        if *ret_type != Type::Void {
+            self.out.push_str(" > ");
+            self.out.push_str(&type_name(ret_type));
+        }
+    }
+
+    fn list(&mut self, items: &[Expression]) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expression(item, 0);
+        }
+    }
+
+    fn string(&mut self, str: &str) {
+        self.out.push('"');
+        for c in str.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\t' => self.out.push_str("\\t"),
+                _ => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+fn is_declaration(node: &Ast) -> bool {
+    matches!(
+        node,
+        Ast::Statement(Statement {
+            kind: StatementKind::Function { .. }
+                | StatementKind::Struct { .. }
+                | StatementKind::Interface { .. }
+                | StatementKind::Impl { .. },
+            ..
+        })
+    )
+}
+
+/// Whether the leftmost token of `expr` is `if`
+fn starts_with_if(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::If { .. } => true,
+        ExpressionKind::BinaryOp { lhs, .. }
+        | ExpressionKind::Conditional { lhs, .. }
+        | ExpressionKind::Coalesce { lhs, .. } => starts_with_if(lhs),
+        ExpressionKind::MethodCall { target, .. }
+        | ExpressionKind::Field { target, .. }
+        | ExpressionKind::Index { target, .. }
+        | ExpressionKind::Propagate(target) => starts_with_if(target),
+        ExpressionKind::Range {
+            start: Some(start),
+            step: None,
+            ..
+        } => starts_with_if(start),
+        _ => false,
+    }
+}
+
+/// Mirrors the precedence levels used by the parser, atoms bind tightest
+fn precedence(expr: &Expression) -> u8 {
+    match &expr.kind {
+        ExpressionKind::Range { step: None, .. } => 0,
+        ExpressionKind::Coalesce { .. } => 1,
+        ExpressionKind::Conditional { op, .. } if op.is_ordering() => 3,
+        ExpressionKind::Conditional { .. } => 2,
+        ExpressionKind::BinaryOp { op, .. } => match op {
+            TokenType::BitwiseOr => 4,
+            TokenType::BitwiseXor => 5,
+            TokenType::BitwiseAnd => 6,
+            TokenType::ShiftLeft | TokenType::ShiftRight => 7,
+            TokenType::Add | TokenType::Sub => 8,
+            _ => 9,
+        },
+        _ => 10,
+    }
+}
+
+/// How a comparison is written
+pub fn comparison(op: &ConditionType) -> &'static str {
+    match op {
+        ConditionType::Equal => "==",
+        ConditionType::NotEqual => "!=",
+        ConditionType::Less => "<",
+        ConditionType::LessEqual => "<=",
+        ConditionType::Greater => ">",
+        ConditionType::GreaterEqual => ">=",
+        ConditionType::Unary => unreachable!(),
+    }
+}
+
+/// How a binary operator is written
+pub fn operator(op: &TokenType) -> &'static str {
+    match op {
+        TokenType::Add => "+",
+        TokenType::Sub => "-",
+        TokenType::Mul => "*",
+        TokenType::Div => "/",
+        TokenType::Mod => "%",
+        TokenType::BitwiseAnd => "&",
+        TokenType::BitwiseOr => "|",
+        TokenType::BitwiseXor => "^",
+        TokenType::ShiftLeft => "<<",
+        TokenType::ShiftRight => ">>",
+        _ => unreachable!(),
+    }
+}
+
+pub fn type_name(t: &Type) -> String {
+    match t {
+        Type::Bool => "bool".to_string(),
+        Type::Int => "int".to_string(),
+        Type::Short => "short".to_string(),
+        Type::Large => "large".to_string(),
+        Type::BigInt => "bigint".to_string(),
+        Type::Float => "float".to_string(),
+        Type::Decimal => "decimal".to_string(),
+        Type::String => "string".to_string(),
+        Type::Array(element) if **element == Type::Any => "array".to_string(),
+        Type::Array(element) => format!("array<{}>", type_name(element)),
+        Type::Tuple(_) => "tuple".to_string(),
+        Type::Error => "error".to_string(),
+        Type::Result => "result".to_string(),
+        Type::Optional(t) => format!("{}?", type_name(t)),
+        Type::Any => "any".to_string(),
+        Type::Param(name) => name.clone(),
+        Type::Named { name, args } if args.is_empty() => name.clone(),
+        Type::Named { name, args } => {
+            let args: Vec<String> = args.iter().map(type_name).collect();
+            format!("{}<{}>", name, args.join(", "))
+        }
+        Type::Void => "void".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_source, print_program};
+    use crate::syntax::ast::visit::{walk_expression_mut, walk_statement_mut, VisitorMut};
+    use crate::syntax::ast::{Ast, Expression, ExpressionKind, Statement, StatementKind};
+    use crate::syntax::parse::parse_source;
+    use crate::syntax::span::Span;
+
+    /// Resets every span, the one thing printing does not keep
+    struct ClearSpans;
+
+    impl VisitorMut for ClearSpans {
+        fn visit_statement_mut(&mut self, stmt: &mut Statement) {
+            stmt.span = Span::default();
+            for annotation in &mut stmt.annotations {
+                annotation.span = Span::default();
+                for arg in &mut annotation.args {
+                    self.visit_expression_mut(arg);
+                }
+            }
+            if let StatementKind::Interface { methods, .. } = &mut stmt.kind {
+                for method in methods {
+                    method.span = Span::default();
+                }
+            }
+            walk_statement_mut(self, stmt);
+        }
+
+        fn visit_expression_mut(&mut self, expr: &mut Expression) {
+            expr.span = Span::default();
+            if let ExpressionKind::CallExpr { named, .. } = &mut expr.kind {
+                for arg in named {
+                    arg.span = Span::default();
+                }
+            }
+            walk_expression_mut(self, expr);
+        }
+    }
+
+    fn parse(source: &str) -> Vec<Ast> {
+        let mut nodes = parse_source(source).expect("the source parses");
+        ClearSpans.visit_block_mut(&mut nodes);
+        nodes
+    }
+
+    const PROGRAM: &str = r#"
+import "lib.velo";
+@deprecated("use area") @meta(1, "two", true)
+struct Box<T> { value T, items array<array<int>>, label string? }
+type Shape interface { fun area(self) > float fun scale(self, by float) }
+impl Shape for Box {
+  @inline fun area(self) > float { return 1.5; }
+  fun scale(self, by float = 1.0) {}
+}
+fun sum<T>(first T, ...rest int) > int {
+  total := 0;
+  for x in rest { print(x); }
+  return total;
+}
+fun main() {
+  immut big := 10n; d := 1.50d;
+  xs := [1, 2, 3][1..]; ys := xs[..=2];
+  n := ((1 + 2) * 3 - 4 / 2 % 5);
+  bits := 1 << 2 | 3 & (4 ^ 5 >> 1);
+  ok := n != 4 == (n <= -2);
+  s := "a\tb \"c\" ${n}";
+  v := box?.value ?? null; w := xs?.[0];
+  r := parse(s)?; m := xs.len(); o := box?.area();
+  sum(1, ...xs, by: 2);
+  k := if n < 1 { "a" } elif n >= 2 { "b" } else { t := 1; "c" };
+  if ok { print(k); } elif n > 1 { print(n); } else { throw "no"; }
+  try { risky(); } catch e { print(e); } finally { print("done"); }
+  (if ok { 1 } else { 2 }).len();
+  return;
+}
+"#;
+
+    #[test]
+    fn printing_round_trips() {
+        let nodes = parse(PROGRAM);
+        let printed = print_program(&nodes);
+        assert_eq!(parse(&printed), nodes, "printed as\n{}", printed);
+        assert_eq!(print_program(&parse(&printed)), printed);
+    }
+
+    #[test]
+    fn formatting_keeps_comments() {
+        let source = "\
+// velo: allow(unused_variable)
+import \"lib.velo\"; // first
+// A point
+struct Point {
+  // across
+  x int, // right
+  y int,
+  // no z
+}
+fun main() { // entry
+  x := if true {
+    // one
+    1
+  } else { 2 };
+  if x == 1 {
+    // nothing yet
+  }
+  print(x); }
+// the end
+";
+        let formatted = format_source(source).expect("the source parses");
+        assert_eq!(
+            formatted,
+            "\
+// velo: allow(unused_variable)
+import \"lib.velo\"; // first
+
+// A point
+struct Point {
+  // across
+  x int, // right
+  y int,
+  // no z
+}
+
+fun main() { // entry
+  x := if true {
+    // one
+    1
+  } else { 2 };
+  if x == 1 {
+    // nothing yet
+  }
+  print(x);
+}
+// the end
+"
+        );
+        assert_eq!(format_source(&formatted).unwrap(), formatted);
+        assert_eq!(parse(&formatted), parse(source));
+    }
+}
@@ -1,59 +1,157 @@
+use crate::error::VeloError;
 use crate::runtime::environment::Environment;
-use crate::syntax::ast::Expression;
-use std::path::PathBuf;
+use crate::syntax::span::Span;
+use std::path::{Component, Path, PathBuf};
 
 #[allow(deprecated)]
 pub fn expand_tilde(path: &str) -> PathBuf {
     if path.starts_with('~') {
-        if let Some((home, rest)) = std::env::home_dir().and_then(|h| Some((h, &path[1..]))) {
+        if let Some((home, rest)) = std::env::home_dir().map(|h| (h, &path[1..])) {
             return [home.to_str().unwrap(), rest].iter().collect();
         }
     }
     path.into()
 }
 
-pub fn interpolate_string(input: &str, env: &Environment) -> String {
+/// Path of `to` relative to `from`. Both paths are expected to be absolute
+/// (e.g. canonicalized), otherwise `to` is returned unchanged.
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    if !from.is_absolute() || !to.is_absolute() {
+        return to.to_path_buf();
+    }
+
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut result = PathBuf::new();
+    for _ in common..from.len() {
+        result.push("..");
+    }
+    for component in &to[common..] {
+        result.push(component);
+    }
+
+    if result.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        result
+    }
+}
+
+pub fn interpolate_string(
+    input: &str,
+    env: &mut Environment,
+    span: Span,
+) -> Result<String, VeloError> {
     let mut result = String::new();
     let mut src: Vec<char> = input.chars().collect();
 
     while !src.is_empty() {
         match src[0] {
-            '$' => {
-                if src[1] == '{' {
-                    src.remove(0);
-                    src.remove(0);
-                    let mut var_name = String::new();
-                    while src[0] != '}' {
-                        var_name.push(src.remove(0))
-                    }
-                    src.remove(0);
-
-                    if let Some(var) = env.variables.get(&var_name) {
-                        match var {
-                            Expression::StringLiteral(str) => result.push_str(str),
-                            Expression::Bool(val) => result.push_str(&format!("{}", val)),
-                            Expression::BinaryOp {
-                                lhs: _,
-                                op: _,
-                                rhs: _,
-                            } => result.push_str(&format!("{:#?}", var)),
-                            Expression::Conditional { lhs, op, rhs } => {
-                                result.push_str(&format!("{:#?}", var))
-                            }
-                            Expression::Float(val) => result.push_str(&format!("{:#?}", val)),
-                            _ => todo!(),
-                        }
-                    } else {
-                        eprintln!("Cannot locate variable `{}`", var_name);
-                        std::process::exit(1)
-                    }
+            '$' if src.get(1) == Some(&'{') => {
+                src.remove(0);
+                src.remove(0);
+                let mut var_name = String::new();
+                while !src.is_empty() && src[0] != '}' {
+                    var_name.push(src.remove(0))
+                }
+                if src.is_empty() {
+                    return Err(env.throw_error(
+                        span,
+                        format!("Unclosed interpolation `${{{}` in string", var_name),
+                    ));
+                }
+                src.remove(0);
+
+                if let Some(var) = env.variable(&var_name) {
+                    result.push_str(&var.to_string());
                 } else {
-                    result.push(src.remove(0))
+                    return Err(
+                        env.throw_error(span, format!("Cannot locate variable `{}`", var_name))
+                    );
                 }
             }
             _ => result.push(src.remove(0)),
         }
     }
 
-    result
+    Ok(result)
+}
+
+/// A float as Velo writes it, always with a `.` so that it reads back as
+/// a float rather than an integer
+pub fn format_float(value: f64) -> String {
+    let text = value.to_string();
+    if value.is_finite() && !text.contains('.') {
+        format!("{}.0", text)
+    } else {
+        text
+    }
+}
+
+/// The variable names a string interpolates with `${name}`
+pub fn interpolated_names(input: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        rest = &rest[start + 2..];
+        match rest.find('}') {
+            Some(end) => {
+                names.push(rest[..end].to_string());
+                rest = &rest[end + 1..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+/// How many insertions, deletions, substitutions and swaps of neighbouring
+/// characters turn `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The candidates close enough to `name` to be what was meant, closest
+/// first, at most three
+pub fn similar_names<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, &str)> = candidates
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+    close.sort();
+    close.dedup();
+    close.into_iter().take(3).map(|(_, name)| name).collect()
+}
+
+/// `word` with its first letter upper case, to start a sentence with
+pub fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
 }